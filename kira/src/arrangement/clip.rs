@@ -1,7 +1,7 @@
 use basedrop::Owned;
 
 use crate::{
-	sound::{handle::SoundHandle, Sound, SoundId},
+	sound::{handle::SoundHandle, ResamplingQuality, Sound, SoundId},
 	static_container::index_map::StaticIndexMap,
 	util::inverse_lerp,
 	util::lerp,
@@ -72,6 +72,8 @@ impl SoundClip {
 	pub(crate) fn get_frame_at_position(
 		&self,
 		position: f64,
+		step: f64,
+		quality: Option<ResamplingQuality>,
 		sounds: &StaticIndexMap<SoundId, Owned<Sound>>,
 	) -> Frame {
 		if let Some(sound) = sounds.get(&self.sound_id) {
@@ -80,11 +82,18 @@ impl SoundClip {
 			if relative_time < 0.0 || relative_time > 1.0 {
 				Frame::from_mono(0.0)
 			} else {
-				sound.get_frame_at_position(lerp(
-					self.sound_time_range.0,
-					self.sound_time_range.1,
-					relative_time,
-				))
+				let playback_rate = (self.sound_time_range.1 - self.sound_time_range.0)
+					/ (self.clip_time_range.1 - self.clip_time_range.0);
+				sound.get_resampled_frame(
+					lerp(
+						self.sound_time_range.0,
+						self.sound_time_range.1,
+						relative_time,
+					),
+					None,
+					step * playback_rate,
+					quality,
+				)
 			}
 		} else {
 			Frame::from_mono(0.0)
@@ -12,10 +12,12 @@ use crate::{
 	mixer::TrackIndex,
 };
 
+use basedrop::Owned;
+
 use super::{Arrangement, ArrangementId};
 
 /// Allows you to control an arrangement.
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct ArrangementHandle {
 	id: ArrangementId,
 	duration: f64,
@@ -23,10 +25,15 @@ pub struct ArrangementHandle {
 	semantic_duration: Option<f64>,
 	default_loop_start: Option<f64>,
 	command_producer: CommandProducer,
+	resource_collector_handle: basedrop::Handle,
 }
 
 impl ArrangementHandle {
-	pub(crate) fn new(arrangement: &Arrangement, command_producer: CommandProducer) -> Self {
+	pub(crate) fn new(
+		arrangement: &Arrangement,
+		command_producer: CommandProducer,
+		resource_collector_handle: basedrop::Handle,
+	) -> Self {
 		Self {
 			id: arrangement.id(),
 			duration: arrangement.duration(),
@@ -34,6 +41,7 @@ impl ArrangementHandle {
 			semantic_duration: arrangement.semantic_duration(),
 			default_loop_start: arrangement.default_loop_start(),
 			command_producer,
+			resource_collector_handle,
 		}
 	}
 
@@ -73,7 +81,15 @@ impl ArrangementHandle {
 			self.id.into(),
 			self.duration,
 			None,
-			settings.into_internal(self.duration, self.default_loop_start, self.default_track),
+			None,
+			// arrangements are meant to loop seamlessly,
+			// so they don't crossfade by default
+			settings.into_internal(
+				self.duration,
+				self.default_loop_start,
+				0.0,
+				self.default_track,
+			),
 		);
 		let handle = InstanceHandle::new(
 			id,
@@ -81,6 +97,7 @@ impl ArrangementHandle {
 			instance.public_position(),
 			self.command_producer.clone(),
 		);
+		let instance = Owned::new(&self.resource_collector_handle, Some(instance));
 		self.command_producer
 			.push(InstanceCommand::Play(id, instance).into())?;
 		Ok(handle)
@@ -104,3 +121,20 @@ impl ArrangementHandle {
 			.push(InstanceCommand::StopInstancesOf(self.id.into(), settings).into())
 	}
 }
+
+impl std::fmt::Debug for ArrangementHandle {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		#[derive(Debug)]
+		struct ResourceCollectorHandle;
+
+		f.debug_struct("ArrangementHandle")
+			.field("id", &self.id)
+			.field("duration", &self.duration)
+			.field("default_track", &self.default_track)
+			.field("semantic_duration", &self.semantic_duration)
+			.field("default_loop_start", &self.default_loop_start)
+			.field("command_producer", &self.command_producer)
+			.field("resource_collector_handle", &ResourceCollectorHandle)
+			.finish()
+	}
+}
@@ -137,8 +137,9 @@ pub use settings::{ArrangementSettings, LoopArrangementSettings};
 
 use crate::{
 	group::{groups::Groups, GroupId, GroupSet},
+	instance::VoiceStealingPolicy,
 	mixer::TrackIndex,
-	sound::{handle::SoundHandle, Sound, SoundId},
+	sound::{handle::SoundHandle, ResamplingQuality, Sound, SoundId},
 	static_container::index_map::StaticIndexMap,
 	Frame,
 };
@@ -155,6 +156,8 @@ pub struct Arrangement {
 	duration: f64,
 	default_track: TrackIndex,
 	cooldown: Option<f64>,
+	max_instances: Option<usize>,
+	voice_stealing_policy: VoiceStealingPolicy,
 	semantic_duration: Option<f64>,
 	default_loop_start: Option<f64>,
 	groups: GroupSet,
@@ -170,6 +173,8 @@ impl Arrangement {
 			duration: 0.0,
 			default_track: settings.default_track,
 			cooldown: settings.cooldown,
+			max_instances: settings.max_instances,
+			voice_stealing_policy: settings.voice_stealing_policy,
 			semantic_duration: settings.semantic_duration,
 			default_loop_start: settings.default_loop_start,
 			groups: settings.groups,
@@ -191,6 +196,8 @@ impl Arrangement {
 			id: settings.id,
 			default_track: settings.default_track,
 			cooldown: settings.cooldown,
+			max_instances: settings.max_instances,
+			voice_stealing_policy: settings.voice_stealing_policy,
 			semantic_duration: settings.semantic_duration,
 			default_loop_start: Some(duration),
 			groups: settings.groups,
@@ -224,6 +231,8 @@ impl Arrangement {
 			id: settings.id,
 			default_track: settings.default_track,
 			cooldown: settings.cooldown,
+			max_instances: settings.max_instances,
+			voice_stealing_policy: settings.voice_stealing_policy,
 			semantic_duration: settings.semantic_duration,
 			default_loop_start: Some(intro_duration + loop_duration),
 			groups: settings.groups,
@@ -283,15 +292,25 @@ impl Arrangement {
 	pub(crate) fn get_frame_at_position(
 		&self,
 		position: f64,
+		step: f64,
+		quality: Option<ResamplingQuality>,
 		sounds: &StaticIndexMap<SoundId, Owned<Sound>>,
 	) -> Frame {
 		let mut frame = Frame::from_mono(0.0);
 		for clip in &self.clips {
-			frame += clip.get_frame_at_position(position, sounds);
+			frame += clip.get_frame_at_position(position, step, quality, sounds);
 		}
 		frame
 	}
 
+	/// Returns the maximum number of instances of this arrangement
+	/// that can be playing at a time and what to do when a new
+	/// instance is started at that limit, if there is a limit.
+	pub(crate) fn instance_limit(&self) -> Option<(usize, VoiceStealingPolicy)> {
+		self.max_instances
+			.map(|max_instances| (max_instances, self.voice_stealing_policy))
+	}
+
 	/// Starts the cooldown timer for the arrangement.
 	pub(crate) fn start_cooldown(&mut self) {
 		if let Some(cooldown) = self.cooldown {
@@ -1,4 +1,4 @@
-use crate::{group::GroupSet, mixer::TrackIndex};
+use crate::{group::GroupSet, instance::VoiceStealingPolicy, mixer::TrackIndex};
 
 use super::ArrangementId;
 
@@ -22,6 +22,12 @@ pub struct ArrangementSettings {
 	/// is played multiple times at the exact same point in time,
 	/// resulting in the arrangement being louder than normal.
 	pub cooldown: Option<f64>,
+	/// The maximum number of instances of this arrangement that can
+	/// be playing at a time, if any.
+	pub max_instances: Option<usize>,
+	/// What to do when a new instance of this arrangement is started
+	/// while the maximum number of instances are already playing.
+	pub voice_stealing_policy: VoiceStealingPolicy,
 	/// How long the arrangement is musically.
 	///
 	/// For example, a recording of a 2-bar drum fill
@@ -71,6 +77,24 @@ impl ArrangementSettings {
 		}
 	}
 
+	/// Sets the maximum number of instances of this arrangement
+	/// that can be playing at a time.
+	pub fn max_instances(self, max_instances: usize) -> Self {
+		Self {
+			max_instances: Some(max_instances),
+			..self
+		}
+	}
+
+	/// Sets what to do when a new instance of this arrangement is started
+	/// while the maximum number of instances are already playing.
+	pub fn voice_stealing_policy(self, voice_stealing_policy: VoiceStealingPolicy) -> Self {
+		Self {
+			voice_stealing_policy,
+			..self
+		}
+	}
+
 	/// Sets the semantic duration of the arrangement.
 	pub fn semantic_duration(self, semantic_duration: f64) -> Self {
 		Self {
@@ -102,6 +126,8 @@ impl Default for ArrangementSettings {
 			id: None,
 			default_track: TrackIndex::Main,
 			cooldown: Some(0.0001),
+			max_instances: None,
+			voice_stealing_policy: VoiceStealingPolicy::default(),
 			semantic_duration: None,
 			default_loop_start: None,
 			groups: GroupSet::new(),
@@ -129,6 +155,12 @@ pub struct LoopArrangementSettings {
 	/// is played multiple times at the exact same point in time,
 	/// resulting in the arrangement being louder than normal.
 	pub cooldown: Option<f64>,
+	/// The maximum number of instances of this arrangement that can
+	/// be playing at a time, if any.
+	pub max_instances: Option<usize>,
+	/// What to do when a new instance of this arrangement is started
+	/// while the maximum number of instances are already playing.
+	pub voice_stealing_policy: VoiceStealingPolicy,
 	/// How long the arrangement is musically.
 	///
 	/// For example, a recording of a 2-bar drum fill
@@ -174,6 +206,24 @@ impl LoopArrangementSettings {
 		}
 	}
 
+	/// Sets the maximum number of instances of this arrangement
+	/// that can be playing at a time.
+	pub fn max_instances(self, max_instances: usize) -> Self {
+		Self {
+			max_instances: Some(max_instances),
+			..self
+		}
+	}
+
+	/// Sets what to do when a new instance of this arrangement is started
+	/// while the maximum number of instances are already playing.
+	pub fn voice_stealing_policy(self, voice_stealing_policy: VoiceStealingPolicy) -> Self {
+		Self {
+			voice_stealing_policy,
+			..self
+		}
+	}
+
 	/// Sets the semantic duration of the arrangement.
 	pub fn semantic_duration(self, semantic_duration: f64) -> Self {
 		Self {
@@ -197,6 +247,8 @@ impl Default for LoopArrangementSettings {
 			id: None,
 			default_track: TrackIndex::Main,
 			cooldown: Some(0.0001),
+			max_instances: None,
+			voice_stealing_policy: VoiceStealingPolicy::default(),
 			semantic_duration: None,
 			groups: GroupSet::new(),
 		}
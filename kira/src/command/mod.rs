@@ -7,17 +7,19 @@ use crate::{
 	audio_stream::{AudioStream, AudioStreamId},
 	group::{Group, GroupId},
 	instance::{
-		Instance, InstanceId, PauseInstanceSettings, ResumeInstanceSettings, StopInstanceSettings,
+		Instance, InstanceEffect, InstanceId, PauseInstanceSettings, ResumeInstanceSettings,
+		StopInstanceSettings,
 	},
 	metronome::{Metronome, MetronomeId},
 	mixer::{
 		effect::{Effect, EffectId, EffectSettings},
-		SendTrackId, SubTrackId, Track, TrackIndex,
+		DuckingSettings, SendTrackId, SubTrackId, Track, TrackIndex,
 	},
 	parameter::{tween::Tween, ParameterId},
 	playable::PlayableId,
 	sequence::{SequenceInstance, SequenceInstanceId},
 	sound::{Sound, SoundId},
+	spatial::{hrtf::HrtfDataset, ListenerId, ListenerSettings, Vec3},
 	tempo::Tempo,
 	value::Value,
 };
@@ -30,14 +32,29 @@ pub(crate) enum ResourceCommand {
 	RemoveArrangement(ArrangementId),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub(crate) enum InstanceCommand {
-	Play(InstanceId, Instance),
+	/// Plays an instance. The instance is moved out of the `Owned`
+	/// on the audio thread, and the empty allocation is freed by
+	/// the resource collector.
+	Play(InstanceId, Owned<Option<Instance>>),
 	SetInstanceVolume(InstanceId, Value<f64>),
 	SetInstancePlaybackRate(InstanceId, Value<f64>),
 	SetInstancePanning(InstanceId, Value<f64>),
 	SeekInstance(InstanceId, f64),
 	SeekInstanceTo(InstanceId, f64),
+	SetInstanceLoopRegion(InstanceId, f64, Option<f64>),
+	SetInstanceLoopCount(InstanceId, Option<u32>),
+	ExitInstanceLoop(InstanceId),
+	SetInstanceEffect(InstanceId, usize, InstanceEffect),
+	RemoveInstanceEffect(InstanceId, usize),
+	SetInstanceEffectEnabled(InstanceId, usize, bool),
+	SetInstanceSend(InstanceId, SendTrackId, Value<f64>),
+	RemoveInstanceSend(InstanceId, SendTrackId),
+	SetInstanceEmitterPosition(InstanceId, Vec3),
+	SetInstanceEmitterVelocity(InstanceId, Vec3),
+	SetInstanceOcclusion(InstanceId, f64),
+	SetInstanceObstruction(InstanceId, f64),
 	PauseInstance(InstanceId, PauseInstanceSettings),
 	ResumeInstance(InstanceId, ResumeInstanceSettings),
 	StopInstance(InstanceId, StopInstanceSettings),
@@ -76,12 +93,19 @@ pub(crate) enum SequenceCommand {
 pub(crate) enum MixerCommand {
 	AddTrack(Owned<Track>),
 	SetTrackVolume(TrackIndex, Value<f64>),
+	SetTrackParent(TrackIndex, TrackIndex),
+	SetTrackSend(TrackIndex, SendTrackId, Value<f64>),
+	RemoveTrackSend(TrackIndex, SendTrackId),
+	RetargetTrackSend(TrackIndex, SendTrackId, SendTrackId),
+	SetTrackDucking(TrackIndex, DuckingSettings),
+	RemoveTrackDucking(TrackIndex, TrackIndex),
 	RemoveSubTrack(SubTrackId),
 	RemoveSendTrack(SendTrackId),
 	AddEffect(TrackIndex, EffectId, Owned<Box<dyn Effect>>, EffectSettings),
 	SetEffectEnabled(TrackIndex, EffectId, bool),
 	SetEffectMix(TrackIndex, EffectId, Value<f64>),
 	RemoveEffect(TrackIndex, EffectId),
+	SetProcessingOrder(Owned<Vec<TrackIndex>>),
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -95,6 +119,8 @@ pub(crate) enum ParameterCommand {
 pub(crate) enum GroupCommand {
 	AddGroup(GroupId, Owned<Group>),
 	RemoveGroup(GroupId),
+	SetGroupVolume(GroupId, Value<f64>),
+	SetGroupPlaybackRate(GroupId, Value<f64>),
 }
 
 pub(crate) enum StreamCommand {
@@ -102,6 +128,15 @@ pub(crate) enum StreamCommand {
 	RemoveStream(AudioStreamId),
 }
 
+pub(crate) enum SpatialCommand {
+	AddListener(ListenerId, ListenerSettings),
+	RemoveListener(ListenerId),
+	SetListenerPosition(ListenerId, Vec3),
+	SetListenerVelocity(ListenerId, Vec3),
+	SetListenerOrientation(ListenerId, Vec3, Vec3),
+	SetHrtfDataset(Option<Owned<HrtfDataset>>),
+}
+
 pub(crate) enum Command {
 	Resource(ResourceCommand),
 	Instance(InstanceCommand),
@@ -111,6 +146,7 @@ pub(crate) enum Command {
 	Parameter(ParameterCommand),
 	Group(GroupCommand),
 	Stream(StreamCommand),
+	Spatial(SpatialCommand),
 }
 
 impl From<ResourceCommand> for Command {
@@ -160,3 +196,9 @@ impl From<StreamCommand> for Command {
 		Self::Stream(command)
 	}
 }
+
+impl From<SpatialCommand> for Command {
+	fn from(command: SpatialCommand) -> Self {
+		Self::Spatial(command)
+	}
+}
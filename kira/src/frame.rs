@@ -1,5 +1,53 @@
 use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
 
+/// How the volume of each channel changes as a sound is panned.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+#[cfg_attr(
+	feature = "serde_support",
+	derive(serde::Serialize, serde::Deserialize)
+)]
+pub enum PanLaw {
+	/// The volumes of the two channels always add up to 1,
+	/// so each channel is 6 dB quieter at the center than when
+	/// panned all the way to its side.
+	///
+	/// This is also known as the -6 dB pan law.
+	Linear,
+	/// The combined power of the two channels stays the same,
+	/// so each channel is 3 dB quieter at the center than when
+	/// panned all the way to its side.
+	///
+	/// This is also known as the -3 dB pan law.
+	#[default]
+	ConstantPower,
+	/// A compromise between the linear and constant power pan laws
+	/// where each channel is 4.5 dB quieter at the center.
+	///
+	/// This is also known as the -4.5 dB pan law.
+	Compromise,
+	/// Both channels are at full volume at the center, and the
+	/// channel on the opposite side is turned down as the sound
+	/// is panned.
+	///
+	/// This is meant for stereo sounds, since it keeps
+	/// the stereo image intact at the center instead of
+	/// making both channels quieter.
+	Balance,
+}
+
+impl PanLaw {
+	/// Returns the volumes of the left and right channels
+	/// for a panning position.
+	fn volumes(self, x: f32) -> (f32, f32) {
+		match self {
+			PanLaw::Linear => (1.0 - x, x),
+			PanLaw::ConstantPower => ((1.0 - x).sqrt(), x.sqrt()),
+			PanLaw::Compromise => ((1.0 - x).powf(0.75), x.powf(0.75)),
+			PanLaw::Balance => ((2.0 * (1.0 - x)).min(1.0), (2.0 * x).min(1.0)),
+		}
+	}
+}
+
 /// An audio sample with a left and right channel.
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub struct Frame {
@@ -28,12 +76,22 @@ impl Frame {
 		Self::new(left as f32 * scale, right as f32 * scale)
 	}
 
-	/// Pans a frame to the left or right.
+	/// Pans a frame to the left or right using the
+	/// [constant power](PanLaw::ConstantPower) pan law.
 	///
 	/// An `x` of 0 represents a hard left panning, an `x` of 1
 	/// represents a hard right panning.
 	pub fn panned(self, x: f32) -> Self {
-		Self::new(self.left * (1.0 - x).sqrt(), self.right * x.sqrt())
+		self.panned_with_law(x, PanLaw::ConstantPower)
+	}
+
+	/// Pans a frame to the left or right using the given pan law.
+	///
+	/// An `x` of 0 represents a hard left panning, an `x` of 1
+	/// represents a hard right panning.
+	pub fn panned_with_law(self, x: f32, law: PanLaw) -> Self {
+		let (left_volume, right_volume) = law.volumes(x);
+		Self::new(self.left * left_volume, self.right * right_volume)
 	}
 }
 
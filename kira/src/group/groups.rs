@@ -1,17 +1,43 @@
 use basedrop::Owned;
 
-use crate::{command::GroupCommand, static_container::index_map::StaticIndexMap};
+use crate::{
+	command::GroupCommand, parameter::Parameters, static_container::index_map::StaticIndexMap,
+};
 
-use super::{Group, GroupId};
+use super::{Group, GroupId, GroupSet};
+
+const BITS_PER_WORD: usize = u64::BITS as usize;
 
 pub(crate) struct Groups {
 	groups: StaticIndexMap<GroupId, Owned<Group>>,
+	/// For each group (by index), a bit set of the indices of
+	/// the group itself and every group it's a descendant of.
+	ancestry: Vec<u64>,
+	/// The number of words in each group's bit set.
+	words_per_group: usize,
+	/// The combined volume and playback rate of each group
+	/// (by index) and all of its ancestors.
+	effective_multipliers: Vec<(f64, f64)>,
+	/// Whether groups have been added or removed since the
+	/// groups' active instance and sequence counts were updated.
+	group_counts_outdated: bool,
 }
 
 impl Groups {
 	pub fn new(capacity: usize) -> Self {
+		let groups = StaticIndexMap::new(capacity);
+		// the map can end up with more room than requested,
+		// so size everything else to match it
+		let capacity = groups.capacity();
+		// usize::div_ceil isn't available on older toolchains
+		#[allow(clippy::manual_div_ceil)]
+		let words_per_group = (capacity + BITS_PER_WORD - 1) / BITS_PER_WORD;
 		Self {
-			groups: StaticIndexMap::new(capacity),
+			groups,
+			ancestry: vec![0; capacity * words_per_group],
+			words_per_group,
+			effective_multipliers: vec![(1.0, 1.0); capacity],
+			group_counts_outdated: false,
 		}
 	}
 
@@ -19,14 +45,177 @@ impl Groups {
 		self.groups.get(&id)
 	}
 
+	pub fn iter(&self) -> impl Iterator<Item = (&GroupId, &Owned<Group>)> {
+		self.groups.iter()
+	}
+
+	pub fn take_group_counts_outdated(&mut self) -> bool {
+		std::mem::take(&mut self.group_counts_outdated)
+	}
+
+	/// Returns the combined volume and playback rate of every
+	/// group that's an ancestor of any of the given group sets.
+	///
+	/// Each group is only counted once, even if it's an ancestor
+	/// of more than one of the sets.
+	pub fn multipliers(&self, sets: &[&GroupSet]) -> (f64, f64) {
+		let mut indices = sets
+			.iter()
+			.flat_map(|set| set.iter())
+			.filter_map(|id| self.groups.get_index_of(id));
+		let first_index = match indices.next() {
+			Some(index) => index,
+			None => return (1.0, 1.0),
+		};
+		// most things only belong to one group, and the multipliers
+		// for each group are already calculated
+		if indices.clone().all(|index| index == first_index) {
+			return self.effective_multipliers[first_index];
+		}
+		self.combined_multipliers(|word_index| {
+			indices.clone().fold(
+				self.ancestry[first_index * self.words_per_group + word_index],
+				|word, index| word | self.ancestry[index * self.words_per_group + word_index],
+			)
+		})
+	}
+
+	/// Returns the combined volume and playback rate of the
+	/// groups in a bit set of group indices.
+	///
+	/// `word` returns each word of the bit set.
+	fn combined_multipliers(&self, word: impl Fn(usize) -> u64) -> (f64, f64) {
+		let mut volume = 1.0;
+		let mut playback_rate = 1.0;
+		for word_index in 0..self.words_per_group {
+			let mut word = word(word_index);
+			while word != 0 {
+				let index = word_index * BITS_PER_WORD + word.trailing_zeros() as usize;
+				word &= word - 1;
+				if let Some((_, group)) = self.groups.get_index(index) {
+					volume *= group.volume();
+					playback_rate *= group.playback_rate();
+				}
+			}
+		}
+		(volume, playback_rate)
+	}
+
+	/// Calculates the combined volume and playback rate of
+	/// each group and its ancestors.
+	fn update_effective_multipliers(&mut self) {
+		for index in 0..self.groups.len() {
+			let row = index * self.words_per_group;
+			self.effective_multipliers[index] =
+				self.combined_multipliers(|word_index| self.ancestry[row + word_index]);
+		}
+	}
+
+	/// Records which groups the group at an index is a descendant of.
+	///
+	/// A group's parents are always added before it, so their
+	/// ancestry has to be up to date already.
+	fn update_ancestry_of(&mut self, index: usize) {
+		let row = index * self.words_per_group;
+		for word in &mut self.ancestry[row..row + self.words_per_group] {
+			*word = 0;
+		}
+		self.ancestry[row + index / BITS_PER_WORD] |= 1 << (index % BITS_PER_WORD);
+		let group = match self.groups.get_index(index) {
+			Some((_, group)) => group,
+			None => return,
+		};
+		for parent_id in group.groups().iter() {
+			if let Some(parent_index) = self.groups.get_index_of(parent_id) {
+				let parent_row = parent_index * self.words_per_group;
+				for word_index in 0..self.words_per_group {
+					self.ancestry[row + word_index] |= self.ancestry[parent_row + word_index];
+				}
+			}
+		}
+	}
+
+	/// Removes a group from the ancestry of the other groups
+	/// after it's been removed from the list of groups.
+	///
+	/// Groups are removed without changing the order of the other
+	/// groups, so every group after it moves down by one index.
+	fn remove_from_ancestry(&mut self, removed_index: usize) {
+		let num_groups = self.groups.len();
+		let words_per_group = self.words_per_group;
+		self.ancestry.copy_within(
+			(removed_index + 1) * words_per_group..(num_groups + 1) * words_per_group,
+			removed_index * words_per_group,
+		);
+		for word in
+			&mut self.ancestry[num_groups * words_per_group..(num_groups + 1) * words_per_group]
+		{
+			*word = 0;
+		}
+		for index in 0..num_groups {
+			let row = &mut self.ancestry[index * words_per_group..(index + 1) * words_per_group];
+			let was_descendant =
+				row[removed_index / BITS_PER_WORD] & (1 << (removed_index % BITS_PER_WORD)) != 0;
+			remove_bit(row, removed_index);
+			// descendants may have only been related to some of
+			// their other ancestors through the removed group
+			if was_descendant {
+				self.update_ancestry_of(index);
+			}
+		}
+	}
+
 	pub fn run_command(&mut self, command: GroupCommand) {
 		match command {
 			GroupCommand::AddGroup(id, group) => {
 				self.groups.try_insert(id, group).ok();
+				if let Some(index) = self.groups.get_index_of(&id) {
+					self.update_ancestry_of(index);
+				}
+				self.update_effective_multipliers();
+				self.group_counts_outdated = true;
 			}
 			GroupCommand::RemoveGroup(id) => {
-				self.groups.remove(&id);
+				if let Some(index) = self.groups.get_index_of(&id) {
+					self.groups.shift_remove_index(index);
+					self.remove_from_ancestry(index);
+				}
+				self.update_effective_multipliers();
+				self.group_counts_outdated = true;
 			}
+			GroupCommand::SetGroupVolume(id, volume) => {
+				if let Some(group) = self.groups.get_mut(&id) {
+					group.set_volume(volume);
+				}
+			}
+			GroupCommand::SetGroupPlaybackRate(id, playback_rate) => {
+				if let Some(group) = self.groups.get_mut(&id) {
+					group.set_playback_rate(playback_rate);
+				}
+			}
+		}
+	}
+
+	/// Updates each group's volume and playback rate and
+	/// calculates the combined multipliers of every group
+	/// and its ancestors.
+	pub fn update(&mut self, parameters: &Parameters) {
+		for (_, group) in &mut self.groups {
+			group.update(parameters);
 		}
+		self.update_effective_multipliers();
+	}
+}
+
+/// Removes a bit from a bit set, moving every bit after it
+/// down by one.
+fn remove_bit(words: &mut [u64], index: usize) {
+	let word_index = index / BITS_PER_WORD;
+	let lower_bits = (1 << (index % BITS_PER_WORD)) - 1;
+	let word = words[word_index];
+	words[word_index] = (word & lower_bits) | ((word >> 1) & !lower_bits);
+	for i in word_index + 1..words.len() {
+		words[i - 1] |= (words[i] & 1) << (BITS_PER_WORD - 1);
+		words[i] >>= 1;
 	}
 }
@@ -1,11 +1,16 @@
 //! An interface for controlling groups.
 
+use std::sync::Arc;
+
+use atomic::{Atomic, Ordering};
+
 use crate::{
 	command::{
 		producer::{CommandError, CommandProducer},
-		InstanceCommand, SequenceCommand,
+		GroupCommand, InstanceCommand, SequenceCommand,
 	},
 	instance::{PauseInstanceSettings, ResumeInstanceSettings, StopInstanceSettings},
+	value::Value,
 };
 
 use super::GroupId;
@@ -14,13 +19,22 @@ use super::GroupId;
 /// Allows you to control a group.
 pub struct GroupHandle {
 	id: GroupId,
+	num_active_instances: Arc<Atomic<usize>>,
+	num_active_sequences: Arc<Atomic<usize>>,
 	command_producer: CommandProducer,
 }
 
 impl GroupHandle {
-	pub(crate) fn new(id: GroupId, command_producer: CommandProducer) -> Self {
+	pub(crate) fn new(
+		id: GroupId,
+		num_active_instances: Arc<Atomic<usize>>,
+		num_active_sequences: Arc<Atomic<usize>>,
+		command_producer: CommandProducer,
+	) -> Self {
 		Self {
 			id,
+			num_active_instances,
+			num_active_sequences,
 			command_producer,
 		}
 	}
@@ -30,6 +44,35 @@ impl GroupHandle {
 		self.id
 	}
 
+	/// Returns the number of instances in this group that
+	/// are playing, paused, or waiting to start.
+	///
+	/// Instances that are fading out to a stop aren't counted.
+	pub fn num_active_instances(&self) -> usize {
+		self.num_active_instances.load(Ordering::Relaxed)
+	}
+
+	/// Returns the number of sequences in this group that
+	/// are running or paused.
+	pub fn num_active_sequences(&self) -> usize {
+		self.num_active_sequences.load(Ordering::Relaxed)
+	}
+
+	/// Sets the volume of the group.
+	pub fn set_volume(&mut self, volume: impl Into<Value<f64>>) -> Result<(), CommandError> {
+		self.command_producer
+			.push(GroupCommand::SetGroupVolume(self.id, volume.into()).into())
+	}
+
+	/// Sets the playback rate of the group.
+	pub fn set_playback_rate(
+		&mut self,
+		playback_rate: impl Into<Value<f64>>,
+	) -> Result<(), CommandError> {
+		self.command_producer
+			.push(GroupCommand::SetGroupPlaybackRate(self.id, playback_rate.into()).into())
+	}
+
 	/// Pauses all instances of sounds, arrangements, and sequences in this group.
 	pub fn pause(&mut self, settings: PauseInstanceSettings) -> Result<(), CommandError> {
 		self.command_producer
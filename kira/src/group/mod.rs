@@ -9,15 +9,35 @@
 //! The [`pause`](handle::GroupHandle::pause), [`resume`](handle::GroupHandle::resume), and
 //! [`stop`](handle::GroupHandle::stop) functions on [`GroupHandle`](handle::GroupHandle)s will
 //! affect all instances that have the specified group anywhere in their ancestry.
+//!
+//! Groups also have a volume and playback rate, which multiply the volume
+//! and playback rate of every instance and sequence that has the group
+//! anywhere in its ancestry. A sequence's playback rate affects how fast
+//! it moves through its steps, and instances started by a sequence are
+//! affected by the sequence's groups as well as their sound's or
+//! arrangement's groups.
+//!
+//! A group can limit how many instances in it can be playing at a time
+//! with [`GroupSettings::max_instances`], which works like the per-sound
+//! limit but is shared by every sound and arrangement in the group.
 
 pub(crate) mod groups;
 pub mod handle;
 mod set;
 
+use std::sync::Arc;
+
+use atomic::{Atomic, Ordering};
 use handle::GroupHandle;
 pub use set::GroupSet;
 use uuid::Uuid;
 
+use crate::{
+	instance::VoiceStealingPolicy,
+	parameter::Parameters,
+	value::{CachedValue, Value},
+};
+
 /// A unique identifier for a group.
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 #[cfg_attr(
@@ -55,6 +75,16 @@ pub struct GroupSettings {
 	pub id: Option<GroupId>,
 	/// The groups this group belongs to.
 	pub groups: GroupSet,
+	/// The volume of the group.
+	pub volume: Value<f64>,
+	/// The playback rate of the group.
+	pub playback_rate: Value<f64>,
+	/// The maximum number of instances in this group that can
+	/// be playing at a time, if any.
+	pub max_instances: Option<usize>,
+	/// What to do when a new instance in this group is started
+	/// while the maximum number of instances are already playing.
+	pub voice_stealing_policy: VoiceStealingPolicy,
 }
 
 impl GroupSettings {
@@ -78,6 +108,40 @@ impl GroupSettings {
 			..Default::default()
 		}
 	}
+
+	/// Sets the volume of the group.
+	pub fn volume(self, volume: impl Into<Value<f64>>) -> Self {
+		Self {
+			volume: volume.into(),
+			..self
+		}
+	}
+
+	/// Sets the playback rate of the group.
+	pub fn playback_rate(self, playback_rate: impl Into<Value<f64>>) -> Self {
+		Self {
+			playback_rate: playback_rate.into(),
+			..self
+		}
+	}
+
+	/// Sets the maximum number of instances in this group
+	/// that can be playing at a time.
+	pub fn max_instances(self, max_instances: usize) -> Self {
+		Self {
+			max_instances: Some(max_instances),
+			..self
+		}
+	}
+
+	/// Sets what to do when a new instance in this group is started
+	/// while the maximum number of instances are already playing.
+	pub fn voice_stealing_policy(self, voice_stealing_policy: VoiceStealingPolicy) -> Self {
+		Self {
+			voice_stealing_policy,
+			..self
+		}
+	}
 }
 
 impl Default for GroupSettings {
@@ -85,6 +149,10 @@ impl Default for GroupSettings {
 		Self {
 			id: None,
 			groups: GroupSet::new(),
+			volume: Value::Fixed(1.0),
+			playback_rate: Value::Fixed(1.0),
+			max_instances: None,
+			voice_stealing_policy: VoiceStealingPolicy::default(),
 		}
 	}
 }
@@ -92,16 +160,75 @@ impl Default for GroupSettings {
 #[derive(Debug, Clone)]
 pub(crate) struct Group {
 	groups: GroupSet,
+	volume: CachedValue<f64>,
+	playback_rate: CachedValue<f64>,
+	max_instances: Option<usize>,
+	voice_stealing_policy: VoiceStealingPolicy,
+	num_active_instances: Arc<Atomic<usize>>,
+	num_active_sequences: Arc<Atomic<usize>>,
 }
 
 impl Group {
 	pub fn new(settings: GroupSettings) -> Self {
 		Self {
 			groups: settings.groups,
+			volume: CachedValue::new(settings.volume, 1.0),
+			playback_rate: CachedValue::new(settings.playback_rate, 1.0),
+			max_instances: settings.max_instances,
+			voice_stealing_policy: settings.voice_stealing_policy,
+			num_active_instances: Arc::new(Atomic::new(0)),
+			num_active_sequences: Arc::new(Atomic::new(0)),
 		}
 	}
 
 	pub fn groups(&self) -> &GroupSet {
 		&self.groups
 	}
+
+	pub fn volume(&self) -> f64 {
+		self.volume.value()
+	}
+
+	pub fn playback_rate(&self) -> f64 {
+		self.playback_rate.value()
+	}
+
+	/// Returns the maximum number of instances in this group
+	/// that can be playing at a time and what to do when a new
+	/// instance is started at that limit, if there is a limit.
+	pub fn instance_limit(&self) -> Option<(usize, VoiceStealingPolicy)> {
+		self.max_instances
+			.map(|max_instances| (max_instances, self.voice_stealing_policy))
+	}
+
+	pub fn num_active_instances(&self) -> Arc<Atomic<usize>> {
+		self.num_active_instances.clone()
+	}
+
+	pub fn num_active_sequences(&self) -> Arc<Atomic<usize>> {
+		self.num_active_sequences.clone()
+	}
+
+	pub fn set_num_active_instances(&self, num_active_instances: usize) {
+		self.num_active_instances
+			.store(num_active_instances, Ordering::Relaxed);
+	}
+
+	pub fn set_num_active_sequences(&self, num_active_sequences: usize) {
+		self.num_active_sequences
+			.store(num_active_sequences, Ordering::Relaxed);
+	}
+
+	pub fn set_volume(&mut self, volume: Value<f64>) {
+		self.volume.set(volume);
+	}
+
+	pub fn set_playback_rate(&mut self, playback_rate: Value<f64>) {
+		self.playback_rate.set(playback_rate);
+	}
+
+	pub fn update(&mut self, parameters: &Parameters) {
+		self.volume.update(parameters);
+		self.playback_rate.update(parameters);
+	}
 }
@@ -0,0 +1,181 @@
+use crate::{
+	frame::Frame,
+	mixer::effect::{
+		distortion::{Distortion, DistortionSettings},
+		filter::{Filter, FilterSettings},
+		Effect,
+	},
+	parameter::Parameters,
+};
+
+/// The maximum number of effects an instance can have.
+pub const MAX_INSTANCE_EFFECTS: usize = 4;
+
+/// An effect that can be applied directly to an instance.
+#[derive(Debug, Copy, Clone)]
+#[cfg_attr(
+	feature = "serde_support",
+	derive(serde::Serialize, serde::Deserialize)
+)]
+pub enum InstanceEffect {
+	/// A [`Filter`] effect.
+	Filter(FilterSettings),
+	/// A [`Distortion`] effect.
+	Distortion(DistortionSettings),
+}
+
+impl From<FilterSettings> for InstanceEffect {
+	fn from(settings: FilterSettings) -> Self {
+		Self::Filter(settings)
+	}
+}
+
+impl From<DistortionSettings> for InstanceEffect {
+	fn from(settings: DistortionSettings) -> Self {
+		Self::Distortion(settings)
+	}
+}
+
+/// A chain of up to [`MAX_INSTANCE_EFFECTS`] effects that
+/// are applied to an instance before its volume and panning.
+#[derive(Debug, Copy, Clone, Default)]
+#[cfg_attr(
+	feature = "serde_support",
+	derive(serde::Serialize, serde::Deserialize)
+)]
+pub struct InstanceEffects {
+	effects: [Option<InstanceEffect>; MAX_INSTANCE_EFFECTS],
+}
+
+impl InstanceEffects {
+	/// Creates a new, empty effect chain.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Adds an effect to the end of the chain.
+	///
+	/// If the chain already has [`MAX_INSTANCE_EFFECTS`] effects,
+	/// the effect is given back as an error.
+	pub fn push(&mut self, effect: impl Into<InstanceEffect>) -> Result<(), InstanceEffect> {
+		let effect = effect.into();
+		match self.effects.iter_mut().find(|slot| slot.is_none()) {
+			Some(slot) => {
+				*slot = Some(effect);
+				Ok(())
+			}
+			None => Err(effect),
+		}
+	}
+
+	/// Returns the number of effects in the chain.
+	pub fn len(&self) -> usize {
+		self.iter().count()
+	}
+
+	/// Returns whether the chain has no effects.
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+
+	/// Returns an iterator over the effects in the chain.
+	pub fn iter(&self) -> impl Iterator<Item = &InstanceEffect> {
+		self.effects.iter().filter_map(|effect| effect.as_ref())
+	}
+}
+
+#[derive(Debug, Copy, Clone)]
+enum InstanceEffectProcessor {
+	Filter(Filter),
+	Distortion(Distortion),
+}
+
+impl InstanceEffectProcessor {
+	fn new(effect: InstanceEffect) -> Self {
+		match effect {
+			InstanceEffect::Filter(settings) => Self::Filter(Filter::new(settings)),
+			InstanceEffect::Distortion(settings) => Self::Distortion(Distortion::new(settings)),
+		}
+	}
+
+	/// Changes the effect's settings, keeping its internal state
+	/// if it's the same kind of effect.
+	fn set(&mut self, effect: InstanceEffect) {
+		match (self, effect) {
+			(Self::Filter(filter), InstanceEffect::Filter(settings)) => {
+				filter.set_settings(settings);
+			}
+			(Self::Distortion(distortion), InstanceEffect::Distortion(settings)) => {
+				distortion.set_settings(settings);
+			}
+			(processor, effect) => *processor = Self::new(effect),
+		}
+	}
+
+	fn process(&mut self, dt: f64, input: Frame, parameters: &Parameters) -> Frame {
+		match self {
+			Self::Filter(filter) => filter.process(dt, input, parameters),
+			Self::Distortion(distortion) => distortion.process(dt, input, parameters),
+		}
+	}
+}
+
+#[derive(Debug, Copy, Clone)]
+struct EffectSlot {
+	processor: InstanceEffectProcessor,
+	enabled: bool,
+}
+
+/// The effects of an instance on the audio thread.
+#[derive(Debug, Clone)]
+pub(crate) struct InstanceEffectChain {
+	slots: [Option<EffectSlot>; MAX_INSTANCE_EFFECTS],
+}
+
+impl InstanceEffectChain {
+	pub fn new(effects: InstanceEffects) -> Self {
+		let mut slots = [None; MAX_INSTANCE_EFFECTS];
+		for (slot, effect) in slots.iter_mut().zip(effects.iter()) {
+			*slot = Some(EffectSlot {
+				processor: InstanceEffectProcessor::new(*effect),
+				enabled: true,
+			});
+		}
+		Self { slots }
+	}
+
+	pub fn set(&mut self, index: usize, effect: InstanceEffect) {
+		if let Some(slot) = self.slots.get_mut(index) {
+			match slot {
+				Some(slot) => slot.processor.set(effect),
+				None => {
+					*slot = Some(EffectSlot {
+						processor: InstanceEffectProcessor::new(effect),
+						enabled: true,
+					})
+				}
+			}
+		}
+	}
+
+	pub fn remove(&mut self, index: usize) {
+		if let Some(slot) = self.slots.get_mut(index) {
+			*slot = None;
+		}
+	}
+
+	pub fn set_enabled(&mut self, index: usize, enabled: bool) {
+		if let Some(Some(slot)) = self.slots.get_mut(index) {
+			slot.enabled = enabled;
+		}
+	}
+
+	pub fn process(&mut self, dt: f64, mut input: Frame, parameters: &Parameters) -> Frame {
+		for slot in self.slots.iter_mut().flatten() {
+			if slot.enabled {
+				input = slot.processor.process(dt, input, parameters);
+			}
+		}
+		input
+	}
+}
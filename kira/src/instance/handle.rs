@@ -9,11 +9,14 @@ use crate::{
 		producer::{CommandError, CommandProducer},
 		InstanceCommand,
 	},
+	mixer::SendTrackId,
+	spatial::Vec3,
 	Value,
 };
 
 use super::{
-	InstanceId, InstanceState, PauseInstanceSettings, ResumeInstanceSettings, StopInstanceSettings,
+	InstanceEffect, InstanceId, InstanceState, PauseInstanceSettings, ResumeInstanceSettings,
+	StopInstanceSettings,
 };
 
 #[derive(Debug, Clone)]
@@ -88,6 +91,124 @@ impl InstanceHandle {
 			.push(InstanceCommand::SeekInstanceTo(self.id, position).into())
 	}
 
+	/// Sets the region of the instance that should loop.
+	///
+	/// If `end` is `None`, the instance will loop from the end
+	/// of the sound or arrangement.
+	pub fn set_loop_region(
+		&mut self,
+		start: f64,
+		end: impl Into<Option<f64>>,
+	) -> Result<(), CommandError> {
+		self.command_producer
+			.push(InstanceCommand::SetInstanceLoopRegion(self.id, start, end.into()).into())
+	}
+
+	/// Sets how many more times the instance should loop
+	/// before playing through to the end.
+	///
+	/// If `count` is `None`, the instance will loop forever.
+	pub fn set_loop_count(&mut self, count: impl Into<Option<u32>>) -> Result<(), CommandError> {
+		self.command_producer
+			.push(InstanceCommand::SetInstanceLoopCount(self.id, count.into()).into())
+	}
+
+	/// Stops the instance from looping, letting it play
+	/// through to the end.
+	pub fn exit_loop(&mut self) -> Result<(), CommandError> {
+		self.command_producer
+			.push(InstanceCommand::ExitInstanceLoop(self.id).into())
+	}
+
+	/// Sets the effect at the given index of the instance's
+	/// effect chain.
+	///
+	/// If there's already an effect of the same kind at that index,
+	/// its settings will be changed without resetting its state.
+	/// Indices past [`MAX_INSTANCE_EFFECTS`](super::MAX_INSTANCE_EFFECTS)
+	/// are ignored.
+	pub fn set_effect(
+		&mut self,
+		index: usize,
+		effect: impl Into<InstanceEffect>,
+	) -> Result<(), CommandError> {
+		self.command_producer
+			.push(InstanceCommand::SetInstanceEffect(self.id, index, effect.into()).into())
+	}
+
+	/// Removes the effect at the given index of the instance's
+	/// effect chain.
+	pub fn remove_effect(&mut self, index: usize) -> Result<(), CommandError> {
+		self.command_producer
+			.push(InstanceCommand::RemoveInstanceEffect(self.id, index).into())
+	}
+
+	/// Enables or disables the effect at the given index of
+	/// the instance's effect chain.
+	pub fn set_effect_enabled(&mut self, index: usize, enabled: bool) -> Result<(), CommandError> {
+		self.command_producer
+			.push(InstanceCommand::SetInstanceEffectEnabled(self.id, index, enabled).into())
+	}
+
+	/// Sets the volume level the instance sends its audio
+	/// to a send track at.
+	///
+	/// If the instance isn't already sending audio to the send track,
+	/// it will start to, unless it's already sending audio to
+	/// [`MAX_INSTANCE_SENDS`](super::MAX_INSTANCE_SENDS) send tracks.
+	pub fn set_send(
+		&mut self,
+		send_track: impl Into<SendTrackId>,
+		volume: impl Into<Value<f64>>,
+	) -> Result<(), CommandError> {
+		self.command_producer.push(
+			InstanceCommand::SetInstanceSend(self.id, send_track.into(), volume.into()).into(),
+		)
+	}
+
+	/// Stops the instance from sending audio to a send track.
+	pub fn remove_send(&mut self, send_track: impl Into<SendTrackId>) -> Result<(), CommandError> {
+		self.command_producer
+			.push(InstanceCommand::RemoveInstanceSend(self.id, send_track.into()).into())
+	}
+
+	/// Sets the position of the instance's emitter.
+	///
+	/// This does nothing if the instance was played without
+	/// an emitter.
+	pub fn set_emitter_position(&mut self, position: impl Into<Vec3>) -> Result<(), CommandError> {
+		self.command_producer
+			.push(InstanceCommand::SetInstanceEmitterPosition(self.id, position.into()).into())
+	}
+
+	/// Sets the velocity of the instance's emitter
+	/// (in units per second).
+	///
+	/// This does nothing if the instance was played without
+	/// an emitter.
+	pub fn set_emitter_velocity(&mut self, velocity: impl Into<Vec3>) -> Result<(), CommandError> {
+		self.command_producer
+			.push(InstanceCommand::SetInstanceEmitterVelocity(self.id, velocity.into()).into())
+	}
+
+	/// Sets how occluded the instance is (from 0 to 1).
+	///
+	/// An occluded instance is completely blocked from the listener,
+	/// like a sound in another room.
+	pub fn set_occlusion(&mut self, occlusion: f64) -> Result<(), CommandError> {
+		self.command_producer
+			.push(InstanceCommand::SetInstanceOcclusion(self.id, occlusion).into())
+	}
+
+	/// Sets how obstructed the instance is (from 0 to 1).
+	///
+	/// An obstructed instance only has its direct path to the
+	/// listener blocked, like a sound behind a pillar.
+	pub fn set_obstruction(&mut self, obstruction: f64) -> Result<(), CommandError> {
+		self.command_producer
+			.push(InstanceCommand::SetInstanceObstruction(self.id, obstruction).into())
+	}
+
 	/// Pauses the instance.
 	pub fn pause(&mut self, settings: PauseInstanceSettings) -> Result<(), CommandError> {
 		self.command_producer
@@ -55,28 +55,55 @@
 //!
 //! If the instance has a loop start point and it's playing
 //! backward, when the playback position is earlier than the
-//! loop start point, it will wrap around to the end
-//! of the instance.
+//! loop start point, it will wrap around to the loop end point
+//! (or the end of the instance if there isn't one).
+//!
+//! ## Loop regions
+//!
+//! By default, a looping instance jumps back to the loop start
+//! point when it reaches the end of the sound or arrangement. A loop
+//! end point can be set to loop a region in the middle instead,
+//! and a loop count can be set to make the instance play through
+//! to the end after looping a number of times. The loop region
+//! and count can be changed while the instance is playing, and
+//! [`InstanceHandle::exit_loop`](handle::InstanceHandle::exit_loop)
+//! lets the instance play through to the end, which is useful
+//! for sustain loops.
 
+mod effects;
 pub mod handle;
+mod sends;
 mod settings;
 
 use atomic::Atomic;
+use effects::InstanceEffectChain;
+pub use effects::{InstanceEffect, InstanceEffects, MAX_INSTANCE_EFFECTS};
 use handle::InstanceHandle;
+use sends::InstanceSendLevels;
+pub use sends::{InstanceSends, MAX_INSTANCE_SENDS};
 pub use settings::*;
 
 use uuid::Uuid;
 
 use crate::{
-	frame::Frame,
-	mixer::TrackIndex,
+	frame::{Frame, PanLaw},
+	metronome::Metronomes,
+	mixer::{SendTrackId, TrackIndex},
 	parameter::{Parameter, Parameters},
 	playable::{PlayableId, Playables},
 	sequence::SequenceInstanceId,
+	sound::{ResamplingQuality, EXACT_PLAYBACK_EPSILON},
+	spatial::{
+		hrtf::{HrtfDataset, HrtfState},
+		Emitter, Occlusion, Panner, SpatialScene, Vec3,
+	},
 	value::CachedValue,
 	value::Value,
 };
-use std::sync::{atomic::Ordering, Arc};
+use std::{
+	f64::consts::PI,
+	sync::{atomic::Ordering, Arc},
+};
 
 /// A unique identifier for an instance.
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
@@ -106,6 +133,8 @@ impl From<&InstanceHandle> for InstanceId {
 /// The playback state of the instance.
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum InstanceState {
+	/// The instance is waiting for its start time.
+	WaitingToStart,
 	/// The instance is playing.
 	Playing,
 	/// The instance is paused.
@@ -130,6 +159,7 @@ pub enum InstanceState {
 pub(crate) struct Instance {
 	playable_id: PlayableId,
 	duration: f64,
+	sample_rate: Option<u32>,
 	sequence_id: Option<SequenceInstanceId>,
 	track_index: TrackIndex,
 	volume: CachedValue<f64>,
@@ -137,9 +167,27 @@ pub(crate) struct Instance {
 	panning: CachedValue<f64>,
 	reverse: bool,
 	loop_start: Option<f64>,
+	loop_end: Option<f64>,
+	loops_remaining: Option<u32>,
+	loop_crossfade: f64,
+	resampling_quality: Option<ResamplingQuality>,
+	effects: InstanceEffectChain,
+	sends: InstanceSendLevels,
+	emitter: Option<Emitter>,
+	pan_law: Option<PanLaw>,
+	occlusion: Occlusion,
+	group_volume: f64,
+	group_playback_rate: f64,
+	priority: i32,
+	is_virtual: bool,
+	start_time: InstanceStartTime,
 	state: InstanceState,
 	public_state: Arc<Atomic<InstanceState>>,
 	position: f64,
+	/// The playback position as a whole number of samples, kept
+	/// while the position is exactly on a sample so that long
+	/// instances don't drift away from it.
+	sample_index: Option<i64>,
 	public_position: Arc<Atomic<f64>>,
 	fade_volume: Parameter,
 }
@@ -148,6 +196,7 @@ impl Instance {
 	pub fn new(
 		playable: PlayableId,
 		duration: f64,
+		sample_rate: Option<u32>,
 		sequence_id: Option<SequenceInstanceId>,
 		settings: InternalInstanceSettings,
 	) -> Self {
@@ -158,9 +207,14 @@ impl Instance {
 		} else {
 			fade_volume = Parameter::new(1.0);
 		}
-		Self {
+		let state = match settings.start_time {
+			InstanceStartTime::Immediate => InstanceState::Playing,
+			_ => InstanceState::WaitingToStart,
+		};
+		let mut instance = Self {
 			playable_id: playable,
 			duration,
+			sample_rate,
 			sequence_id,
 			track_index: settings.track,
 			volume: CachedValue::new(settings.volume, 1.0),
@@ -168,12 +222,29 @@ impl Instance {
 			panning: CachedValue::new(settings.panning, 0.5).with_valid_range(0.0..1.0),
 			reverse: settings.reverse,
 			loop_start: settings.loop_start,
-			state: InstanceState::Playing,
-			public_state: Arc::new(Atomic::new(InstanceState::Playing)),
+			loop_end: settings.loop_end,
+			loops_remaining: settings.loop_count,
+			loop_crossfade: settings.loop_crossfade,
+			resampling_quality: settings.resampling_quality,
+			effects: InstanceEffectChain::new(settings.effects),
+			sends: InstanceSendLevels::new(settings.sends),
+			emitter: settings.emitter.map(Emitter::new),
+			pan_law: settings.pan_law,
+			occlusion: Occlusion::new(settings.occlusion),
+			group_volume: 1.0,
+			group_playback_rate: 1.0,
+			priority: settings.priority,
+			is_virtual: false,
+			start_time: settings.start_time,
+			state,
+			public_state: Arc::new(Atomic::new(state)),
 			position: settings.start_position,
+			sample_index: None,
 			public_position: Arc::new(Atomic::new(settings.start_position)),
 			fade_volume,
-		}
+		};
+		instance.sample_index = instance.exact_sample_index();
+		instance
 	}
 
 	pub fn playable_id(&self) -> PlayableId {
@@ -188,8 +259,38 @@ impl Instance {
 		self.sequence_id
 	}
 
+	pub fn priority(&self) -> i32 {
+		self.priority
+	}
+
+	/// Returns the volume of the instance, not counting fades.
+	pub fn volume(&self) -> f64 {
+		let spatial_volume = match &self.emitter {
+			Some(emitter) => emitter.volume(),
+			None => 1.0,
+		};
+		self.volume.value() * spatial_volume * self.occlusion.volume() * self.group_volume
+	}
+
 	pub fn effective_volume(&self) -> f64 {
-		self.volume.value() * self.fade_volume.value()
+		self.volume() * self.fade_volume.value()
+	}
+
+	/// Returns the playback rate of the instance, including
+	/// the Doppler effect from its emitter.
+	fn effective_playback_rate(&self) -> f64 {
+		let doppler_playback_rate = match &self.emitter {
+			Some(emitter) => emitter.doppler_playback_rate(),
+			None => 1.0,
+		};
+		self.playback_rate.value() * doppler_playback_rate * self.group_playback_rate
+	}
+
+	fn effective_panning(&self) -> f64 {
+		match &self.emitter {
+			Some(emitter) => emitter.panning(),
+			None => self.panning.value(),
+		}
 	}
 
 	pub fn public_state(&self) -> Arc<Atomic<InstanceState>> {
@@ -202,6 +303,7 @@ impl Instance {
 
 	pub fn playing(&self) -> bool {
 		match self.state {
+			InstanceState::WaitingToStart => false,
 			InstanceState::Playing => true,
 			InstanceState::Paused(_) => false,
 			InstanceState::Stopped => false,
@@ -210,6 +312,24 @@ impl Instance {
 		}
 	}
 
+	/// Returns whether the instance is too quiet to be heard
+	/// (not counting fades),
+	/// in which case it keeps its playback position up to date
+	/// without producing any audio.
+	pub fn is_virtual(&self) -> bool {
+		self.is_virtual
+	}
+
+	pub fn set_virtual(&mut self, is_virtual: bool) {
+		self.is_virtual = is_virtual;
+	}
+
+	/// Returns whether the instance is stopped or fading out
+	/// to a stop.
+	pub fn stopping(&self) -> bool {
+		matches!(self.state, InstanceState::Stopping | InstanceState::Stopped)
+	}
+
 	pub fn finished(&self) -> bool {
 		self.state == InstanceState::Stopped
 	}
@@ -228,10 +348,158 @@ impl Instance {
 
 	pub fn seek(&mut self, offset: f64) {
 		self.position += offset;
+		self.sample_index = self.exact_sample_index();
 	}
 
 	pub fn seek_to(&mut self, position: f64) {
 		self.position = position;
+		self.sample_index = self.exact_sample_index();
+	}
+
+	/// Returns the playback position as a whole number of samples
+	/// if it's exactly on a sample of the sound.
+	///
+	/// This should only be used when the position has just been set,
+	/// since floating point error builds up as the position moves.
+	fn exact_sample_index(&self) -> Option<i64> {
+		let sample_position = self.sample_rate? as f64 * self.position;
+		let rounded = sample_position.round();
+		if (sample_position - rounded).abs() < EXACT_PLAYBACK_EPSILON {
+			Some(rounded as i64)
+		} else {
+			None
+		}
+	}
+
+	/// Moves the whole-sample playback position along with the
+	/// playback position if the instance moves exactly one sample
+	/// per frame, and snaps the playback position to it.
+	///
+	/// `jumped` should be `true` if the playback position wrapped
+	/// around a loop this frame.
+	fn update_sample_index(&mut self, step: f64, jumped: bool) {
+		let sample_rate = match self.sample_rate {
+			Some(sample_rate) => sample_rate as f64,
+			None => return,
+		};
+		let sample_step = sample_rate * step;
+		self.sample_index = if jumped {
+			self.exact_sample_index()
+		} else {
+			match self.sample_index {
+				Some(sample_index) if (sample_step.abs() - 1.0).abs() < EXACT_PLAYBACK_EPSILON => {
+					Some(sample_index + sample_step.signum() as i64)
+				}
+				_ => None,
+			}
+		};
+		if let Some(sample_index) = self.sample_index {
+			self.position = sample_index as f64 / sample_rate;
+		}
+	}
+
+	pub fn set_effect(&mut self, index: usize, effect: InstanceEffect) {
+		self.effects.set(index, effect);
+	}
+
+	pub fn remove_effect(&mut self, index: usize) {
+		self.effects.remove(index);
+	}
+
+	pub fn set_effect_enabled(&mut self, index: usize, enabled: bool) {
+		self.effects.set_enabled(index, enabled);
+	}
+
+	pub fn set_send(&mut self, send_track: SendTrackId, volume: Value<f64>) {
+		self.sends.set(send_track, volume);
+	}
+
+	pub fn remove_send(&mut self, send_track: SendTrackId) {
+		self.sends.remove(send_track);
+	}
+
+	/// Returns the send tracks the instance sends its audio to
+	/// and the volume levels it sends it at.
+	pub fn sends(&self) -> impl Iterator<Item = (SendTrackId, f64)> + '_ {
+		self.sends.iter()
+	}
+
+	pub fn set_emitter_position(&mut self, position: Vec3) {
+		if let Some(emitter) = &mut self.emitter {
+			emitter.set_position(position);
+		}
+	}
+
+	/// Sets the velocity of the instance's emitter (if it has one).
+	pub fn set_emitter_velocity(&mut self, velocity: Vec3) {
+		if let Some(emitter) = &mut self.emitter {
+			emitter.set_velocity(velocity);
+		}
+	}
+
+	/// Returns whether the instance should be rendered
+	/// with the HRTF panner.
+	pub fn uses_hrtf(&self) -> bool {
+		matches!(&self.emitter, Some(emitter) if emitter.panner() == Panner::Hrtf)
+	}
+
+	pub fn set_occlusion(&mut self, occlusion: f64) {
+		self.occlusion.set_occlusion(occlusion);
+	}
+
+	pub fn set_obstruction(&mut self, obstruction: f64) {
+		self.occlusion.set_obstruction(obstruction);
+	}
+
+	/// Sets the combined volume and playback rate of the
+	/// groups the instance belongs to.
+	pub fn set_group_multipliers(&mut self, volume: f64, playback_rate: f64) {
+		self.group_volume = volume;
+		self.group_playback_rate = playback_rate;
+	}
+
+	/// Updates the volume and panning of the instance's emitter
+	/// (if it has one) relative to the listeners, as well as
+	/// the instance's occlusion and obstruction.
+	///
+	/// This should be called before checking the volume of the
+	/// instance or getting its output for the frame.
+	pub fn update_spatialization(&mut self, dt: f64, spatial_scene: &SpatialScene) {
+		if let Some(emitter) = &mut self.emitter {
+			emitter.update(dt, spatial_scene);
+		}
+		self.occlusion.update(dt);
+	}
+
+	pub fn set_loop_region(&mut self, start: f64, end: Option<f64>) {
+		self.loop_start = Some(start);
+		self.loop_end = end;
+	}
+
+	pub fn set_loop_count(&mut self, count: Option<u32>) {
+		self.loops_remaining = count;
+	}
+
+	pub fn exit_loop(&mut self) {
+		self.loop_start = None;
+	}
+
+	/// Returns the loop start and end points if the instance
+	/// should still loop.
+	fn active_loop_region(&self) -> Option<(f64, f64)> {
+		if self.loops_remaining == Some(0) {
+			return None;
+		}
+		self.loop_start
+			.map(|start| (start, self.loop_end.unwrap_or(self.duration)))
+	}
+
+	/// Counts down the number of loops left after the instance
+	/// jumps back to the start of the loop region.
+	fn count_loop(&mut self) {
+		if let Some(loops_remaining) = &mut self.loops_remaining {
+			*loops_remaining = loops_remaining.saturating_sub(1);
+		}
 	}
 
 	fn set_state(&mut self, state: InstanceState) {
@@ -270,33 +538,89 @@ impl Instance {
 		self.fade_volume.set(0.0, settings.fade_tween);
 	}
 
+	/// Starts the instance if it's waiting for its start time
+	/// and the start time has come.
+	///
+	/// This should be called before getting the instance's output
+	/// for the frame so that the instance starts on the exact
+	/// frame it's supposed to.
+	pub fn update_start_time(&mut self, dt: f64, metronomes: &Metronomes) {
+		if self.state != InstanceState::WaitingToStart {
+			return;
+		}
+		let start = match &mut self.start_time {
+			InstanceStartTime::Immediate => true,
+			InstanceStartTime::Delayed(delay) => {
+				// start on the frame closest to the end of the delay
+				if *delay < dt / 2.0 {
+					true
+				} else {
+					*delay -= dt;
+					false
+				}
+			}
+			InstanceStartTime::NextInterval(id, interval) => metronomes
+				.get(*id)
+				.map(|metronome| metronome.interval_passed(*interval))
+				.unwrap_or(false),
+		};
+		if start {
+			self.set_state(InstanceState::Playing);
+		}
+	}
+
 	pub fn update(&mut self, dt: f64, parameters: &Parameters) {
+		// don't start fading in until the instance starts
+		if self.state == InstanceState::WaitingToStart {
+			return;
+		}
 		if self.playing() {
 			self.volume.update(parameters);
 			self.playback_rate.update(parameters);
 			self.panning.update(parameters);
-			let mut playback_rate = self.playback_rate.value();
+			self.sends.update(parameters);
+			let mut playback_rate = self.effective_playback_rate();
 			if self.reverse {
 				playback_rate *= -1.0;
 			}
+			let previous_position = self.position;
 			self.position += playback_rate * dt;
+			let mut wrapped = false;
+			// only wrap around when the playback position crosses
+			// the edge of the loop region, so an instance that's
+			// outside of the loop region can play into it
 			if playback_rate < 0.0 {
-				if let Some(loop_start) = self.loop_start {
-					while self.position < loop_start {
-						self.position += self.duration - loop_start;
+				while let Some((loop_start, loop_end)) = self.active_loop_region() {
+					if previous_position < loop_start
+						|| self.position >= loop_start
+						|| loop_end <= loop_start
+					{
+						break;
 					}
-				} else if self.position < 0.0 {
+					self.position += loop_end - loop_start;
+					wrapped = true;
+					self.count_loop();
+				}
+				if self.position < 0.0 {
 					self.set_state(InstanceState::Stopped);
 				}
 			} else {
-				if let Some(loop_start) = self.loop_start {
-					while self.position > self.duration {
-						self.position -= self.duration - loop_start;
+				while let Some((loop_start, loop_end)) = self.active_loop_region() {
+					if previous_position > loop_end
+						|| self.position <= loop_end
+						|| loop_end <= loop_start
+					{
+						break;
 					}
-				} else if self.position > self.duration {
+					self.position -= loop_end - loop_start;
+					wrapped = true;
+					self.count_loop();
+				}
+				if self.position > self.duration {
 					self.set_state(InstanceState::Stopped);
 				}
 			}
+			self.update_sample_index(playback_rate * dt, wrapped);
 		}
 		let finished_fading = self.fade_volume.update(dt);
 		if finished_fading {
@@ -313,11 +637,85 @@ impl Instance {
 		self.public_position.store(self.position, Ordering::Relaxed);
 	}
 
-	pub fn get_sample(&self, playables: &Playables) -> Frame {
-		let mut out = playables
-			.frame_at_position(self.playable_id, self.position)
-			.unwrap_or(Frame::from_mono(0.0));
-		out = out.panned(self.panning.value() as f32);
+	fn frame_at_position(
+		&self,
+		position: f64,
+		sample_index: Option<i64>,
+		dt: f64,
+		playables: &Playables,
+	) -> Frame {
+		playables
+			.frame_at_position(
+				self.playable_id,
+				position,
+				sample_index,
+				self.effective_playback_rate() * dt,
+				self.resampling_quality,
+			)
+			.unwrap_or(Frame::from_mono(0.0))
+	}
+
+	/// Gets the frame at the current playback position, blending in
+	/// the audio from the other side of the loop region when the
+	/// playback position is about to wrap around.
+	fn crossfaded_frame(&self, dt: f64, playables: &Playables) -> Frame {
+		let out = self.frame_at_position(self.position, self.sample_index, dt, playables);
+		let (loop_start, loop_end) = match self.active_loop_region() {
+			Some(region) if self.loop_crossfade > 0.0 => region,
+			_ => return out,
+		};
+		let loop_length = loop_end - loop_start;
+		let backward = (self.playback_rate.value() < 0.0) != self.reverse;
+		// the crossfade can't be longer than the loop, and it
+		// needs audio outside of the loop region to blend in
+		let (crossfade, distance_to_seam) = if backward {
+			(
+				self.loop_crossfade
+					.min(loop_length)
+					.min(self.duration - loop_end),
+				self.position - loop_start,
+			)
+		} else {
+			(
+				self.loop_crossfade.min(loop_length).min(loop_start),
+				loop_end - self.position,
+			)
+		};
+		if crossfade <= 0.0 || distance_to_seam < 0.0 || distance_to_seam > crossfade {
+			return out;
+		}
+		let other_side = if backward {
+			self.frame_at_position(self.position + loop_length, None, dt, playables)
+		} else {
+			self.frame_at_position(self.position - loop_length, None, dt, playables)
+		};
+		// equal power crossfade
+		let progress = 1.0 - distance_to_seam / crossfade;
+		let fade_out = (progress * PI / 2.0).cos() as f32;
+		let fade_in = (progress * PI / 2.0).sin() as f32;
+		out * fade_out + other_side * fade_in
+	}
+
+	pub fn get_sample(
+		&mut self,
+		dt: f64,
+		playables: &Playables,
+		parameters: &Parameters,
+		default_pan_law: PanLaw,
+		hrtf: Option<(&HrtfDataset, &mut HrtfState)>,
+	) -> Frame {
+		let mut out = self.crossfaded_frame(dt, playables);
+		out = self.effects.process(dt, out, parameters);
+		out = self.occlusion.process(dt, out, parameters);
+		out = match (&self.emitter, hrtf) {
+			(Some(emitter), Some((dataset, state))) => {
+				state.process(dataset, emitter.direction(), (out.left + out.right) / 2.0)
+			}
+			_ => out.panned_with_law(
+				self.effective_panning() as f32,
+				self.pan_law.unwrap_or(default_pan_law),
+			),
+		};
 		out * (self.effective_volume() as f32)
 	}
 }
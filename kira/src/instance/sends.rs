@@ -0,0 +1,108 @@
+use crate::{mixer::SendTrackId, parameter::Parameters, CachedValue, Value};
+
+/// The maximum number of send tracks an instance can send audio to.
+pub const MAX_INSTANCE_SENDS: usize = 4;
+
+/// A mapping of send tracks to the volume levels an instance
+/// sends its audio to them at.
+///
+/// Unlike [`TrackSends`](crate::mixer::TrackSends), this can hold
+/// at most [`MAX_INSTANCE_SENDS`] send tracks, which lets instances
+/// be created on the audio thread without allocating memory.
+#[derive(Debug, Copy, Clone, Default)]
+#[cfg_attr(
+	feature = "serde_support",
+	derive(serde::Serialize, serde::Deserialize)
+)]
+pub struct InstanceSends {
+	sends: [Option<(SendTrackId, Value<f64>)>; MAX_INSTANCE_SENDS],
+}
+
+impl InstanceSends {
+	/// Creates a new, empty `InstanceSends` struct.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Returns an iterator over the pairs of `SendTrackId`s and
+	/// volume levels in the map.
+	pub fn iter(&self) -> impl Iterator<Item = &(SendTrackId, Value<f64>)> {
+		self.sends.iter().filter_map(|send| send.as_ref())
+	}
+
+	/// Adds a `SendTrackId` to the map with the volume level of the signal
+	/// to send to that track.
+	///
+	/// If the send track is already in the map, its volume level
+	/// will be replaced. If the map is full, the send track will not
+	/// be added.
+	pub fn add(
+		mut self,
+		send_track: impl Into<SendTrackId>,
+		volume: impl Into<Value<f64>>,
+	) -> Self {
+		let send_track = send_track.into();
+		let volume = volume.into();
+		let slot = match self
+			.sends
+			.iter()
+			.position(|send| matches!(send, Some((id, _)) if *id == send_track))
+		{
+			Some(index) => Some(index),
+			None => self.sends.iter().position(|send| send.is_none()),
+		};
+		if let Some(index) = slot {
+			self.sends[index] = Some((send_track, volume));
+		}
+		self
+	}
+}
+
+/// The sends of an instance on the audio thread.
+#[derive(Debug, Clone)]
+pub(crate) struct InstanceSendLevels {
+	sends: [Option<(SendTrackId, CachedValue<f64>)>; MAX_INSTANCE_SENDS],
+}
+
+impl InstanceSendLevels {
+	pub fn new(sends: InstanceSends) -> Self {
+		let mut levels = [None; MAX_INSTANCE_SENDS];
+		for (level, (id, volume)) in levels.iter_mut().zip(sends.iter()) {
+			*level = Some((*id, CachedValue::new(*volume, 1.0)));
+		}
+		Self { sends: levels }
+	}
+
+	pub fn set(&mut self, send_track: SendTrackId, volume: Value<f64>) {
+		for (id, level) in self.sends.iter_mut().flatten() {
+			if *id == send_track {
+				level.set(volume);
+				return;
+			}
+		}
+		if let Some(slot) = self.sends.iter_mut().find(|send| send.is_none()) {
+			*slot = Some((send_track, CachedValue::new(volume, 1.0)));
+		}
+	}
+
+	pub fn remove(&mut self, send_track: SendTrackId) {
+		for send in &mut self.sends {
+			if matches!(send, Some((id, _)) if *id == send_track) {
+				*send = None;
+			}
+		}
+	}
+
+	pub fn update(&mut self, parameters: &Parameters) {
+		for (_, level) in self.sends.iter_mut().flatten() {
+			level.update(parameters);
+		}
+	}
+
+	pub fn iter(&self) -> impl Iterator<Item = (SendTrackId, f64)> + '_ {
+		self.sends
+			.iter()
+			.flatten()
+			.map(|(id, level)| (*id, level.value()))
+	}
+}
@@ -1,10 +1,14 @@
 use crate::{
+	frame::PanLaw,
+	metronome::MetronomeId,
 	mixer::{SubTrackId, TrackIndex},
 	parameter::tween::{EaseDirection, Easing, Tween},
+	sound::ResamplingQuality,
+	spatial::{EmitterSettings, OcclusionSettings},
 	Value,
 };
 
-use super::InstanceId;
+use super::{InstanceEffect, InstanceEffects, InstanceId, InstanceSends};
 
 /// A track index for an instance to play on.
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
@@ -73,6 +77,47 @@ impl From<Option<f64>> for InstanceLoopStart {
 	}
 }
 
+/// What happens when a new instance is started while the
+/// maximum number of instances are already playing.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+#[cfg_attr(
+	feature = "serde_support",
+	derive(serde::Serialize, serde::Deserialize)
+)]
+pub enum VoiceStealingPolicy {
+	/// Stop the instance that was started the longest time ago.
+	#[default]
+	Oldest,
+	/// Stop the instance with the lowest volume.
+	Quietest,
+	/// Stop the instance with the lowest priority, or the oldest
+	/// instance with that priority if there's more than one.
+	///
+	/// If every playing instance has a higher priority than the
+	/// new instance, the new instance will not be played.
+	LowestPriority,
+	/// Don't play the new instance.
+	Refuse,
+}
+
+/// When an instance should start playing.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+#[cfg_attr(
+	feature = "serde_support",
+	derive(serde::Serialize, serde::Deserialize)
+)]
+pub enum InstanceStartTime {
+	/// Start playing as soon as the audio thread receives
+	/// the instance.
+	#[default]
+	Immediate,
+	/// Start playing after the given number of seconds.
+	Delayed(f64),
+	/// Start playing the next time the metronome with the given
+	/// ID passes the given interval (in beats).
+	NextInterval(MetronomeId, f64),
+}
+
 /// Settings for an instance.
 #[derive(Debug, Copy, Clone)]
 #[cfg_attr(
@@ -92,6 +137,13 @@ pub struct InstanceSettings {
 	pub panning: Value<f64>,
 	/// The position to start playing the instance at (in seconds).
 	pub start_position: f64,
+	/// When the instance should start playing.
+	///
+	/// An instance that's waiting to start already counts toward
+	/// the instance limits of the audio manager, its sound or
+	/// arrangement, and its groups, so it can steal or be refused
+	/// a voice when it's played, not when it starts.
+	pub start_time: InstanceStartTime,
 	/// Whether to play the instance in reverse.
 	pub reverse: bool,
 	/// Whether to fade in the instance from silence, and if so,
@@ -100,8 +152,54 @@ pub struct InstanceSettings {
 	/// Whether the instance should loop, and if so, the position
 	/// it should jump back to when it reaches the end.
 	pub loop_start: InstanceLoopStart,
+	/// The position (in seconds) the instance should jump back
+	/// to the loop start point from, if the instance loops.
+	///
+	/// If `None`, the instance will loop from the end of the
+	/// sound or arrangement.
+	pub loop_end: Option<f64>,
+	/// How many times the instance should loop before playing
+	/// through to the end, if the instance loops.
+	///
+	/// If `None`, the instance will loop forever.
+	pub loop_count: Option<u32>,
+	/// How long (in seconds) the instance should crossfade the
+	/// audio before the loop end point with the audio before the
+	/// loop start point, if not the default for the sound.
+	pub loop_crossfade: Option<f64>,
 	/// Which track to play the instance on.
 	pub track: InstanceTrackIndex,
+	/// The effects to apply to the instance before its
+	/// volume and panning.
+	pub effects: InstanceEffects,
+	/// The send tracks the instance should send its audio to,
+	/// and the volume levels to send it at.
+	///
+	/// Audio is sent after the instance's volume and panning
+	/// are applied.
+	pub sends: InstanceSends,
+	/// The emitter that positions the instance in 3D space, if any.
+	///
+	/// An instance with an emitter gets its panning from its
+	/// direction relative to the listener instead of from its
+	/// panning setting, and its volume is multiplied by the
+	/// volume from its distance to the listener.
+	pub emitter: Option<EmitterSettings>,
+	/// The pan law to use instead of the audio manager's
+	/// default pan law, if any.
+	pub pan_law: Option<PanLaw>,
+	/// How the instance is affected by being occluded or
+	/// obstructed.
+	pub occlusion: OcclusionSettings,
+	/// The resampling quality to use instead of the default
+	/// quality of the sounds being played, if any.
+	pub resampling_quality: Option<ResamplingQuality>,
+	/// How important the instance is when deciding which instance
+	/// to stop when the instance limit is reached.
+	///
+	/// Higher numbers are more important. This is only used with
+	/// [`VoiceStealingPolicy::LowestPriority`].
+	pub priority: i32,
 }
 
 impl InstanceSettings {
@@ -150,6 +248,31 @@ impl InstanceSettings {
 		}
 	}
 
+	/// Sets the instance to start playing after a delay (in seconds).
+	///
+	/// The instance counts toward instance limits while it waits.
+	pub fn start_delay(self, delay: f64) -> Self {
+		Self {
+			start_time: InstanceStartTime::Delayed(delay),
+			..self
+		}
+	}
+
+	/// Sets the instance to start playing the next time a
+	/// metronome passes an interval (in beats).
+	///
+	/// For example, an interval of `1.0` will start the instance
+	/// on the next beat, and an interval of `4.0` will start it
+	/// on the next bar of a song in 4/4 time.
+	///
+	/// The instance counts toward instance limits while it waits.
+	pub fn start_on_interval(self, metronome: impl Into<MetronomeId>, interval: f64) -> Self {
+		Self {
+			start_time: InstanceStartTime::NextInterval(metronome.into(), interval),
+			..self
+		}
+	}
+
 	/// Play the instance in reverse.
 	pub fn reverse(self) -> Self {
 		Self {
@@ -174,6 +297,33 @@ impl InstanceSettings {
 		}
 	}
 
+	/// Sets the position the instance will jump back to the
+	/// loop start point from.
+	pub fn loop_end(self, loop_end: f64) -> Self {
+		Self {
+			loop_end: Some(loop_end),
+			..self
+		}
+	}
+
+	/// Sets how many times the instance will loop before
+	/// playing through to the end.
+	pub fn loop_count(self, loop_count: u32) -> Self {
+		Self {
+			loop_count: Some(loop_count),
+			..self
+		}
+	}
+
+	/// Sets how long the instance will crossfade the audio
+	/// around the loop points.
+	pub fn loop_crossfade(self, loop_crossfade: f64) -> Self {
+		Self {
+			loop_crossfade: Some(loop_crossfade),
+			..self
+		}
+	}
+
 	/// Sets the track the instance will play on.
 	pub fn track<T: Into<InstanceTrackIndex>>(self, track: T) -> Self {
 		Self {
@@ -182,10 +332,64 @@ impl InstanceSettings {
 		}
 	}
 
+	/// Adds an effect to the end of the instance's effect chain.
+	///
+	/// If the instance already has
+	/// [`MAX_INSTANCE_EFFECTS`](super::MAX_INSTANCE_EFFECTS) effects,
+	/// the effect will not be added.
+	pub fn effect(mut self, effect: impl Into<InstanceEffect>) -> Self {
+		self.effects.push(effect).ok();
+		self
+	}
+
+	/// Sets the send tracks the instance will send its audio to.
+	pub fn sends(self, sends: InstanceSends) -> Self {
+		Self { sends, ..self }
+	}
+
+	/// Positions the instance in 3D space with an emitter.
+	pub fn emitter(self, emitter: EmitterSettings) -> Self {
+		Self {
+			emitter: Some(emitter),
+			..self
+		}
+	}
+
+	/// Sets the pan law the instance will use instead of the
+	/// audio manager's default pan law.
+	pub fn pan_law(self, pan_law: PanLaw) -> Self {
+		Self {
+			pan_law: Some(pan_law),
+			..self
+		}
+	}
+
+	/// Sets how the instance is affected by being occluded
+	/// or obstructed.
+	pub fn occlusion(self, occlusion: OcclusionSettings) -> Self {
+		Self { occlusion, ..self }
+	}
+
+	/// Sets the resampling quality the instance will use
+	/// instead of the sound's default quality.
+	pub fn resampling_quality(self, resampling_quality: ResamplingQuality) -> Self {
+		Self {
+			resampling_quality: Some(resampling_quality),
+			..self
+		}
+	}
+
+	/// Sets how important the instance is when deciding which
+	/// instance to stop when the instance limit is reached.
+	pub fn priority(self, priority: i32) -> Self {
+		Self { priority, ..self }
+	}
+
 	pub(crate) fn into_internal(
 		self,
 		duration: f64,
 		default_loop_start: Option<f64>,
+		default_loop_crossfade: f64,
 		default_track: TrackIndex,
 	) -> InternalInstanceSettings {
 		InternalInstanceSettings {
@@ -197,6 +401,7 @@ impl InstanceSettings {
 			} else {
 				self.start_position
 			},
+			start_time: self.start_time,
 			reverse: self.reverse,
 			fade_in_tween: self.fade_in_tween,
 			loop_start: match self.loop_start {
@@ -204,10 +409,20 @@ impl InstanceSettings {
 				InstanceLoopStart::None => None,
 				InstanceLoopStart::Custom(position) => Some(position),
 			},
+			loop_end: self.loop_end,
+			loop_count: self.loop_count,
+			loop_crossfade: self.loop_crossfade.unwrap_or(default_loop_crossfade),
 			track: match self.track {
 				InstanceTrackIndex::DefaultForSound => default_track,
 				InstanceTrackIndex::Custom(track) => track,
 			},
+			effects: self.effects,
+			sends: self.sends,
+			emitter: self.emitter,
+			pan_law: self.pan_law,
+			occlusion: self.occlusion,
+			resampling_quality: self.resampling_quality,
+			priority: self.priority,
 		}
 	}
 }
@@ -220,10 +435,21 @@ impl Default for InstanceSettings {
 			playback_rate: Value::Fixed(1.0),
 			panning: Value::Fixed(0.5),
 			start_position: 0.0,
+			start_time: InstanceStartTime::default(),
 			reverse: false,
 			fade_in_tween: None,
 			loop_start: InstanceLoopStart::default(),
+			loop_end: None,
+			loop_count: None,
+			loop_crossfade: None,
 			track: InstanceTrackIndex::default(),
+			effects: InstanceEffects::new(),
+			sends: InstanceSends::new(),
+			emitter: None,
+			pan_law: None,
+			occlusion: OcclusionSettings::new(),
+			resampling_quality: None,
+			priority: 0,
 		}
 	}
 }
@@ -233,10 +459,21 @@ pub(crate) struct InternalInstanceSettings {
 	pub playback_rate: Value<f64>,
 	pub panning: Value<f64>,
 	pub start_position: f64,
+	pub start_time: InstanceStartTime,
 	pub reverse: bool,
 	pub fade_in_tween: Option<Tween>,
 	pub loop_start: Option<f64>,
+	pub loop_end: Option<f64>,
+	pub loop_count: Option<u32>,
+	pub loop_crossfade: f64,
 	pub track: TrackIndex,
+	pub effects: InstanceEffects,
+	pub sends: InstanceSends,
+	pub emitter: Option<EmitterSettings>,
+	pub pan_law: Option<PanLaw>,
+	pub occlusion: OcclusionSettings,
+	pub resampling_quality: Option<ResamplingQuality>,
+	pub priority: i32,
 }
 
 /// Settings for pausing an instance.
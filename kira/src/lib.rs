@@ -120,6 +120,7 @@ pub mod parameter;
 mod playable;
 pub mod sequence;
 pub mod sound;
+pub mod spatial;
 mod static_container;
 mod tempo;
 mod util;
@@ -127,7 +128,7 @@ mod value;
 
 pub use command::producer::CommandError;
 pub use duration::Duration;
-pub use frame::Frame;
+pub use frame::{Frame, PanLaw};
 pub use playable::PlayableId;
 pub use tempo::Tempo;
 pub use value::{CachedValue, Value};
@@ -6,16 +6,17 @@ use crate::{
 	mixer::{SendTrackId, SubTrackId},
 	parameter::ParameterId,
 	sound::SoundId,
+	spatial::ListenerId,
 };
 
 use indexmap::IndexSet;
 
 use super::{
 	error::{
-		AddArrangementError, AddGroupError, AddMetronomeError, AddParameterError,
+		AddArrangementError, AddGroupError, AddListenerError, AddMetronomeError, AddParameterError,
 		AddSendTrackError, AddSoundError, AddStreamError, AddSubTrackError, RemoveArrangementError,
-		RemoveGroupError, RemoveMetronomeError, RemoveParameterError, RemoveSendTrackError,
-		RemoveSoundError, RemoveStreamError, RemoveSubTrackError,
+		RemoveGroupError, RemoveListenerError, RemoveMetronomeError, RemoveParameterError,
+		RemoveSendTrackError, RemoveSoundError, RemoveStreamError, RemoveSubTrackError,
 	},
 	AudioManagerSettings,
 };
@@ -29,6 +30,7 @@ pub struct ActiveIds {
 	pub active_group_ids: IndexSet<GroupId>,
 	pub active_metronome_ids: IndexSet<MetronomeId>,
 	pub active_stream_ids: IndexSet<AudioStreamId>,
+	pub active_listener_ids: IndexSet<ListenerId>,
 }
 
 impl ActiveIds {
@@ -42,6 +44,8 @@ impl ActiveIds {
 			active_group_ids: IndexSet::with_capacity(settings.num_groups),
 			active_metronome_ids: IndexSet::with_capacity(settings.num_metronomes),
 			active_stream_ids: IndexSet::with_capacity(settings.num_streams),
+			// the main listener is always there and isn't tracked here
+			active_listener_ids: IndexSet::with_capacity(settings.num_listeners.saturating_sub(1)),
 		}
 	}
 
@@ -153,6 +157,21 @@ impl ActiveIds {
 		Ok(())
 	}
 
+	pub fn add_listener_id(&mut self, id: ListenerId) -> Result<(), AddListenerError> {
+		if self.active_listener_ids.len() >= self.active_listener_ids.capacity() {
+			return Err(AddListenerError::ListenerLimitReached);
+		}
+		self.active_listener_ids.insert(id);
+		Ok(())
+	}
+
+	pub fn remove_listener_id(&mut self, id: ListenerId) -> Result<(), RemoveListenerError> {
+		if !self.active_listener_ids.remove(&id) {
+			return Err(RemoveListenerError::NoListenerWithId(id));
+		}
+		Ok(())
+	}
+
 	pub fn add_stream_id(&mut self, id: AudioStreamId) -> Result<(), AddStreamError> {
 		if self.active_stream_ids.len() >= self.active_stream_ids.capacity() {
 			return Err(AddStreamError::StreamLimitReached);
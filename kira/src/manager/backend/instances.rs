@@ -1,28 +1,357 @@
 use crate::{
 	command::InstanceCommand,
-	group::groups::Groups,
-	instance::{Instance, InstanceId, StopInstanceSettings},
-	parameter::Parameters,
+	frame::PanLaw,
+	group::{groups::Groups, GroupId},
+	instance::{Instance, InstanceId, StopInstanceSettings, VoiceStealingPolicy},
+	manager::AudioManagerSettings,
+	metronome::Metronomes,
+	mixer::TrackIndex,
+	parameter::{tween::Tween, Parameters},
 	playable::{PlayableId, Playables},
+	spatial::{hrtf::HrtfState, SpatialScene},
 	static_container::{index_map::StaticIndexMap, vec::StaticVec},
 };
 
-use super::mixer::Mixer;
+use super::{mixer::Mixer, sequences::Sequences};
+
+/// How long it takes for a stolen instance to fade out (in seconds).
+const STOLEN_INSTANCE_FADE_DURATION: f64 = 0.01;
 
 pub(crate) struct Instances {
 	instances: StaticIndexMap<InstanceId, Instance>,
 	instances_to_remove: StaticVec<InstanceId>,
+	max_instances: usize,
+	max_virtual_instances: usize,
+	voice_stealing_policy: VoiceStealingPolicy,
+	virtual_instance_threshold: f64,
+	pan_law: PanLaw,
+	hrtf_states: StaticIndexMap<InstanceId, HrtfState>,
+	/// Whether instances have started or stopped since the
+	/// groups' active instance counts were updated.
+	group_counts_outdated: bool,
 }
 
 impl Instances {
-	pub fn new(capacity: usize) -> Self {
+	pub fn new(settings: &AudioManagerSettings) -> Self {
+		// stolen instances keep playing while they fade out,
+		// so leave room for each instance to be replaced once
+		let capacity = settings.num_instances * 2 + settings.num_virtual_instances;
 		Self {
 			instances: StaticIndexMap::new(capacity),
 			instances_to_remove: StaticVec::new(capacity),
+			max_instances: settings.num_instances,
+			max_virtual_instances: settings.num_virtual_instances,
+			voice_stealing_policy: settings.voice_stealing_policy,
+			virtual_instance_threshold: settings.virtual_instance_threshold,
+			pan_law: settings.pan_law,
+			hrtf_states: StaticIndexMap::new(settings.num_hrtf_instances),
+			group_counts_outdated: false,
+		}
+	}
+
+	pub fn take_group_counts_outdated(&mut self) -> bool {
+		std::mem::take(&mut self.group_counts_outdated)
+	}
+
+	/// Returns true if the playable or sequence an instance
+	/// belongs to has the specified group as an ancestor.
+	fn is_instance_in_group(
+		instance: &Instance,
+		id: GroupId,
+		playables: &Playables,
+		sequences: &Sequences,
+		all_groups: &Groups,
+	) -> bool {
+		if let Some(playable) = playables.playable(instance.playable_id()) {
+			if playable.is_in_group(id, all_groups) {
+				return true;
+			}
+		}
+		if let Some(groups) = instance
+			.sequence_id()
+			.and_then(|sequence_id| sequences.groups(sequence_id))
+		{
+			if groups.has_ancestor(id, all_groups) {
+				return true;
+			}
+		}
+		false
+	}
+
+	/// Returns the number of instances in a group that
+	/// aren't fading out to a stop.
+	pub fn num_instances_in_group(
+		&self,
+		id: GroupId,
+		playables: &Playables,
+		sequences: &Sequences,
+		all_groups: &Groups,
+	) -> usize {
+		self.instances
+			.iter()
+			.filter(|(_, instance)| {
+				!instance.stopping()
+					&& Self::is_instance_in_group(instance, id, playables, sequences, all_groups)
+			})
+			.count()
+	}
+
+	/// Returns the number of instances that are producing audio
+	/// and aren't fading out to a stop.
+	fn num_real_instances(&self) -> usize {
+		self.instances
+			.iter()
+			.filter(|(_, instance)| !instance.stopping() && !instance.is_virtual())
+			.count()
+	}
+
+	/// Returns the number of virtual instances that aren't
+	/// fading out to a stop.
+	fn num_virtual_instances(&self) -> usize {
+		self.instances
+			.iter()
+			.filter(|(_, instance)| !instance.stopping() && instance.is_virtual())
+			.count()
+	}
+
+	/// Returns the volume and playback rate of the groups an
+	/// instance's playable and sequence belong to.
+	fn group_multipliers(
+		instance: &Instance,
+		playables: &Playables,
+		groups: &Groups,
+		sequences: &Sequences,
+	) -> Option<(f64, f64)> {
+		let playable = playables.playable(instance.playable_id())?;
+		let sequence_groups = instance.sequence_id().and_then(|id| sequences.groups(id));
+		Some(match sequence_groups {
+			Some(sequence_groups) => groups.multipliers(&[playable.groups(), sequence_groups]),
+			None => groups.multipliers(&[playable.groups()]),
+		})
+	}
+
+	/// Picks an instance to stop to make room for a new instance,
+	/// or returns `None` if the new instance shouldn't be played.
+	///
+	/// Only instances that `filter` returns `true` for are considered.
+	fn choose_instance_to_steal(
+		&self,
+		new_instance: &Instance,
+		policy: VoiceStealingPolicy,
+		filter: impl Fn(&Instance) -> bool,
+	) -> Option<InstanceId> {
+		let mut candidates = self
+			.instances
+			.iter()
+			.filter(|(_, instance)| !instance.stopping() && filter(instance));
+		match policy {
+			VoiceStealingPolicy::Oldest => candidates.next(),
+			VoiceStealingPolicy::Quietest => candidates.min_by(|(_, a), (_, b)| {
+				a.effective_volume()
+					.partial_cmp(&b.effective_volume())
+					.unwrap_or(std::cmp::Ordering::Equal)
+			}),
+			// min_by_key returns the first of several equal elements,
+			// so ties go to the oldest instance
+			VoiceStealingPolicy::LowestPriority => candidates
+				.min_by_key(|(_, instance)| instance.priority())
+				.filter(|(_, instance)| instance.priority() <= new_instance.priority()),
+			VoiceStealingPolicy::Refuse => None,
+		}
+		.map(|(id, _)| *id)
+	}
+
+	/// Checks whether there's room for a new instance if `limit`
+	/// instances that `filter` returns `true` for are already
+	/// playing. If `steal` is `true`, one of those instances
+	/// is stopped to make room.
+	///
+	/// Returns `false` if the new instance shouldn't be played.
+	fn enforce_instance_limit(
+		&mut self,
+		new_instance: &Instance,
+		limit: usize,
+		policy: VoiceStealingPolicy,
+		steal: bool,
+		filter: impl Fn(&Instance) -> bool,
+	) -> bool {
+		let num_playing_instances = self
+			.instances
+			.iter()
+			.filter(|(_, instance)| !instance.stopping() && filter(instance))
+			.count();
+		if num_playing_instances < limit {
+			return true;
+		}
+		match self.choose_instance_to_steal(new_instance, policy, filter) {
+			Some(id) => {
+				if steal {
+					if let Some(instance) = self.instances.get_mut(&id) {
+						instance.stop(
+							StopInstanceSettings::new()
+								.fade_tween(Tween::linear(STOLEN_INSTANCE_FADE_DURATION)),
+						);
+					}
+				}
+				true
+			}
+			None => false,
+		}
+	}
+
+	/// Checks the playable's instance limit, the instance limits
+	/// of its groups, and the global instance limit, stealing
+	/// a playing instance for each limit that has been reached
+	/// if `steal` is `true`.
+	///
+	/// Returns `false` if the new instance shouldn't be played.
+	fn enforce_instance_limits(
+		&mut self,
+		new_instance: &Instance,
+		playable_instance_limit: Option<(usize, VoiceStealingPolicy)>,
+		playables: &Playables,
+		sequences: &Sequences,
+		all_groups: &Groups,
+		steal: bool,
+	) -> bool {
+		if let Some((limit, policy)) = playable_instance_limit {
+			let playable_id = new_instance.playable_id();
+			if !self.enforce_instance_limit(new_instance, limit, policy, steal, |instance| {
+				instance.playable_id() == playable_id
+			}) {
+				return false;
+			}
+		}
+		for (group_id, group) in all_groups.iter() {
+			if let Some((limit, policy)) = group.instance_limit() {
+				let in_group = |instance: &Instance| {
+					Self::is_instance_in_group(
+						instance, *group_id, playables, sequences, all_groups,
+					)
+				};
+				if in_group(new_instance)
+					&& !self.enforce_instance_limit(new_instance, limit, policy, steal, in_group)
+				{
+					return false;
+				}
+			}
+		}
+		// virtual instances don't count toward the global instance limit
+		new_instance.is_virtual()
+			|| self.enforce_instance_limit(
+				new_instance,
+				self.max_instances,
+				self.voice_stealing_policy,
+				steal,
+				|instance| !instance.is_virtual(),
+			)
+	}
+
+	/// Makes room for a new instance, stealing playing instances
+	/// if any of the instance limits that apply to it have been
+	/// reached.
+	///
+	/// Returns `false` if the new instance shouldn't be played.
+	fn make_room_for(
+		&mut self,
+		new_instance: &Instance,
+		playable_instance_limit: Option<(usize, VoiceStealingPolicy)>,
+		playables: &Playables,
+		sequences: &Sequences,
+		all_groups: &Groups,
+	) -> bool {
+		// make sure none of the limits refuse the new instance before
+		// stealing anything, so instances aren't stopped to make room
+		// for an instance that won't play
+		if !self.enforce_instance_limits(
+			new_instance,
+			playable_instance_limit,
+			playables,
+			sequences,
+			all_groups,
+			false,
+		) {
+			return false;
+		}
+		self.enforce_instance_limits(
+			new_instance,
+			playable_instance_limit,
+			playables,
+			sequences,
+			all_groups,
+			true,
+		);
+		// if there's no room left for instances that are fading out,
+		// cut off the one that started fading out the longest time ago.
+		// instances that are still playing are never cut off
+		if self.instances.len() >= self.instances.capacity() {
+			let index = match self
+				.instances
+				.iter()
+				.position(|(_, instance)| instance.stopping())
+			{
+				Some(index) => index,
+				None => return false,
+			};
+			if let Some((_, instance)) = self.instances.get_index_mut(index) {
+				instance.stop(StopInstanceSettings::new().fade_tween(None));
+			}
+			if let Some((instance_id, _)) = self.instances.shift_remove_index(index) {
+				self.hrtf_states.remove(&instance_id);
+				self.group_counts_outdated = true;
+			}
+		}
+		true
+	}
+
+	/// Starts playing an instance if there's room for it.
+	pub fn play(
+		&mut self,
+		instance_id: InstanceId,
+		mut instance: Instance,
+		playables: &mut Playables,
+		sequences: &Sequences,
+		all_groups: &Groups,
+		spatial_scene: &SpatialScene,
+	) {
+		self.group_counts_outdated = true;
+		let playable_id = instance.playable_id();
+		let playable_instance_limit = match playables.playable_mut(playable_id) {
+			Some(playable) if !playable.cooling_down() => playable.instance_limit(),
+			_ => return,
+		};
+		// the instance's groups and emitter affect its volume,
+		// so apply them before deciding whether it's virtual
+		if let Some((volume, playback_rate)) =
+			Self::group_multipliers(&instance, playables, all_groups, sequences)
+		{
+			instance.set_group_multipliers(volume, playback_rate);
+		}
+		instance.update_spatialization(0.0, spatial_scene);
+		instance.set_virtual(
+			instance.volume() < self.virtual_instance_threshold
+				&& self.num_virtual_instances() < self.max_virtual_instances,
+		);
+		if self.make_room_for(
+			&instance,
+			playable_instance_limit,
+			playables,
+			sequences,
+			all_groups,
+		) {
+			self.instances.try_insert(instance_id, instance).ok();
+			if let Some(mut playable) = playables.playable_mut(playable_id) {
+				playable.start_cooldown();
+			}
+		} else {
+			// let the instance handle know the instance
+			// will never play
+			instance.stop(StopInstanceSettings::new().fade_tween(None));
 		}
 	}
 
 	pub fn stop_instances_of(&mut self, playable: PlayableId, settings: StopInstanceSettings) {
+		self.group_counts_outdated = true;
 		for (_, instance) in &mut self.instances {
 			if instance.playable_id() == playable {
 				instance.stop(settings);
@@ -34,20 +363,21 @@ impl Instances {
 		&mut self,
 		command: InstanceCommand,
 		playables: &mut Playables,
+		sequences: &Sequences,
 		all_groups: &Groups,
+		spatial_scene: &SpatialScene,
 	) {
 		match command {
-			InstanceCommand::Play(instance_id, instance) => {
-				if let Some(mut playable) = playables.playable_mut(instance.playable_id()) {
-					if !playable.cooling_down() {
-						// if we're at the instance limit, remove the instance that was
-						// started the longest time ago.
-						if self.instances.len() >= self.instances.capacity() {
-							self.instances.shift_remove_index(0);
-						}
-						self.instances.try_insert(instance_id, instance).ok();
-						playable.start_cooldown();
-					}
+			InstanceCommand::Play(instance_id, mut instance) => {
+				if let Some(instance) = instance.take() {
+					self.play(
+						instance_id,
+						instance,
+						playables,
+						sequences,
+						all_groups,
+						spatial_scene,
+					);
 				}
 			}
 			InstanceCommand::SetInstanceVolume(id, value) => {
@@ -75,6 +405,66 @@ impl Instances {
 					instance.seek_to(position);
 				}
 			}
+			InstanceCommand::SetInstanceLoopRegion(id, start, end) => {
+				if let Some(instance) = self.instances.get_mut(&id) {
+					instance.set_loop_region(start, end);
+				}
+			}
+			InstanceCommand::SetInstanceLoopCount(id, count) => {
+				if let Some(instance) = self.instances.get_mut(&id) {
+					instance.set_loop_count(count);
+				}
+			}
+			InstanceCommand::ExitInstanceLoop(id) => {
+				if let Some(instance) = self.instances.get_mut(&id) {
+					instance.exit_loop();
+				}
+			}
+			InstanceCommand::SetInstanceEffect(id, index, effect) => {
+				if let Some(instance) = self.instances.get_mut(&id) {
+					instance.set_effect(index, effect);
+				}
+			}
+			InstanceCommand::RemoveInstanceEffect(id, index) => {
+				if let Some(instance) = self.instances.get_mut(&id) {
+					instance.remove_effect(index);
+				}
+			}
+			InstanceCommand::SetInstanceEffectEnabled(id, index, enabled) => {
+				if let Some(instance) = self.instances.get_mut(&id) {
+					instance.set_effect_enabled(index, enabled);
+				}
+			}
+			InstanceCommand::SetInstanceSend(id, send_track, volume) => {
+				if let Some(instance) = self.instances.get_mut(&id) {
+					instance.set_send(send_track, volume);
+				}
+			}
+			InstanceCommand::RemoveInstanceSend(id, send_track) => {
+				if let Some(instance) = self.instances.get_mut(&id) {
+					instance.remove_send(send_track);
+				}
+			}
+			InstanceCommand::SetInstanceEmitterPosition(id, position) => {
+				if let Some(instance) = self.instances.get_mut(&id) {
+					instance.set_emitter_position(position);
+				}
+			}
+			InstanceCommand::SetInstanceEmitterVelocity(id, velocity) => {
+				if let Some(instance) = self.instances.get_mut(&id) {
+					instance.set_emitter_velocity(velocity);
+				}
+			}
+			InstanceCommand::SetInstanceOcclusion(id, occlusion) => {
+				if let Some(instance) = self.instances.get_mut(&id) {
+					instance.set_occlusion(occlusion);
+				}
+			}
+			InstanceCommand::SetInstanceObstruction(id, obstruction) => {
+				if let Some(instance) = self.instances.get_mut(&id) {
+					instance.set_obstruction(obstruction);
+				}
+			}
 			InstanceCommand::PauseInstance(id, settings) => {
 				if let Some(instance) = self.instances.get_mut(&id) {
 					instance.pause(settings);
@@ -88,6 +478,7 @@ impl Instances {
 			InstanceCommand::StopInstance(id, settings) => {
 				if let Some(instance) = self.instances.get_mut(&id) {
 					instance.stop(settings);
+					self.group_counts_outdated = true;
 				}
 			}
 			InstanceCommand::PauseInstancesOf(playable, settings) => {
@@ -126,6 +517,7 @@ impl Instances {
 				}
 			}
 			InstanceCommand::StopGroup(id, settings) => {
+				self.group_counts_outdated = true;
 				for (_, instance) in &mut self.instances {
 					if let Some(playable) = playables.playable(instance.playable_id()) {
 						if playable.is_in_group(id, all_groups) {
@@ -149,6 +541,7 @@ impl Instances {
 				}
 			}
 			InstanceCommand::StopInstancesOfSequence(id, settings) => {
+				self.group_counts_outdated = true;
 				for (_, instance) in &mut self.instances {
 					if instance.sequence_id() == Some(id) {
 						instance.stop(settings);
@@ -158,18 +551,87 @@ impl Instances {
 		}
 	}
 
+	/// Applies the volume and playback rate of the groups each
+	/// instance's playable and sequence belong to.
+	pub fn update_group_multipliers(
+		&mut self,
+		playables: &Playables,
+		groups: &Groups,
+		sequences: &Sequences,
+	) {
+		for (_, instance) in &mut self.instances {
+			if let Some((volume, playback_rate)) =
+				Self::group_multipliers(instance, playables, groups, sequences)
+			{
+				instance.set_group_multipliers(volume, playback_rate);
+			}
+		}
+	}
+
 	pub fn process(
 		&mut self,
 		dt: f64,
 		playables: &Playables,
 		mixer: &mut Mixer,
 		parameters: &Parameters,
+		metronomes: &Metronomes,
+		spatial_scene: &SpatialScene,
 	) {
 		// TODO: simplify this code (preferably by removing self.instances_to_remove)
 		// while making sure every sample of the sound gets played before the instance is removed
+		let mut num_real_instances = self.num_real_instances();
+		let mut num_virtual_instances = self.num_virtual_instances();
 		for (instance_id, instance) in &mut self.instances {
+			instance.update_start_time(dt, metronomes);
+			instance.update_spatialization(dt, spatial_scene);
 			if instance.playing() {
-				mixer.add_input(instance.track_index(), instance.get_sample(playables));
+				// virtualize instances that are too quiet to hear, and make
+				// them real again once they're audible and there's room.
+				// fades are left out so instances don't flip back and forth
+				// while fading in
+				let audible = instance.volume() >= self.virtual_instance_threshold;
+				if instance.is_virtual() {
+					if audible && num_real_instances < self.max_instances {
+						instance.set_virtual(false);
+						if !instance.stopping() {
+							num_real_instances += 1;
+							num_virtual_instances -= 1;
+						}
+					}
+				} else if !audible && num_virtual_instances < self.max_virtual_instances {
+					instance.set_virtual(true);
+					if !instance.stopping() {
+						num_real_instances -= 1;
+						num_virtual_instances += 1;
+					}
+				}
+				if !instance.is_virtual() {
+					let hrtf = match spatial_scene.hrtf_dataset() {
+						Some(dataset) if instance.uses_hrtf() => {
+							if self.hrtf_states.get(instance_id).is_none() {
+								self.hrtf_states
+									.try_insert(*instance_id, HrtfState::new())
+									.ok();
+							}
+							self.hrtf_states
+								.get_mut(instance_id)
+								.map(|state| (dataset, state))
+						}
+						_ => None,
+					};
+					let output = instance.get_sample(dt, playables, parameters, self.pan_law, hrtf);
+					mixer.add_input(instance.track_index(), output);
+					for (send_track, volume) in instance.sends() {
+						mixer.add_input(TrackIndex::Send(send_track), output * volume as f32);
+					}
+				} else {
+					// virtual instances are still playing, so they can
+					// still make tracks duck
+					mixer.mark_active(instance.track_index());
+					for (send_track, _) in instance.sends() {
+						mixer.mark_active(TrackIndex::Send(send_track));
+					}
+				}
 			}
 			if instance.finished() {
 				self.instances_to_remove.try_push(*instance_id).ok();
@@ -178,6 +640,8 @@ impl Instances {
 		}
 		for instance_id in self.instances_to_remove.drain(..) {
 			self.instances.shift_remove(&instance_id);
+			self.group_counts_outdated = true;
+			self.hrtf_states.remove(&instance_id);
 		}
 	}
 }
@@ -8,7 +8,7 @@ use crate::{
 	frame::Frame,
 	mixer::{
 		effect::{Effect, EffectId},
-		SendTrackId, SubTrackId, Track, TrackIndex, TrackKind,
+		DuckingSettings, SendTrackId, SubTrackId, Track, TrackIndex, TrackKind,
 	},
 	parameter::Parameters,
 	static_container::index_map::StaticIndexMap,
@@ -19,6 +19,9 @@ pub(crate) struct Mixer {
 	main_track: Track,
 	sub_tracks: StaticIndexMap<SubTrackId, Owned<Track>>,
 	send_tracks: StaticIndexMap<SendTrackId, Owned<Track>>,
+	/// The order to process sub-tracks and send tracks in so that
+	/// each track is processed after the tracks it depends on.
+	processing_order: Option<Owned<Vec<TrackIndex>>>,
 }
 
 impl Mixer {
@@ -27,6 +30,7 @@ impl Mixer {
 			main_track: Track::new_main_track(),
 			sub_tracks: StaticIndexMap::new(sub_track_capacity),
 			send_tracks: StaticIndexMap::new(send_track_capacity),
+			processing_order: None,
 		}
 	}
 
@@ -36,6 +40,24 @@ impl Mixer {
 			MixerCommand::SetTrackVolume(index, volume) => {
 				self.set_track_volume(index, volume);
 			}
+			MixerCommand::SetTrackParent(index, parent_track) => {
+				self.set_track_parent(index, parent_track);
+			}
+			MixerCommand::SetTrackSend(index, send_track, volume) => {
+				self.set_track_send(index, send_track, volume);
+			}
+			MixerCommand::RemoveTrackSend(index, send_track) => {
+				self.remove_track_send(index, send_track);
+			}
+			MixerCommand::RetargetTrackSend(index, from, to) => {
+				self.retarget_track_send(index, from, to);
+			}
+			MixerCommand::SetTrackDucking(index, settings) => {
+				self.set_track_ducking(index, settings);
+			}
+			MixerCommand::RemoveTrackDucking(index, trigger_track) => {
+				self.remove_track_ducking(index, trigger_track);
+			}
 			MixerCommand::AddEffect(index, id, effect, settings) => {
 				self.add_effect(index, id, effect, settings);
 			}
@@ -54,6 +76,9 @@ impl Mixer {
 			MixerCommand::RemoveEffect(track_index, effect_id) => {
 				self.remove_effect(track_index, effect_id);
 			}
+			MixerCommand::SetProcessingOrder(processing_order) => {
+				self.set_processing_order(processing_order);
+			}
 		}
 	}
 
@@ -65,12 +90,16 @@ impl Mixer {
 			TrackKind::Sub { id, .. } => {
 				self.sub_tracks.try_insert(*id, track).ok();
 			}
-			TrackKind::Send { id } => {
+			TrackKind::Send { id, .. } => {
 				self.send_tracks.try_insert(*id, track).ok();
 			}
 		}
 	}
 
+	pub fn set_processing_order(&mut self, processing_order: Owned<Vec<TrackIndex>>) {
+		self.processing_order = Some(processing_order);
+	}
+
 	pub fn set_track_volume(&mut self, index: TrackIndex, volume: Value<f64>) {
 		match index {
 			TrackIndex::Main => {
@@ -89,6 +118,47 @@ impl Mixer {
 		}
 	}
 
+	pub fn set_track_parent(&mut self, index: TrackIndex, parent_track: TrackIndex) {
+		if let Some(track) = self.track_mut(index) {
+			track.set_parent_track(parent_track);
+		}
+	}
+
+	pub fn set_track_send(
+		&mut self,
+		index: TrackIndex,
+		send_track: SendTrackId,
+		volume: Value<f64>,
+	) {
+		if let Some(track) = self.track_mut(index) {
+			track.set_send(send_track, volume);
+		}
+	}
+
+	pub fn remove_track_send(&mut self, index: TrackIndex, send_track: SendTrackId) {
+		if let Some(track) = self.track_mut(index) {
+			track.remove_send(send_track);
+		}
+	}
+
+	pub fn retarget_track_send(&mut self, index: TrackIndex, from: SendTrackId, to: SendTrackId) {
+		if let Some(track) = self.track_mut(index) {
+			track.retarget_send(from, to);
+		}
+	}
+
+	pub fn set_track_ducking(&mut self, index: TrackIndex, settings: DuckingSettings) {
+		if let Some(track) = self.track_mut(index) {
+			track.set_ducking(settings);
+		}
+	}
+
+	pub fn remove_track_ducking(&mut self, index: TrackIndex, trigger_track: TrackIndex) {
+		if let Some(track) = self.track_mut(index) {
+			track.remove_ducking(trigger_track);
+		}
+	}
+
 	pub fn add_effect(
 		&mut self,
 		index: TrackIndex,
@@ -119,6 +189,13 @@ impl Mixer {
 
 	pub fn remove_send_track(&mut self, id: SendTrackId) {
 		self.send_tracks.remove(&id);
+		// free up the send slots that pointed to the removed track
+		for (_, track) in self.sub_tracks.iter_mut() {
+			track.remove_send(id);
+		}
+		for (_, track) in self.send_tracks.iter_mut() {
+			track.remove_send(id);
+		}
 	}
 
 	pub fn set_effect_enabled(
@@ -201,78 +278,142 @@ impl Mixer {
 		};
 	}
 
-	pub fn add_input(&mut self, index: TrackIndex, input: Frame) {
+	fn track(&self, index: TrackIndex) -> Option<&Track> {
 		match index {
-			TrackIndex::Main => {
-				self.main_track.add_input(input);
-			}
-			TrackIndex::Sub(id) => {
-				if let Some(track) = self.sub_tracks.get_mut(&id) {
-					track.add_input(input);
-				}
-			}
-			TrackIndex::Send(id) => {
-				if let Some(track) = self.send_tracks.get_mut(&id) {
-					track.add_input(input);
-				}
-			}
+			TrackIndex::Main => Some(&self.main_track),
+			TrackIndex::Sub(id) => self.sub_tracks.get(&id).map(|track| &**track),
+			TrackIndex::Send(id) => self.send_tracks.get(&id).map(|track| &**track),
 		}
 	}
 
-	/// Processes a sub-track.
-	fn process_sub_track(&mut self, id: SubTrackId, dt: f64, parameters: &Parameters) -> Frame {
-		// process all children of this sub-track and accumulate their outputs
-		let mut children_input = Frame::from_mono(0.0);
-		for i in 0..self.sub_tracks.len() {
-			let (child_id, child_track) = self.sub_tracks.get_index(i).unwrap();
-			let child_id = *child_id;
-			if let Some(parent_track) = child_track.parent_track() {
-				if parent_track == TrackIndex::Sub(id) {
-					children_input += self.process_sub_track(child_id, dt, parameters);
-				}
-			}
+	fn track_mut(&mut self, index: TrackIndex) -> Option<&mut Track> {
+		match index {
+			TrackIndex::Main => Some(&mut self.main_track),
+			TrackIndex::Sub(id) => self.sub_tracks.get_mut(&id).map(|track| &mut **track),
+			TrackIndex::Send(id) => self.send_tracks.get_mut(&id).map(|track| &mut **track),
 		}
-		if let Some(sub_track) = self.sub_tracks.get_mut(&id) {
-			// process this track
-			sub_track.add_input(children_input);
-			let output = sub_track.process(dt, parameters);
-			// route this track's output to send tracks
-			if let TrackKind::Sub { sends, .. } = &sub_track.kind() {
-				for (send_track_id, send_volume) in sends.iter() {
-					if let Some(send_track) = self.send_tracks.get_mut(send_track_id) {
-						send_track.add_input(output * send_volume.value() as f32);
-					}
-				}
-				return output;
+	}
+
+	/// Adds audio from an instance or audio stream to a track.
+	pub fn add_input(&mut self, index: TrackIndex, input: Frame) {
+		if let Some(track) = self.track_mut(index) {
+			track.add_input(input);
+			track.mark_active();
+		}
+	}
+
+	/// Marks a track as receiving audio from instances this
+	/// frame without giving it any input.
+	pub fn mark_active(&mut self, index: TrackIndex) {
+		if let Some(track) = self.track_mut(index) {
+			track.mark_active();
+		}
+	}
+
+	/// Moves each of a track's ducking rules towards or away
+	/// from the ducked volume, depending on whether the last
+	/// frame of audio from the rule's trigger track triggers it.
+	fn update_track_ducking(&mut self, index: TrackIndex, dt: f64) {
+		let num_ducking_rules = match self.track(index) {
+			Some(track) => track.num_ducking_rules(),
+			None => return,
+		};
+		for i in 0..num_ducking_rules {
+			let triggered = match self.track(index).and_then(|track| track.ducking_rule(i)) {
+				Some((trigger_track, trigger)) => match self.track(trigger_track) {
+					Some(trigger_track) => trigger_track.triggers_ducking(trigger),
+					None => false,
+				},
+				None => continue,
+			};
+			if let Some(track) = self.track_mut(index) {
+				track.update_ducking(i, dt, triggered);
 			}
 		}
-		Frame::from_mono(0.0)
 	}
 
-	/// Processes all top-level sub-tracks (sub-tracks that output directly
-	/// to the main track) and sends their output to the main and send tracks.
-	fn process_sub_tracks(&mut self, dt: f64, parameters: &Parameters) {
+	/// Moves each track's ducking towards or away from
+	/// the ducked volume.
+	fn update_ducking(&mut self, dt: f64) {
 		for i in 0..self.sub_tracks.len() {
-			let (id, track) = self.sub_tracks.get_index(i).unwrap();
-			let id = *id;
-			if let Some(TrackIndex::Main) = track.parent_track() {
-				let output = self.process_sub_track(id, dt, parameters);
-				self.main_track.add_input(output);
+			let (id, _) = self.sub_tracks.get_index(i).unwrap();
+			self.update_track_ducking(TrackIndex::Sub(*id), dt);
+		}
+		for i in 0..self.send_tracks.len() {
+			let (id, _) = self.send_tracks.get_index(i).unwrap();
+			self.update_track_ducking(TrackIndex::Send(*id), dt);
+		}
+	}
+
+	/// Gives each of a track's effects the output of the
+	/// track it uses as a sidechain.
+	fn update_sidechain_inputs(&mut self, index: TrackIndex) {
+		let num_effects = match self.track(index) {
+			Some(track) => track.num_effects(),
+			None => return,
+		};
+		for i in 0..num_effects {
+			let sidechain = match self
+				.track(index)
+				.and_then(|track| track.effect_sidechain(i))
+			{
+				Some(sidechain) => sidechain,
+				None => continue,
+			};
+			// if the sidechain track has been removed, the effect
+			// stops receiving its output
+			let sidechain_input = match self.track(sidechain) {
+				Some(sidechain_track) => sidechain_track.output(),
+				None => Frame::from_mono(0.0),
+			};
+			if let Some(track) = self.track_mut(index) {
+				track.set_effect_sidechain_input(i, sidechain_input);
 			}
 		}
 	}
 
-	/// Processes all send tracks and sends their output to the main track.
-	fn process_send_tracks(&mut self, dt: f64, parameters: &Parameters) {
-		for (_, track) in &mut self.send_tracks {
-			self.main_track.add_input(track.process(dt, parameters));
+	/// Processes a sub-track or send track and routes its output
+	/// to its parent track and send tracks.
+	fn process_track(&mut self, index: TrackIndex, dt: f64, parameters: &Parameters) {
+		self.update_sidechain_inputs(index);
+		let (output, active, parent_track, num_sends) = match self.track_mut(index) {
+			Some(track) => (
+				track.process(dt, parameters),
+				track.active(),
+				track.parent_track(),
+				track.num_sends(),
+			),
+			None => return,
+		};
+		if let Some(parent_track) = parent_track.and_then(|index| self.track_mut(index)) {
+			parent_track.add_input(output);
+			if active {
+				parent_track.mark_active();
+			}
+		}
+		for i in 0..num_sends {
+			let send = self.track(index).and_then(|track| track.send(i));
+			if let Some((send_track_id, send_volume)) = send {
+				if let Some(send_track) = self.send_tracks.get_mut(&send_track_id) {
+					send_track.add_input(output * send_volume as f32);
+					if active {
+						send_track.mark_active();
+					}
+				}
+			}
 		}
 	}
 
 	/// Processes all tracks.
 	pub fn process(&mut self, dt: f64, parameters: &Parameters) -> Frame {
-		self.process_sub_tracks(dt, parameters);
-		self.process_send_tracks(dt, parameters);
+		self.update_ducking(dt);
+		if let Some(processing_order) = self.processing_order.take() {
+			for index in processing_order.iter() {
+				self.process_track(*index, dt, parameters);
+			}
+			self.processing_order = Some(processing_order);
+		}
+		self.update_sidechain_inputs(TrackIndex::Main);
 		self.main_track.process(dt, parameters)
 	}
 }
@@ -1,55 +1,110 @@
 use basedrop::{Collector, Owned};
 
 use crate::{
-	mixer::{SendTrackId, SendTrackSettings, SubTrackId, SubTrackSettings, Track, TrackSends},
+	mixer::{
+		effect::{Effect, EffectId, EffectSettings},
+		routing::TrackRouting,
+		DuckingSettings, DuckingTrigger, SendTrackId, SendTrackSettings, SubTrackId,
+		SubTrackSettings, Track, TrackDucking, TrackIndex, TrackKind, TrackSends,
+	},
 	parameter::Parameters,
 	Frame,
 };
 
 use super::Mixer;
 
+/// Adds a track to the mixer and tells the mixer what order
+/// to process its tracks in, like the main thread would.
+fn add_track(mixer: &mut Mixer, routing: &mut TrackRouting, collector: &Collector, track: Track) {
+	let index = match track.kind() {
+		TrackKind::Main => panic!("the main track is always part of the mixer"),
+		TrackKind::Sub { id, .. } => TrackIndex::Sub(*id),
+		TrackKind::Send { id, .. } => TrackIndex::Send(*id),
+	};
+	routing.add_track(
+		index,
+		track.parent_track(),
+		(0..track.num_sends()).filter_map(|i| track.send(i).map(|(id, _)| id)),
+		track.num_sends(),
+	);
+	mixer.add_track(Owned::new(&collector.handle(), track));
+	mixer.set_processing_order(Owned::new(&collector.handle(), routing.processing_order()));
+}
+
+/// Adds an effect to a track and tells the mixer what order
+/// to process its tracks in, like the main thread would.
+fn add_effect(
+	mixer: &mut Mixer,
+	routing: &mut TrackRouting,
+	collector: &Collector,
+	index: TrackIndex,
+	effect: impl Effect + 'static,
+	settings: EffectSettings,
+) {
+	let effect_id = EffectId::new();
+	if let Some(sidechain) = settings.sidechain {
+		routing.add_sidechain(index, effect_id, sidechain);
+	}
+	mixer.add_effect(
+		index,
+		effect_id,
+		Owned::new(&collector.handle(), Box::new(effect)),
+		settings,
+	);
+	mixer.set_processing_order(Owned::new(&collector.handle(), routing.processing_order()));
+}
+
 #[test]
 fn routes_audio_to_parent_tracks() {
 	let collector = Collector::new();
 	let parameters = Parameters::new(100);
 	let mut mixer = Mixer::new(100, 100);
+	let mut routing = TrackRouting::new();
 	// parent track has a volume of 50%
 	let parent_track_id = {
 		let settings = SubTrackSettings::new().volume(0.5);
 		let id = settings.id.unwrap_or(SubTrackId::new());
-		mixer.add_track(Owned::new(
-			&collector.handle(),
+		add_track(
+			&mut mixer,
+			&mut routing,
+			&collector,
 			Track::new_sub_track(id, settings),
-		));
+		);
 		id
 	};
 	// sub tracks 1 and 2 are routed into the parent track
 	let sub_track_1_id = {
 		let settings = SubTrackSettings::new().parent_track(parent_track_id);
 		let id = settings.id.unwrap_or(SubTrackId::new());
-		mixer.add_track(Owned::new(
-			&collector.handle(),
+		add_track(
+			&mut mixer,
+			&mut routing,
+			&collector,
 			Track::new_sub_track(id, settings),
-		));
+		);
 		id
 	};
 	let sub_track_2_id = {
 		let settings = SubTrackSettings::new().parent_track(parent_track_id);
 		let id = settings.id.unwrap_or(SubTrackId::new());
-		mixer.add_track(Owned::new(
-			&collector.handle(),
+		add_track(
+			&mut mixer,
+			&mut routing,
+			&collector,
 			Track::new_sub_track(id, settings),
-		));
+		);
 		id
 	};
 	// sub track 3 is routed directly to main
 	let sub_track_3_id = {
 		let settings = SubTrackSettings::new();
 		let id = settings.id.unwrap_or(SubTrackId::new());
-		mixer.add_track(Owned::new(
-			&collector.handle(),
+		add_track(
+			&mut mixer,
+			&mut routing,
+			&collector,
 			Track::new_sub_track(id, settings),
-		));
+		);
 		id
 	};
 	// each sub-track will contribute one digit of signal to the final output.
@@ -67,22 +122,27 @@ fn routes_audio_to_send_tracks() {
 	let collector = Collector::new();
 	let parameters = Parameters::new(100);
 	let mut mixer = Mixer::new(100, 100);
+	let mut routing = TrackRouting::new();
 	let send_track_1_id = {
 		let settings = SendTrackSettings::new();
 		let id = settings.id.unwrap_or(SendTrackId::new());
-		mixer.add_track(Owned::new(
-			&collector.handle(),
+		add_track(
+			&mut mixer,
+			&mut routing,
+			&collector,
 			Track::new_send_track(id, settings),
-		));
+		);
 		id
 	};
 	let send_track_2_id = {
 		let settings = SendTrackSettings::new();
 		let id = settings.id.unwrap_or(SendTrackId::new());
-		mixer.add_track(Owned::new(
-			&collector.handle(),
+		add_track(
+			&mut mixer,
+			&mut routing,
+			&collector,
 			Track::new_send_track(id, settings),
-		));
+		);
 		id
 	};
 	let sub_track_id = {
@@ -92,13 +152,281 @@ fn routes_audio_to_send_tracks() {
 				.add(send_track_2_id, 0.01),
 		);
 		let id = settings.id.unwrap_or(SubTrackId::new());
-		mixer.add_track(Owned::new(
-			&collector.handle(),
+		add_track(
+			&mut mixer,
+			&mut routing,
+			&collector,
 			Track::new_sub_track(id, settings),
-		));
+		);
 		id
 	};
 	mixer.add_input(sub_track_id.into(), Frame::from_mono(100.0));
 	let out = mixer.process(1.0, &parameters);
 	assert_eq!(out, Frame::from_mono(111.0));
 }
+
+#[test]
+fn ducks_tracks_when_the_trigger_track_has_signal() {
+	let collector = Collector::new();
+	let parameters = Parameters::new(100);
+	let mut mixer = Mixer::new(100, 100);
+	let mut routing = TrackRouting::new();
+	let dialog_track_id = SubTrackId::new();
+	add_track(
+		&mut mixer,
+		&mut routing,
+		&collector,
+		Track::new_sub_track(dialog_track_id, SubTrackSettings::new()),
+	);
+	let music_track_id = SubTrackId::new();
+	add_track(
+		&mut mixer,
+		&mut routing,
+		&collector,
+		Track::new_sub_track(
+			music_track_id,
+			SubTrackSettings::new().ducking(
+				TrackDucking::new().add_rule(
+					DuckingSettings::new(dialog_track_id)
+						.volume(0.5)
+						.attack(0.0)
+						.release(0.0),
+				),
+			),
+		),
+	);
+	let mut process = |dialog: f32| {
+		mixer.add_input(music_track_id.into(), Frame::from_mono(1.0));
+		mixer.add_input(dialog_track_id.into(), Frame::from_mono(dialog));
+		mixer.process(1.0, &parameters)
+	};
+	assert_eq!(process(0.0), Frame::from_mono(1.0));
+	// ducking responds to the previous frame of the trigger track
+	assert_eq!(process(1.0), Frame::from_mono(2.0));
+	assert_eq!(process(1.0), Frame::from_mono(1.5));
+	assert_eq!(process(0.0), Frame::from_mono(0.5));
+	assert_eq!(process(0.0), Frame::from_mono(1.0));
+}
+
+#[test]
+fn ducks_tracks_when_the_trigger_track_has_active_instances() {
+	let collector = Collector::new();
+	let parameters = Parameters::new(100);
+	let mut mixer = Mixer::new(100, 100);
+	let mut routing = TrackRouting::new();
+	let dialog_track_id = SubTrackId::new();
+	add_track(
+		&mut mixer,
+		&mut routing,
+		&collector,
+		Track::new_sub_track(dialog_track_id, SubTrackSettings::new()),
+	);
+	// input to a child track counts as input to the trigger track
+	let voice_track_id = SubTrackId::new();
+	add_track(
+		&mut mixer,
+		&mut routing,
+		&collector,
+		Track::new_sub_track(
+			voice_track_id,
+			SubTrackSettings::new().parent_track(dialog_track_id),
+		),
+	);
+	let send_track_id = SendTrackId::new();
+	add_track(
+		&mut mixer,
+		&mut routing,
+		&collector,
+		Track::new_send_track(
+			send_track_id,
+			SendTrackSettings::new().ducking(
+				TrackDucking::new().add_rule(
+					DuckingSettings::new(dialog_track_id)
+						.trigger(DuckingTrigger::ActiveInstances)
+						.volume(0.5)
+						.attack(2.0),
+				),
+			),
+		),
+	);
+	let mut process = || {
+		mixer.add_input(send_track_id.into(), Frame::from_mono(1.0));
+		mixer.add_input(voice_track_id.into(), Frame::from_mono(0.0));
+		mixer.process(1.0, &parameters)
+	};
+	assert_eq!(process(), Frame::from_mono(1.0));
+	assert_eq!(process(), Frame::from_mono(0.75));
+	assert_eq!(process(), Frame::from_mono(0.5));
+}
+
+#[test]
+fn ducking_rules_combine() {
+	let collector = Collector::new();
+	let parameters = Parameters::new(100);
+	let mut mixer = Mixer::new(100, 100);
+	let mut routing = TrackRouting::new();
+	let dialog_track_id = SubTrackId::new();
+	add_track(
+		&mut mixer,
+		&mut routing,
+		&collector,
+		Track::new_sub_track(dialog_track_id, SubTrackSettings::new().volume(0.0)),
+	);
+	let stinger_track_id = SubTrackId::new();
+	add_track(
+		&mut mixer,
+		&mut routing,
+		&collector,
+		Track::new_sub_track(stinger_track_id, SubTrackSettings::new().volume(0.0)),
+	);
+	let music_track_id = SubTrackId::new();
+	add_track(
+		&mut mixer,
+		&mut routing,
+		&collector,
+		Track::new_sub_track(
+			music_track_id,
+			SubTrackSettings::new().ducking(
+				TrackDucking::new().add_rule(
+					DuckingSettings::new(dialog_track_id)
+						.trigger(DuckingTrigger::ActiveInstances)
+						.volume(0.5)
+						.attack(0.0),
+				),
+			),
+		),
+	);
+	mixer.set_track_ducking(
+		music_track_id.into(),
+		DuckingSettings::new(stinger_track_id)
+			.trigger(DuckingTrigger::ActiveInstances)
+			.volume(0.25)
+			.attack(0.0),
+	);
+	let process = |mixer: &mut Mixer| {
+		mixer.add_input(music_track_id.into(), Frame::from_mono(1.0));
+		mixer.add_input(dialog_track_id.into(), Frame::from_mono(0.0));
+		mixer.add_input(stinger_track_id.into(), Frame::from_mono(0.0));
+		mixer.process(1.0, &parameters)
+	};
+	assert_eq!(process(&mut mixer), Frame::from_mono(1.0));
+	assert_eq!(process(&mut mixer), Frame::from_mono(0.125));
+	mixer.remove_track_ducking(music_track_id.into(), stinger_track_id.into());
+	assert_eq!(process(&mut mixer), Frame::from_mono(0.5));
+}
+
+/// An effect that outputs its sidechain input.
+#[derive(Debug)]
+struct SidechainPassthrough;
+
+impl Effect for SidechainPassthrough {
+	fn process(&mut self, _dt: f64, input: Frame, _parameters: &Parameters) -> Frame {
+		input
+	}
+
+	fn process_with_sidechain(
+		&mut self,
+		_dt: f64,
+		_input: Frame,
+		sidechain: Frame,
+		_parameters: &Parameters,
+	) -> Frame {
+		sidechain
+	}
+}
+
+#[test]
+fn effects_receive_sidechain_inputs() {
+	let collector = Collector::new();
+	let parameters = Parameters::new(100);
+	let mut mixer = Mixer::new(100, 100);
+	let mut routing = TrackRouting::new();
+	let music_track_id = SubTrackId::new();
+	add_track(
+		&mut mixer,
+		&mut routing,
+		&collector,
+		Track::new_sub_track(music_track_id, SubTrackSettings::new()),
+	);
+	// the dialog track is added after the music track, but it
+	// still has to be processed first
+	let dialog_track_id = SubTrackId::new();
+	add_track(
+		&mut mixer,
+		&mut routing,
+		&collector,
+		Track::new_sub_track(dialog_track_id, SubTrackSettings::new().volume(0.5)),
+	);
+	add_effect(
+		&mut mixer,
+		&mut routing,
+		&collector,
+		music_track_id.into(),
+		SidechainPassthrough,
+		EffectSettings::new().sidechain(dialog_track_id),
+	);
+	mixer.add_input(dialog_track_id.into(), Frame::from_mono(4.0));
+	assert_eq!(mixer.process(1.0, &parameters), Frame::from_mono(4.0));
+}
+
+#[test]
+fn effects_stop_receiving_sidechain_inputs_from_removed_tracks() {
+	let collector = Collector::new();
+	let parameters = Parameters::new(100);
+	let mut mixer = Mixer::new(100, 100);
+	let mut routing = TrackRouting::new();
+	let dialog_track_id = SubTrackId::new();
+	add_track(
+		&mut mixer,
+		&mut routing,
+		&collector,
+		Track::new_sub_track(dialog_track_id, SubTrackSettings::new()),
+	);
+	let music_track_id = SubTrackId::new();
+	add_track(
+		&mut mixer,
+		&mut routing,
+		&collector,
+		Track::new_sub_track(music_track_id, SubTrackSettings::new()),
+	);
+	add_effect(
+		&mut mixer,
+		&mut routing,
+		&collector,
+		music_track_id.into(),
+		SidechainPassthrough,
+		EffectSettings::new().sidechain(dialog_track_id),
+	);
+	// the music track outputs the dialog track's output
+	mixer.add_input(dialog_track_id.into(), Frame::from_mono(4.0));
+	assert_eq!(mixer.process(1.0, &parameters), Frame::from_mono(8.0));
+	mixer.remove_sub_track(dialog_track_id);
+	assert_eq!(mixer.process(1.0, &parameters), Frame::from_mono(0.0));
+}
+
+#[test]
+fn sidechains_from_dependent_tracks_use_the_previous_frame() {
+	let collector = Collector::new();
+	let parameters = Parameters::new(100);
+	let mut mixer = Mixer::new(100, 100);
+	let mut routing = TrackRouting::new();
+	let sub_track_id = SubTrackId::new();
+	add_track(
+		&mut mixer,
+		&mut routing,
+		&collector,
+		Track::new_sub_track(sub_track_id, SubTrackSettings::new()),
+	);
+	add_effect(
+		&mut mixer,
+		&mut routing,
+		&collector,
+		sub_track_id.into(),
+		SidechainPassthrough,
+		EffectSettings::new().sidechain(TrackIndex::Main),
+	);
+	mixer.add_input(TrackIndex::Main, Frame::from_mono(1.0));
+	assert_eq!(mixer.process(1.0, &parameters), Frame::from_mono(1.0));
+	mixer.add_input(TrackIndex::Main, Frame::from_mono(1.0));
+	assert_eq!(mixer.process(1.0, &parameters), Frame::from_mono(2.0));
+}
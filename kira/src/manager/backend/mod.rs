@@ -7,8 +7,15 @@ use self::mixer::Mixer;
 
 use super::AudioManagerSettings;
 use crate::{
-	command::Command, frame::Frame, group::groups::Groups, metronome::Metronomes,
-	parameter::Parameters, playable::Playables, static_container::vec::StaticVec,
+	command::Command,
+	frame::Frame,
+	group::groups::Groups,
+	metronome::Metronomes,
+	parameter::Parameters,
+	playable::Playables,
+	sequence::{SequenceInstanceId, SequenceOutputCommand},
+	spatial::SpatialScene,
+	static_container::vec::StaticVec,
 };
 use instances::Instances;
 use ringbuf::Consumer;
@@ -19,15 +26,16 @@ use streams::Streams;
 pub struct Backend {
 	dt: f64,
 	playables: Playables,
-	command_queue: StaticVec<Command>,
 	command_consumer: Consumer<Command>,
 	metronomes: Metronomes,
 	parameters: Parameters,
 	instances: Instances,
 	sequences: Sequences,
+	sequence_output_command_queue: StaticVec<(SequenceInstanceId, SequenceOutputCommand)>,
 	mixer: Mixer,
 	groups: Groups,
 	streams: Streams,
+	spatial_scene: SpatialScene,
 }
 
 impl Backend {
@@ -39,63 +47,122 @@ impl Backend {
 		Self {
 			dt: 1.0 / sample_rate as f64,
 			playables: Playables::new(settings.num_sounds, settings.num_arrangements),
-			command_queue: StaticVec::new(settings.num_commands),
 			command_consumer,
 			parameters: Parameters::new(settings.num_parameters),
 			metronomes: Metronomes::new(settings.num_metronomes),
-			instances: Instances::new(settings.num_instances),
+			instances: Instances::new(&settings),
 			sequences: Sequences::new(settings.num_sequences, settings.num_commands),
+			sequence_output_command_queue: StaticVec::new(settings.num_commands),
 			mixer: Mixer::new(settings.num_sub_tracks, settings.num_send_tracks),
 			groups: Groups::new(settings.num_groups),
 			streams: Streams::new(settings.num_streams),
+			spatial_scene: SpatialScene::new(&settings),
 		}
 	}
 
 	fn process_commands(&mut self) {
 		while let Some(command) = self.command_consumer.pop() {
-			// TODO: find a way to avoid sharing the command queue
-			// between user-called functions and sequence-produced
-			// commands. I don't want sequence commands cutting
-			// into the capacity of the command queue
-			self.command_queue.try_push(command).ok();
+			self.run_command(command);
 		}
-		for command in self.command_queue.drain(..) {
+	}
+
+	fn run_command(&mut self, command: Command) {
+		match command {
+			Command::Resource(command) => {
+				self.playables.run_command(command);
+			}
+			Command::Metronome(command) => {
+				self.metronomes.run_command(command);
+			}
+			Command::Instance(command) => {
+				self.instances.run_command(
+					command,
+					&mut self.playables,
+					&self.sequences,
+					&self.groups,
+					&self.spatial_scene,
+				);
+			}
+			Command::Sequence(command) => {
+				self.sequences.run_command(command, &self.groups);
+			}
+			Command::Mixer(command) => {
+				self.mixer.run_command(command);
+			}
+			Command::Parameter(command) => {
+				self.parameters.run_command(command);
+			}
+			Command::Group(command) => {
+				self.groups.run_command(command);
+			}
+			Command::Stream(command) => {
+				self.streams.run_command(command);
+			}
+			Command::Spatial(command) => {
+				self.spatial_scene.run_command(command);
+			}
+		}
+	}
+
+	/// Updates the sequences and runs the commands they produce
+	/// in the order the sequences produced them.
+	fn update_sequences(&mut self) {
+		self.sequences.update(
+			self.dt,
+			&self.metronomes,
+			&self.groups,
+			&mut self.sequence_output_command_queue,
+		);
+		for i in 0..self.sequence_output_command_queue.len() {
+			let (sequence_id, command) = *self.sequence_output_command_queue.get(i).unwrap();
 			match command {
-				Command::Resource(command) => {
-					self.playables.run_command(command);
-				}
-				Command::Metronome(command) => {
-					self.metronomes.run_command(command);
-				}
-				Command::Instance(command) => {
-					self.instances
-						.run_command(command, &mut self.playables, &self.groups);
-				}
-				Command::Sequence(command) => {
-					self.sequences.run_command(command, &self.groups);
+				SequenceOutputCommand::PlaySound(playable_id, instance_id, step_index) => {
+					if let Some(instance) = self.sequences.create_instance(
+						sequence_id,
+						playable_id,
+						step_index,
+						&self.playables,
+					) {
+						self.instances.play(
+							instance_id,
+							instance,
+							&mut self.playables,
+							&self.sequences,
+							&self.groups,
+							&self.spatial_scene,
+						);
+					}
 				}
-				Command::Mixer(command) => {
-					self.mixer.run_command(command);
-				}
-				Command::Parameter(command) => {
-					self.parameters.run_command(command);
-				}
-				Command::Group(command) => {
-					self.groups.run_command(command);
-				}
-				Command::Stream(command) => {
-					self.streams.run_command(command);
+				command => {
+					if let Some(command) = sequences::convert_output_command(command) {
+						self.run_command(command);
+					}
 				}
 			}
 		}
+		self.sequence_output_command_queue.drain(..);
+		self.sequences.remove_finished();
 	}
 
-	fn update_sequences(&mut self) {
-		for command in self
-			.sequences
-			.update(self.dt, &self.playables, &self.metronomes)
-		{
-			self.command_queue.try_push(command.into()).ok();
+	/// Updates the active instance and sequence counts that
+	/// group handles report if instances or sequences have
+	/// started or stopped.
+	fn update_group_counts(&mut self) {
+		let instances_changed = self.instances.take_group_counts_outdated();
+		let sequences_changed = self.sequences.take_group_counts_outdated();
+		let groups_changed = self.groups.take_group_counts_outdated();
+		if !(instances_changed || sequences_changed || groups_changed) {
+			return;
+		}
+		for (id, group) in self.groups.iter() {
+			group.set_num_active_instances(self.instances.num_instances_in_group(
+				*id,
+				&self.playables,
+				&self.sequences,
+				&self.groups,
+			));
+			group
+				.set_num_active_sequences(self.sequences.num_sequences_in_group(*id, &self.groups));
 		}
 	}
 
@@ -103,12 +170,23 @@ impl Backend {
 	pub fn process(&mut self) -> Frame {
 		self.process_commands();
 		self.parameters.update(self.dt);
+		self.groups.update(&self.parameters);
 		self.playables.update(self.dt);
 		self.metronomes.update(self.dt, &self.parameters);
 		self.update_sequences();
 		self.streams.process(self.dt, &mut self.mixer);
+		self.spatial_scene.update(self.dt);
 		self.instances
-			.process(self.dt, &self.playables, &mut self.mixer, &self.parameters);
+			.update_group_multipliers(&self.playables, &self.groups, &self.sequences);
+		self.instances.process(
+			self.dt,
+			&self.playables,
+			&mut self.mixer,
+			&self.parameters,
+			&self.metronomes,
+			&self.spatial_scene,
+		);
+		self.update_group_counts();
 		self.mixer.process(self.dt, &self.parameters)
 	}
 }
@@ -1,20 +1,21 @@
 use crate::{
 	command::{Command, InstanceCommand, MetronomeCommand, ParameterCommand, SequenceCommand},
-	group::groups::Groups,
+	group::{groups::Groups, GroupId, GroupSet},
 	instance::Instance,
 	metronome::Metronomes,
-	playable::Playables,
+	playable::{PlayableId, Playables},
 	sequence::{SequenceInstance, SequenceInstanceId, SequenceOutputCommand},
 	static_container::{index_map::StaticIndexMap, vec::StaticVec},
 };
 use basedrop::Owned;
-use std::vec::Drain;
 
 pub(crate) struct Sequences {
 	sequence_instances: StaticIndexMap<SequenceInstanceId, Owned<SequenceInstance>>,
 	sequence_instances_to_remove: StaticVec<SequenceInstanceId>,
 	sequence_output_command_queue: StaticVec<SequenceOutputCommand>,
-	output_command_queue: StaticVec<Command>,
+	/// Whether sequences have started or stopped since the
+	/// groups' active sequence counts were updated.
+	group_counts_outdated: bool,
 }
 
 impl Sequences {
@@ -23,10 +24,25 @@ impl Sequences {
 			sequence_instances: StaticIndexMap::new(sequence_capacity),
 			sequence_instances_to_remove: StaticVec::new(sequence_capacity),
 			sequence_output_command_queue: StaticVec::new(command_capacity),
-			output_command_queue: StaticVec::new(command_capacity),
+			group_counts_outdated: false,
 		}
 	}
 
+	pub fn take_group_counts_outdated(&mut self) -> bool {
+		std::mem::take(&mut self.group_counts_outdated)
+	}
+
+	/// Returns the number of sequences in a group that
+	/// haven't finished.
+	pub fn num_sequences_in_group(&self, id: GroupId, all_groups: &Groups) -> usize {
+		self.sequence_instances
+			.iter()
+			.filter(|(_, sequence_instance)| {
+				!sequence_instance.finished() && sequence_instance.is_in_group(id, all_groups)
+			})
+			.count()
+	}
+
 	fn start_sequence_instance(
 		&mut self,
 		id: SequenceInstanceId,
@@ -37,6 +53,7 @@ impl Sequences {
 	}
 
 	pub fn run_command(&mut self, command: SequenceCommand, groups: &Groups) {
+		self.group_counts_outdated = true;
 		match command {
 			SequenceCommand::StartSequenceInstance(id, instance) => {
 				self.start_sequence_instance(id, instance);
@@ -90,181 +107,142 @@ impl Sequences {
 		}
 	}
 
+	pub fn groups(&self, id: SequenceInstanceId) -> Option<&GroupSet> {
+		self.sequence_instances
+			.get(&id)
+			.map(|sequence_instance| sequence_instance.groups())
+	}
+
+	/// Updates the sequences and adds the commands they produce
+	/// to `output_command_queue` in the order they were produced.
+	///
+	/// Finished sequences are kept until [`Sequences::remove_finished`]
+	/// is called so that the instances they start can still be created.
 	pub fn update(
 		&mut self,
 		dt: f64,
-		playables: &Playables,
 		metronomes: &Metronomes,
-	) -> Drain<Command> {
-		// update sequences and process their commands
+		groups: &Groups,
+		output_command_queue: &mut StaticVec<(SequenceInstanceId, SequenceOutputCommand)>,
+	) {
 		for (id, sequence_instance) in &mut self.sequence_instances {
-			sequence_instance.update(dt, metronomes, &mut self.sequence_output_command_queue);
-			// convert sequence commands to commands that can be consumed
-			// by the backend
+			let (_, playback_rate) = groups.multipliers(&[sequence_instance.groups()]);
+			sequence_instance.update(
+				dt * playback_rate,
+				metronomes,
+				&mut self.sequence_output_command_queue,
+			);
 			for command in self.sequence_output_command_queue.drain(..) {
-				match command {
-					SequenceOutputCommand::PlaySound(playable_id, instance_id, settings) => {
-						if let Some(playable) = playables.playable(playable_id) {
-							self.output_command_queue
-								.try_push(Command::Instance(InstanceCommand::Play(
-									instance_id,
-									Instance::new(
-										playable_id,
-										playable.duration(),
-										Some(*id),
-										settings.into_internal(
-											playable.duration(),
-											playable.default_loop_start(),
-											playable.default_track(),
-										),
-									),
-								)))
-								.ok();
-						}
-					}
-					SequenceOutputCommand::SetInstanceVolume(id, volume) => {
-						self.output_command_queue
-							.try_push(Command::Instance(InstanceCommand::SetInstanceVolume(
-								id, volume,
-							)))
-							.ok();
-					}
-					SequenceOutputCommand::SetInstancePlaybackRate(id, playback_rate) => {
-						self.output_command_queue
-							.try_push(Command::Instance(InstanceCommand::SetInstancePlaybackRate(
-								id,
-								playback_rate,
-							)))
-							.ok();
-					}
-					SequenceOutputCommand::SetInstancePanning(id, panning) => {
-						self.output_command_queue
-							.try_push(Command::Instance(InstanceCommand::SetInstancePanning(
-								id, panning,
-							)))
-							.ok();
-					}
-					SequenceOutputCommand::PauseInstance(id, settings) => {
-						self.output_command_queue
-							.try_push(Command::Instance(InstanceCommand::PauseInstance(
-								id, settings,
-							)))
-							.ok();
-					}
-					SequenceOutputCommand::ResumeInstance(id, settings) => {
-						self.output_command_queue
-							.try_push(Command::Instance(InstanceCommand::ResumeInstance(
-								id, settings,
-							)))
-							.ok();
-					}
-					SequenceOutputCommand::StopInstance(id, settings) => {
-						self.output_command_queue
-							.try_push(Command::Instance(InstanceCommand::StopInstance(
-								id, settings,
-							)))
-							.ok();
-					}
-					SequenceOutputCommand::PauseInstancesOf(id, settings) => {
-						self.output_command_queue
-							.try_push(Command::Instance(InstanceCommand::PauseInstancesOf(
-								id, settings,
-							)))
-							.ok();
-					}
-					SequenceOutputCommand::ResumeInstancesOf(id, settings) => {
-						self.output_command_queue
-							.try_push(Command::Instance(InstanceCommand::ResumeInstancesOf(
-								id, settings,
-							)))
-							.ok();
-					}
-					SequenceOutputCommand::StopInstancesOf(id, settings) => {
-						self.output_command_queue
-							.try_push(Command::Instance(InstanceCommand::StopInstancesOf(
-								id, settings,
-							)))
-							.ok();
-					}
-					SequenceOutputCommand::PauseSequence(id) => {
-						self.output_command_queue
-							.try_push(Command::Sequence(SequenceCommand::PauseSequenceInstance(
-								id,
-							)))
-							.ok();
-					}
-					SequenceOutputCommand::ResumeSequence(id) => {
-						self.output_command_queue
-							.try_push(Command::Sequence(SequenceCommand::ResumeSequenceInstance(
-								id,
-							)))
-							.ok();
-					}
-					SequenceOutputCommand::StopSequence(id) => {
-						self.output_command_queue
-							.try_push(Command::Sequence(SequenceCommand::StopSequenceInstance(id)))
-							.ok();
-					}
-					SequenceOutputCommand::PauseInstancesOfSequence(id, settings) => {
-						self.output_command_queue
-							.try_push(Command::Instance(
-								InstanceCommand::PauseInstancesOfSequence(id, settings),
-							))
-							.ok();
-					}
-					SequenceOutputCommand::ResumeInstancesOfSequence(id, settings) => {
-						self.output_command_queue
-							.try_push(Command::Instance(
-								InstanceCommand::ResumeInstancesOfSequence(id, settings),
-							))
-							.ok();
-					}
-					SequenceOutputCommand::StopInstancesOfSequence(id, settings) => {
-						self.output_command_queue
-							.try_push(Command::Instance(InstanceCommand::StopInstancesOfSequence(
-								id, settings,
-							)))
-							.ok();
-					}
-					SequenceOutputCommand::SetMetronomeTempo(id, tempo) => {
-						self.output_command_queue
-							.try_push(Command::Metronome(MetronomeCommand::SetMetronomeTempo(
-								id, tempo,
-							)))
-							.ok();
-					}
-					SequenceOutputCommand::StartMetronome(id) => {
-						self.output_command_queue
-							.try_push(Command::Metronome(MetronomeCommand::StartMetronome(id)))
-							.ok();
-					}
-					SequenceOutputCommand::PauseMetronome(id) => {
-						self.output_command_queue
-							.try_push(Command::Metronome(MetronomeCommand::PauseMetronome(id)))
-							.ok();
-					}
-					SequenceOutputCommand::StopMetronome(id) => {
-						self.output_command_queue
-							.try_push(Command::Metronome(MetronomeCommand::StopMetronome(id)))
-							.ok();
-					}
-					SequenceOutputCommand::SetParameter(id, target, tween) => {
-						self.output_command_queue
-							.try_push(Command::Parameter(ParameterCommand::SetParameter(
-								id, target, tween,
-							)))
-							.ok();
-					}
-				}
+				output_command_queue.try_push((*id, command)).ok();
 			}
 			if sequence_instance.finished() {
 				self.sequence_instances_to_remove.try_push(*id).ok();
 			}
 		}
-		// remove finished sequences
+	}
+
+	/// Creates the instance that a sequence's step plays.
+	pub fn create_instance(
+		&self,
+		sequence_id: SequenceInstanceId,
+		playable_id: PlayableId,
+		step_index: usize,
+		playables: &Playables,
+	) -> Option<Instance> {
+		let playable = playables.playable(playable_id)?;
+		let settings = self
+			.sequence_instances
+			.get(&sequence_id)?
+			.instance_settings(step_index)?;
+		Some(Instance::new(
+			playable_id,
+			playable.duration(),
+			playable.sample_rate(),
+			Some(sequence_id),
+			settings.into_internal(
+				playable.duration(),
+				playable.default_loop_start(),
+				playable.default_loop_crossfade(),
+				playable.default_track(),
+			),
+		))
+	}
+
+	/// Removes the sequences that finished during the last update.
+	pub fn remove_finished(&mut self) {
 		for id in self.sequence_instances_to_remove.drain(..) {
 			self.sequence_instances.remove(&id).unwrap();
+			self.group_counts_outdated = true;
+		}
+	}
+}
+
+/// Converts a command produced by a sequence to a command that
+/// can be consumed by the backend. Returns `None` for commands
+/// that play sounds, since the backend creates those instances
+/// itself.
+pub(crate) fn convert_output_command(command: SequenceOutputCommand) -> Option<Command> {
+	match command {
+		SequenceOutputCommand::PlaySound(..) => None,
+		SequenceOutputCommand::SetInstanceVolume(id, volume) => Some(Command::Instance(
+			InstanceCommand::SetInstanceVolume(id, volume),
+		)),
+		SequenceOutputCommand::SetInstancePlaybackRate(id, playback_rate) => Some(
+			Command::Instance(InstanceCommand::SetInstancePlaybackRate(id, playback_rate)),
+		),
+		SequenceOutputCommand::SetInstancePanning(id, panning) => Some(Command::Instance(
+			InstanceCommand::SetInstancePanning(id, panning),
+		)),
+		SequenceOutputCommand::PauseInstance(id, settings) => Some(Command::Instance(
+			InstanceCommand::PauseInstance(id, settings),
+		)),
+		SequenceOutputCommand::ResumeInstance(id, settings) => Some(Command::Instance(
+			InstanceCommand::ResumeInstance(id, settings),
+		)),
+		SequenceOutputCommand::StopInstance(id, settings) => Some(Command::Instance(
+			InstanceCommand::StopInstance(id, settings),
+		)),
+		SequenceOutputCommand::PauseInstancesOf(id, settings) => Some(Command::Instance(
+			InstanceCommand::PauseInstancesOf(id, settings),
+		)),
+		SequenceOutputCommand::ResumeInstancesOf(id, settings) => Some(Command::Instance(
+			InstanceCommand::ResumeInstancesOf(id, settings),
+		)),
+		SequenceOutputCommand::StopInstancesOf(id, settings) => Some(Command::Instance(
+			InstanceCommand::StopInstancesOf(id, settings),
+		)),
+		SequenceOutputCommand::PauseSequence(id) => Some(Command::Sequence(
+			SequenceCommand::PauseSequenceInstance(id),
+		)),
+		SequenceOutputCommand::ResumeSequence(id) => Some(Command::Sequence(
+			SequenceCommand::ResumeSequenceInstance(id),
+		)),
+		SequenceOutputCommand::StopSequence(id) => {
+			Some(Command::Sequence(SequenceCommand::StopSequenceInstance(id)))
+		}
+		SequenceOutputCommand::PauseInstancesOfSequence(id, settings) => Some(Command::Instance(
+			InstanceCommand::PauseInstancesOfSequence(id, settings),
+		)),
+		SequenceOutputCommand::ResumeInstancesOfSequence(id, settings) => Some(Command::Instance(
+			InstanceCommand::ResumeInstancesOfSequence(id, settings),
+		)),
+		SequenceOutputCommand::StopInstancesOfSequence(id, settings) => Some(Command::Instance(
+			InstanceCommand::StopInstancesOfSequence(id, settings),
+		)),
+		SequenceOutputCommand::SetMetronomeTempo(id, tempo) => Some(Command::Metronome(
+			MetronomeCommand::SetMetronomeTempo(id, tempo),
+		)),
+		SequenceOutputCommand::StartMetronome(id) => {
+			Some(Command::Metronome(MetronomeCommand::StartMetronome(id)))
+		}
+		SequenceOutputCommand::PauseMetronome(id) => {
+			Some(Command::Metronome(MetronomeCommand::PauseMetronome(id)))
+		}
+		SequenceOutputCommand::StopMetronome(id) => {
+			Some(Command::Metronome(MetronomeCommand::StopMetronome(id)))
 		}
-		self.output_command_queue.drain(..)
+		SequenceOutputCommand::SetParameter(id, target, tween) => Some(Command::Parameter(
+			ParameterCommand::SetParameter(id, target, tween),
+		)),
 	}
 }
@@ -13,6 +13,7 @@ use crate::{
 	parameter::ParameterId,
 	sequence::error::SequenceError,
 	sound::{error::SoundFromFileError, SoundId},
+	spatial::ListenerId,
 };
 
 /// Things that can go wrong when creating an `AudioManager`.
@@ -139,6 +140,32 @@ pub enum RemoveMetronomeError {
 	CommandProducerError(#[from] CommandError),
 }
 
+/// Things that can go wrong when adding a listener to the audio thread.
+#[derive(Debug, Error)]
+pub enum AddListenerError {
+	/// The maximum listener limit has been reached.
+	#[error("Cannot add a listener because the max number of listeners has been reached")]
+	ListenerLimitReached,
+
+	/// A command could not be sent to the audio thread.
+	#[error("Could not send the command to the audio thread.")]
+	CommandProducerError(#[from] CommandError),
+}
+
+/// Things that can go wrong when removing a listener from the
+/// audio thread.
+#[derive(Debug, Error)]
+pub enum RemoveListenerError {
+	/// No listener with the specified ID exists, or the ID
+	/// is the main listener's, which can't be removed.
+	#[error("The listener with the specified ID does not exist or cannot be removed")]
+	NoListenerWithId(ListenerId),
+
+	/// A command could not be sent to the audio thread.
+	#[error("Could not send the command to the audio thread.")]
+	CommandProducerError(#[from] CommandError),
+}
+
 /// Things that can go wrong when adding a group to the audio thread.
 #[derive(Debug, Error)]
 pub enum AddGroupError {
@@ -208,6 +235,10 @@ pub enum AddSubTrackError {
 	#[error("One of the track's send tracks does not exist")]
 	NonexistentSendTrack(SendTrackId),
 
+	/// The track that makes this track duck does not exist.
+	#[error("The track's ducking trigger track does not exist")]
+	NonexistentDuckingTriggerTrack(TrackIndex),
+
 	/// A command could not be sent to the audio thread.
 	#[error("Could not send the command to the audio thread.")]
 	CommandProducerError(#[from] CommandError),
@@ -220,6 +251,18 @@ pub enum AddSendTrackError {
 	#[error("Cannot add an track because the max number of tracks has been reached")]
 	TrackLimitReached,
 
+	/// The track's parent track does not exist.
+	#[error("The track's parent track does not exist")]
+	NonexistentParentTrack(TrackIndex),
+
+	/// One of the track's send tracks does not exist.
+	#[error("One of the track's send tracks does not exist")]
+	NonexistentSendTrack(SendTrackId),
+
+	/// The track that makes this track duck does not exist.
+	#[error("The track's ducking trigger track does not exist")]
+	NonexistentDuckingTriggerTrack(TrackIndex),
+
 	/// A command could not be sent to the audio thread.
 	#[error("Could not send the command to the audio thread.")]
 	CommandProducerError(#[from] CommandError),
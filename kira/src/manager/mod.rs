@@ -9,6 +9,7 @@ mod tests;
 use std::{
 	hash::Hash,
 	io::{stderr, Write},
+	sync::{Arc, Mutex},
 };
 
 use active_ids::ActiveIds;
@@ -18,10 +19,11 @@ use backend::Backend;
 pub use backend::Backend;
 use basedrop::{Collector, Owned};
 use error::{
-	AddArrangementError, AddGroupError, AddMetronomeError, AddParameterError, AddSendTrackError,
-	AddSoundError, AddStreamError, AddSubTrackError, RemoveArrangementError, RemoveGroupError,
-	RemoveMetronomeError, RemoveParameterError, RemoveSendTrackError, RemoveSoundError,
-	RemoveStreamError, RemoveSubTrackError, SetupError, StartSequenceError,
+	AddArrangementError, AddGroupError, AddListenerError, AddMetronomeError, AddParameterError,
+	AddSendTrackError, AddSoundError, AddStreamError, AddSubTrackError, RemoveArrangementError,
+	RemoveGroupError, RemoveListenerError, RemoveMetronomeError, RemoveParameterError,
+	RemoveSendTrackError, RemoveSoundError, RemoveStreamError, RemoveSubTrackError, SetupError,
+	StartSequenceError,
 };
 use ringbuf::{Consumer, Producer, RingBuffer};
 
@@ -29,20 +31,27 @@ use crate::{
 	arrangement::{handle::ArrangementHandle, Arrangement, ArrangementId},
 	audio_stream::{AudioStream, AudioStreamId},
 	command::{
-		producer::CommandProducer, Command, GroupCommand, MetronomeCommand, MixerCommand,
-		ParameterCommand, ResourceCommand, SequenceCommand, StreamCommand,
+		producer::{CommandError, CommandProducer},
+		Command, GroupCommand, MetronomeCommand, MixerCommand, ParameterCommand, ResourceCommand,
+		SequenceCommand, SpatialCommand, StreamCommand,
 	},
+	frame::PanLaw,
 	group::{handle::GroupHandle, Group, GroupId, GroupSet, GroupSettings},
+	instance::VoiceStealingPolicy,
 	metronome::{handle::MetronomeHandle, Metronome, MetronomeId, MetronomeSettings},
 	mixer::{
-		MainTrackHandle, SendTrackHandle, SendTrackId, SendTrackSettings, SubTrackHandle,
-		SubTrackId, SubTrackSettings, Track, TrackIndex,
+		push_processing_order, routing::TrackRouting, MainTrackHandle, SendTrackHandle,
+		SendTrackId, SendTrackSettings, SubTrackHandle, SubTrackId, SubTrackSettings, Track,
+		TrackIndex,
 	},
 	parameter::{handle::ParameterHandle, ParameterId, ParameterSettings},
 	sequence::{
 		handle::SequenceInstanceHandle, Sequence, SequenceInstanceId, SequenceInstanceSettings,
 	},
 	sound::{handle::SoundHandle, Sound, SoundId},
+	spatial::{
+		handle::ListenerHandle, hrtf::HrtfDataset, ListenerId, ListenerPolicy, ListenerSettings,
+	},
 };
 use cpal::{
 	traits::{DeviceTrait, HostTrait, StreamTrait},
@@ -73,6 +82,25 @@ pub struct AudioManagerSettings {
 	pub num_parameters: usize,
 	/// The maximum number of instances of sounds that can be playing at a time.
 	pub num_instances: usize,
+	/// What to do when a new instance is started while the
+	/// maximum number of instances are already playing.
+	pub voice_stealing_policy: VoiceStealingPolicy,
+	/// The maximum number of virtual instances that can exist
+	/// at a time, on top of the instances that are actually playing.
+	///
+	/// Virtual instances are instances that are too quiet to
+	/// hear. They keep track of their playback position, but
+	/// they don't produce any audio and don't count toward
+	/// [`num_instances`](Self::num_instances). Once this many
+	/// instances are virtual, instances that are too quiet to
+	/// hear keep playing as real instances.
+	pub num_virtual_instances: usize,
+	/// The volume instances have to fall below to become virtual.
+	///
+	/// Set this to `0.0` to disable virtualization.
+	pub virtual_instance_threshold: f64,
+	/// The pan law instances use unless they have their own.
+	pub pan_law: PanLaw,
 	/// The maximum number of sequences that can be running at a time.
 	pub num_sequences: usize,
 	/// The maximum number of mixer sub-tracks that can be used at a time.
@@ -85,6 +113,26 @@ pub struct AudioManagerSettings {
 	pub num_streams: usize,
 	/// The maximum number of metronomes that can be used at a time.
 	pub num_metronomes: usize,
+	/// How long it takes for the listener to move or turn most of
+	/// the way (about 63%) to a new position or orientation (in seconds).
+	pub listener_smoothing: f64,
+	/// The maximum number of listeners that can exist at a time,
+	/// including the main listener.
+	pub num_listeners: usize,
+	/// How emitters are spatialized when there's more than
+	/// one listener.
+	pub listener_policy: ListenerPolicy,
+	/// The speed of sound (in units per second) used for
+	/// the Doppler effect.
+	///
+	/// The default is the speed of sound in air in meters per second.
+	pub speed_of_sound: f64,
+	/// The maximum number of instances that can use the HRTF
+	/// panner at a time.
+	///
+	/// Other instances that would use the HRTF panner
+	/// use stereo panning instead.
+	pub num_hrtf_instances: usize,
 }
 
 impl Default for AudioManagerSettings {
@@ -95,12 +143,21 @@ impl Default for AudioManagerSettings {
 			num_arrangements: 100,
 			num_parameters: 100,
 			num_instances: 100,
+			voice_stealing_policy: VoiceStealingPolicy::default(),
+			num_virtual_instances: 100,
+			virtual_instance_threshold: 0.0001,
+			pan_law: PanLaw::default(),
 			num_sequences: 25,
 			num_sub_tracks: 100,
 			num_send_tracks: 10,
 			num_groups: 100,
 			num_streams: 10,
 			num_metronomes: 5,
+			listener_smoothing: 0.05,
+			num_listeners: 4,
+			listener_policy: ListenerPolicy::default(),
+			speed_of_sound: 343.0,
+			num_hrtf_instances: 16,
 		}
 	}
 }
@@ -115,6 +172,7 @@ pub struct AudioManager {
 	command_producer: CommandProducer,
 	resource_collector: Option<Collector>,
 	active_ids: ActiveIds,
+	track_routing: Arc<Mutex<TrackRouting>>,
 	sample_rate: u32,
 
 	#[cfg(not(target_arch = "wasm32"))]
@@ -172,6 +230,7 @@ impl AudioManager {
 			quit_signal_producer,
 			command_producer: CommandProducer::new(command_producer),
 			active_ids,
+			track_routing: Arc::new(Mutex::new(TrackRouting::new())),
 			sample_rate,
 			resource_collector: Some(resource_collector),
 		})
@@ -187,6 +246,7 @@ impl AudioManager {
 		Ok(Self {
 			command_producer: CommandProducer::new(command_producer),
 			active_ids,
+			track_routing: Arc::new(Mutex::new(TrackRouting::new())),
 			resource_collector: Some(resource_collector),
 			sample_rate,
 			_stream,
@@ -239,6 +299,7 @@ impl AudioManager {
 			quit_signal_producer,
 			command_producer: CommandProducer::new(command_producer),
 			active_ids: ActiveIds::new(&settings),
+			track_routing: Arc::new(Mutex::new(TrackRouting::new())),
 			sample_rate: SAMPLE_RATE,
 			resource_collector: Some(resource_collector),
 		};
@@ -261,6 +322,14 @@ impl AudioManager {
 			.copied()
 	}
 
+	fn track_exists(&self, index: TrackIndex) -> bool {
+		match index {
+			TrackIndex::Main => true,
+			TrackIndex::Sub(id) => self.active_ids.active_sub_track_ids.contains(&id),
+			TrackIndex::Send(id) => self.active_ids.active_send_track_ids.contains(&id),
+		}
+	}
+
 	fn resource_collector(&self) -> &Collector {
 		self.resource_collector.as_ref().unwrap()
 	}
@@ -269,8 +338,16 @@ impl AudioManager {
 		self.resource_collector.as_mut().unwrap()
 	}
 
+	/// Returns the sample rate of the audio device.
+	pub fn sample_rate(&self) -> u32 {
+		self.sample_rate
+	}
+
 	/// Sends a sound to the audio thread and returns a handle to the sound.
-	pub fn add_sound(&mut self, sound: Sound) -> Result<SoundHandle, AddSoundError> {
+	///
+	/// If the sound's settings ask for it, the sound will be
+	/// resampled to the audio device's sample rate first.
+	pub fn add_sound(&mut self, mut sound: Sound) -> Result<SoundHandle, AddSoundError> {
 		if !self.does_track_exist(sound.default_track()) {
 			return Err(AddSoundError::NoTrackWithIndex(sound.default_track()));
 		}
@@ -278,7 +355,14 @@ impl AudioManager {
 			return Err(AddSoundError::NoGroupWithId(group));
 		}
 		self.active_ids.add_sound_id(sound.id())?;
-		let handle = SoundHandle::new(&sound, self.command_producer.clone());
+		if let Some(quality) = sound.resample_on_add() {
+			sound.resample(self.sample_rate, quality);
+		}
+		let handle = SoundHandle::new(
+			&sound,
+			self.command_producer.clone(),
+			self.resource_collector().handle(),
+		);
 		let sound = Owned::new(&self.resource_collector().handle(), sound);
 		self.command_producer
 			.push(ResourceCommand::AddSound(sound).into())?;
@@ -322,7 +406,11 @@ impl AudioManager {
 			return Err(AddArrangementError::NoGroupWithId(group));
 		}
 		self.active_ids.add_arrangement_id(arrangement.id())?;
-		let handle = ArrangementHandle::new(&arrangement, self.command_producer.clone());
+		let handle = ArrangementHandle::new(
+			&arrangement,
+			self.command_producer.clone(),
+			self.resource_collector().handle(),
+		);
 		let arrangement = Owned::new(&self.resource_collector().handle(), arrangement);
 		self.command_producer
 			.push(ResourceCommand::AddArrangement(arrangement).into())?;
@@ -423,11 +511,64 @@ impl AudioManager {
 	pub fn main_track(&mut self) -> MainTrackHandle {
 		MainTrackHandle::new(
 			self.command_producer.clone(),
+			self.track_routing.clone(),
 			self.sample_rate,
 			self.resource_collector().handle(),
 		)
 	}
 
+	/// Returns a handle to the main listener for
+	/// [spatial audio](crate::spatial).
+	pub fn listener(&mut self) -> ListenerHandle {
+		ListenerHandle::new(ListenerId::main(), self.command_producer.clone())
+	}
+
+	/// Adds a listener for [spatial audio](crate::spatial)
+	/// alongside the main listener.
+	pub fn add_listener(
+		&mut self,
+		settings: ListenerSettings,
+	) -> Result<ListenerHandle, AddListenerError> {
+		let id = settings.id.unwrap_or(ListenerId::new());
+		self.active_ids.add_listener_id(id)?;
+		self.command_producer
+			.push(SpatialCommand::AddListener(id, settings).into())?;
+		Ok(ListenerHandle::new(id, self.command_producer.clone()))
+	}
+
+	/// Removes a listener from the audio thread.
+	///
+	/// The main listener can't be removed.
+	pub fn remove_listener(
+		&mut self,
+		id: impl Into<ListenerId>,
+	) -> Result<(), RemoveListenerError> {
+		let id = id.into();
+		self.active_ids.remove_listener_id(id)?;
+		self.command_producer
+			.push(SpatialCommand::RemoveListener(id).into())?;
+		Ok(())
+	}
+
+	/// Sets the HRTF dataset that emitters using
+	/// [`Panner::Hrtf`](crate::spatial::Panner::Hrtf) are rendered with.
+	///
+	/// The dataset is resampled to the audio device's sample rate
+	/// if needed.
+	pub fn set_hrtf_dataset(&mut self, mut dataset: HrtfDataset) -> Result<(), CommandError> {
+		dataset.resample(self.sample_rate);
+		let dataset = Owned::new(&self.resource_collector().handle(), dataset);
+		self.command_producer
+			.push(SpatialCommand::SetHrtfDataset(Some(dataset)).into())
+	}
+
+	/// Removes the HRTF dataset, making all emitters use
+	/// stereo panning.
+	pub fn remove_hrtf_dataset(&mut self) -> Result<(), CommandError> {
+		self.command_producer
+			.push(SpatialCommand::SetHrtfDataset(None).into())
+	}
+
 	/// Creates a mixer sub-track.
 	pub fn add_sub_track(
 		&mut self,
@@ -447,21 +588,45 @@ impl AudioManager {
 				return Err(AddSubTrackError::NonexistentSendTrack(*send_track_id));
 			}
 		}
+		for ducking in settings.ducking.iter() {
+			if !self.track_exists(ducking.trigger_track) {
+				return Err(AddSubTrackError::NonexistentDuckingTriggerTrack(
+					ducking.trigger_track,
+				));
+			}
+		}
+		let mut track_routing = TrackRouting::lock(&self.track_routing);
 		let id = settings.id.unwrap_or(SubTrackId::new());
 		self.active_ids.add_sub_track_id(id)?;
+		track_routing.add_track(
+			id.into(),
+			Some(settings.parent_track),
+			settings
+				.sends
+				.iter()
+				.map(|(send_track_id, _)| *send_track_id),
+			settings.num_sends.max(settings.sends.len()),
+		);
+		let resource_collector_handle = self.resource_collector().handle();
 		let handle = SubTrackHandle::new(
 			id,
 			&settings,
 			self.command_producer.clone(),
+			self.track_routing.clone(),
 			self.sample_rate,
-			self.resource_collector().handle(),
+			resource_collector_handle.clone(),
 		);
 		let track = Owned::new(
-			&self.resource_collector().handle(),
+			&resource_collector_handle,
 			Track::new_sub_track(id, settings),
 		);
 		self.command_producer
 			.push(MixerCommand::AddTrack(track).into())?;
+		push_processing_order(
+			&mut self.command_producer,
+			&track_routing,
+			&resource_collector_handle,
+		)?;
 		Ok(handle)
 	}
 
@@ -471,9 +636,17 @@ impl AudioManager {
 		id: impl Into<SubTrackId>,
 	) -> Result<(), RemoveSubTrackError> {
 		let id = id.into();
+		let mut track_routing = TrackRouting::lock(&self.track_routing);
+		let resource_collector_handle = self.resource_collector().handle();
 		self.active_ids.remove_sub_track_id(id)?;
+		track_routing.remove_track(id.into());
 		self.command_producer
 			.push(MixerCommand::RemoveSubTrack(id).into())?;
+		push_processing_order(
+			&mut self.command_producer,
+			&track_routing,
+			&resource_collector_handle,
+		)?;
 		Ok(())
 	}
 
@@ -482,21 +655,59 @@ impl AudioManager {
 		&mut self,
 		settings: SendTrackSettings,
 	) -> Result<SendTrackHandle, AddSendTrackError> {
+		if !self.track_exists(settings.parent_track) {
+			return Err(AddSendTrackError::NonexistentParentTrack(
+				settings.parent_track,
+			));
+		}
+		for (send_track_id, _) in settings.sends.iter() {
+			if !self
+				.active_ids
+				.active_send_track_ids
+				.contains(send_track_id)
+			{
+				return Err(AddSendTrackError::NonexistentSendTrack(*send_track_id));
+			}
+		}
+		for ducking in settings.ducking.iter() {
+			if !self.track_exists(ducking.trigger_track) {
+				return Err(AddSendTrackError::NonexistentDuckingTriggerTrack(
+					ducking.trigger_track,
+				));
+			}
+		}
+		let mut track_routing = TrackRouting::lock(&self.track_routing);
 		let id = settings.id.unwrap_or(SendTrackId::new());
 		self.active_ids.add_send_track_id(id)?;
+		track_routing.add_track(
+			id.into(),
+			Some(settings.parent_track),
+			settings
+				.sends
+				.iter()
+				.map(|(send_track_id, _)| *send_track_id),
+			settings.num_sends.max(settings.sends.len()),
+		);
+		let resource_collector_handle = self.resource_collector().handle();
 		let handle = SendTrackHandle::new(
 			id,
 			&settings,
 			self.command_producer.clone(),
+			self.track_routing.clone(),
 			self.sample_rate,
-			self.resource_collector().handle(),
+			resource_collector_handle.clone(),
 		);
 		let track = Owned::new(
-			&self.resource_collector().handle(),
+			&resource_collector_handle,
 			Track::new_send_track(id, settings),
 		);
 		self.command_producer
 			.push(MixerCommand::AddTrack(track).into())?;
+		push_processing_order(
+			&mut self.command_producer,
+			&track_routing,
+			&resource_collector_handle,
+		)?;
 		Ok(handle)
 	}
 
@@ -506,9 +717,17 @@ impl AudioManager {
 		id: impl Into<SendTrackId>,
 	) -> Result<(), RemoveSendTrackError> {
 		let id = id.into();
+		let mut track_routing = TrackRouting::lock(&self.track_routing);
+		let resource_collector_handle = self.resource_collector().handle();
 		self.active_ids.remove_send_track_id(id)?;
+		track_routing.remove_track(id.into());
 		self.command_producer
 			.push(MixerCommand::RemoveSendTrack(id).into())?;
+		push_processing_order(
+			&mut self.command_producer,
+			&track_routing,
+			&resource_collector_handle,
+		)?;
 		Ok(())
 	}
 
@@ -520,9 +739,15 @@ impl AudioManager {
 		let id = settings.id.unwrap_or(GroupId::new());
 		self.active_ids.add_group_id(id)?;
 		let group = Owned::new(&self.resource_collector().handle(), Group::new(settings));
+		let handle = GroupHandle::new(
+			id,
+			group.num_active_instances(),
+			group.num_active_sequences(),
+			self.command_producer.clone(),
+		);
 		self.command_producer
 			.push(GroupCommand::AddGroup(id, group).into())?;
-		Ok(GroupHandle::new(id, self.command_producer.clone()))
+		Ok(handle)
 	}
 
 	/// Removes a group.
@@ -1,11 +1,35 @@
-use crate::{arrangement::Arrangement, sound::Sound};
+use crate::{
+	arrangement::Arrangement,
+	group::{GroupSet, GroupSettings},
+	instance::{
+		InstanceSends, InstanceSettings, InstanceState, StopInstanceSettings, VoiceStealingPolicy,
+	},
+	metronome::MetronomeSettings,
+	mixer::{
+		effect::{
+			distortion::{Distortion, DistortionSettings},
+			EffectSettings,
+		},
+		routing::TrackRouting,
+		ChangeDuckingError, ChangeRoutingError, DuckingSettings, DuckingTrigger, SendTrackSettings,
+		SubTrackSettings, TrackIndex, TrackSends,
+	},
+	parameter::Mapping,
+	sequence::{Sequence, SequenceSettings},
+	sound::{ResamplingQuality, Sound, SoundSettings},
+	spatial::{
+		hrtf::{HrtfDataset, HrtfMeasurement},
+		EmitterSettings, ListenerPolicy, ListenerSettings, OcclusionSettings, Panner, Rolloff,
+	},
+	Duration, Frame, PanLaw, Tempo,
+};
 
 use super::{
 	error::{
-		AddArrangementError, AddGroupError, AddMetronomeError, AddParameterError,
-		AddSendTrackError, AddSoundError, AddSubTrackError,
+		AddArrangementError, AddGroupError, AddListenerError, AddMetronomeError, AddParameterError,
+		AddSendTrackError, AddSoundError, AddSubTrackError, RemoveListenerError,
 	},
-	AudioManager, AudioManagerSettings,
+	AudioManager, AudioManagerSettings, Backend,
 };
 
 fn create_manager_with_limited_capacity() -> AudioManager {
@@ -102,3 +126,1291 @@ fn returns_error_on_exceeded_metronome_capacity() {
 }
 
 // TODO: write a test for exceeded stream capacity
+
+fn create_manager_with_voice_stealing_policy(
+	voice_stealing_policy: VoiceStealingPolicy,
+) -> (AudioManager, Backend) {
+	AudioManager::new_without_audio_thread(AudioManagerSettings {
+		num_instances: 2,
+		voice_stealing_policy,
+		..Default::default()
+	})
+}
+
+/// Creates a one second long sound that can be played
+/// any number of times in a row.
+fn create_long_sound(settings: SoundSettings) -> Sound {
+	Sound::from_frames(
+		48000,
+		vec![Frame::from_mono(1.0); 48000],
+		SoundSettings {
+			cooldown: None,
+			..settings
+		},
+	)
+}
+
+#[test]
+fn steals_oldest_instance_by_default() {
+	let (mut manager, mut backend) = create_manager_with_voice_stealing_policy(Default::default());
+	let mut sound = manager
+		.add_sound(create_long_sound(SoundSettings::new()))
+		.unwrap();
+	let first = sound.play(InstanceSettings::new()).unwrap();
+	let second = sound.play(InstanceSettings::new()).unwrap();
+	let third = sound.play(InstanceSettings::new()).unwrap();
+	backend.process();
+	assert_eq!(first.state(), InstanceState::Stopping);
+	assert_eq!(second.state(), InstanceState::Playing);
+	assert_eq!(third.state(), InstanceState::Playing);
+	// stolen instances fade out instead of being cut off
+	for _ in 0..1000 {
+		backend.process();
+	}
+	assert_eq!(first.state(), InstanceState::Stopped);
+}
+
+#[test]
+fn steals_quietest_instance() {
+	let (mut manager, mut backend) =
+		create_manager_with_voice_stealing_policy(VoiceStealingPolicy::Quietest);
+	let mut sound = manager
+		.add_sound(create_long_sound(SoundSettings::new()))
+		.unwrap();
+	let first = sound.play(InstanceSettings::new()).unwrap();
+	let second = sound.play(InstanceSettings::new().volume(0.5)).unwrap();
+	let third = sound.play(InstanceSettings::new()).unwrap();
+	backend.process();
+	assert_eq!(first.state(), InstanceState::Playing);
+	assert_eq!(second.state(), InstanceState::Stopping);
+	assert_eq!(third.state(), InstanceState::Playing);
+}
+
+#[test]
+fn steals_lowest_priority_instance() {
+	let (mut manager, mut backend) =
+		create_manager_with_voice_stealing_policy(VoiceStealingPolicy::LowestPriority);
+	let mut sound = manager
+		.add_sound(create_long_sound(SoundSettings::new()))
+		.unwrap();
+	let music = sound.play(InstanceSettings::new().priority(10)).unwrap();
+	let first = sound.play(InstanceSettings::new().priority(1)).unwrap();
+	let less_important = sound.play(InstanceSettings::new().priority(0)).unwrap();
+	backend.process();
+	assert_eq!(music.state(), InstanceState::Playing);
+	assert_eq!(first.state(), InstanceState::Playing);
+	assert_eq!(less_important.state(), InstanceState::Stopped);
+	let more_important = sound.play(InstanceSettings::new().priority(1)).unwrap();
+	backend.process();
+	assert_eq!(music.state(), InstanceState::Playing);
+	assert_eq!(first.state(), InstanceState::Stopping);
+	assert_eq!(more_important.state(), InstanceState::Playing);
+}
+
+#[test]
+fn refuses_new_instances_at_instance_limit() {
+	let (mut manager, mut backend) =
+		create_manager_with_voice_stealing_policy(VoiceStealingPolicy::Refuse);
+	let mut sound = manager
+		.add_sound(create_long_sound(SoundSettings::new()))
+		.unwrap();
+	let first = sound.play(InstanceSettings::new()).unwrap();
+	let second = sound.play(InstanceSettings::new()).unwrap();
+	let third = sound.play(InstanceSettings::new()).unwrap();
+	backend.process();
+	assert_eq!(first.state(), InstanceState::Playing);
+	assert_eq!(second.state(), InstanceState::Playing);
+	assert_eq!(third.state(), InstanceState::Stopped);
+}
+
+#[test]
+fn enforces_per_playable_instance_limits() {
+	let (mut manager, mut backend) = AudioManager::new_without_audio_thread(Default::default());
+	let mut limited_sound = manager
+		.add_sound(create_long_sound(SoundSettings::new().max_instances(1)))
+		.unwrap();
+	let mut refusing_sound = manager
+		.add_sound(create_long_sound(
+			SoundSettings::new()
+				.max_instances(1)
+				.voice_stealing_policy(VoiceStealingPolicy::Refuse),
+		))
+		.unwrap();
+	let first = limited_sound.play(InstanceSettings::new()).unwrap();
+	let second = limited_sound.play(InstanceSettings::new()).unwrap();
+	let third = refusing_sound.play(InstanceSettings::new()).unwrap();
+	let fourth = refusing_sound.play(InstanceSettings::new()).unwrap();
+	backend.process();
+	assert_eq!(first.state(), InstanceState::Stopping);
+	assert_eq!(second.state(), InstanceState::Playing);
+	assert_eq!(third.state(), InstanceState::Playing);
+	assert_eq!(fourth.state(), InstanceState::Stopped);
+}
+
+#[test]
+fn virtual_instances_advance_without_using_the_instance_budget() {
+	let (mut manager, mut backend) =
+		create_manager_with_voice_stealing_policy(VoiceStealingPolicy::Refuse);
+	let mut sound = manager
+		.add_sound(create_long_sound(SoundSettings::new()))
+		.unwrap();
+	let mut silent = sound.play(InstanceSettings::new().volume(0.0)).unwrap();
+	let first = sound.play(InstanceSettings::new()).unwrap();
+	let second = sound.play(InstanceSettings::new()).unwrap();
+	for _ in 0..480 {
+		backend.process();
+	}
+	assert_eq!(silent.state(), InstanceState::Playing);
+	assert_eq!(first.state(), InstanceState::Playing);
+	assert_eq!(second.state(), InstanceState::Playing);
+	assert!((silent.position() - first.position()).abs() < 0.000001);
+	// the virtual instance can't become real again while
+	// the instance budget is full
+	silent.set_volume(1.0).unwrap();
+	let output = backend.process();
+	assert!((output.left - 2.0f32.sqrt()).abs() < 0.0001);
+}
+
+#[test]
+fn enforces_the_virtual_instance_limit() {
+	let (mut manager, mut backend) = AudioManager::new_without_audio_thread(AudioManagerSettings {
+		num_instances: 1,
+		num_virtual_instances: 1,
+		voice_stealing_policy: VoiceStealingPolicy::Refuse,
+		..Default::default()
+	});
+	let mut sound = manager
+		.add_sound(create_long_sound(SoundSettings::new()))
+		.unwrap();
+	let first = sound.play(InstanceSettings::new().volume(0.0)).unwrap();
+	// once the virtual instance limit is reached, quiet
+	// instances use up the real instance budget
+	let second = sound.play(InstanceSettings::new().volume(0.0)).unwrap();
+	let third = sound.play(InstanceSettings::new().volume(0.0)).unwrap();
+	backend.process();
+	assert_eq!(first.state(), InstanceState::Playing);
+	assert_eq!(second.state(), InstanceState::Playing);
+	assert_eq!(third.state(), InstanceState::Stopped);
+}
+
+#[test]
+fn instances_silenced_by_their_groups_start_out_virtual() {
+	let (mut manager, mut backend) = AudioManager::new_without_audio_thread(AudioManagerSettings {
+		num_instances: 1,
+		voice_stealing_policy: VoiceStealingPolicy::Refuse,
+		..Default::default()
+	});
+	let group = manager.add_group(GroupSettings::new().volume(0.0)).unwrap();
+	let mut muted_sound = manager
+		.add_sound(create_long_sound(
+			SoundSettings::new().groups(GroupSet::new().add(&group)),
+		))
+		.unwrap();
+	let mut sound = manager
+		.add_sound(create_long_sound(SoundSettings::new()))
+		.unwrap();
+	let muted = muted_sound.play(InstanceSettings::new()).unwrap();
+	let audible = sound.play(InstanceSettings::new()).unwrap();
+	backend.process();
+	assert_eq!(muted.state(), InstanceState::Playing);
+	assert_eq!(audible.state(), InstanceState::Playing);
+}
+
+#[test]
+fn instances_loop_a_region_a_set_number_of_times() {
+	let (mut manager, mut backend) = AudioManager::new_without_audio_thread(Default::default());
+	let mut sound = manager
+		.add_sound(create_long_sound(SoundSettings::new()))
+		.unwrap();
+	let instance = sound
+		.play(
+			InstanceSettings::new()
+				.loop_start(0.25)
+				.loop_end(0.5)
+				.loop_count(2),
+		)
+		.unwrap();
+	for _ in 0..(48000 * 6 / 10) {
+		backend.process();
+	}
+	assert!((instance.position() - 0.35).abs() < 0.001);
+	for _ in 0..(48000 * 65 / 100) {
+		backend.process();
+	}
+	// after looping twice, the instance plays through the loop end point
+	assert!((instance.position() - 0.75).abs() < 0.001);
+	for _ in 0..(48000 * 3 / 10) {
+		backend.process();
+	}
+	assert_eq!(instance.state(), InstanceState::Stopped);
+}
+
+#[test]
+fn instances_can_leave_loops_while_playing() {
+	let (mut manager, mut backend) = AudioManager::new_without_audio_thread(Default::default());
+	let mut sound = manager
+		.add_sound(create_long_sound(SoundSettings::new()))
+		.unwrap();
+	let mut instance = sound
+		.play(InstanceSettings::new().loop_start(0.25).loop_end(0.5))
+		.unwrap();
+	for _ in 0..(48000 * 6 / 10) {
+		backend.process();
+	}
+	assert!((instance.position() - 0.35).abs() < 0.001);
+	instance.set_loop_region(0.0, 0.4).unwrap();
+	for _ in 0..(48000 / 10) {
+		backend.process();
+	}
+	assert!((instance.position() - 0.05).abs() < 0.001);
+	instance.exit_loop().unwrap();
+	for _ in 0..(48000 / 2) {
+		backend.process();
+	}
+	assert!((instance.position() - 0.55).abs() < 0.001);
+}
+
+#[test]
+fn instances_crossfade_loop_seams() {
+	let (mut manager, mut backend) = AudioManager::new_without_audio_thread(Default::default());
+	let frames = (0..48000)
+		.map(|i| Frame::from_mono(if i < 24000 { 0.0 } else { 1.0 }))
+		.collect();
+	let mut sound = manager
+		.add_sound(Sound::from_frames(
+			48000,
+			frames,
+			SoundSettings::new().default_loop_crossfade(0.1),
+		))
+		.unwrap();
+	sound
+		.play(InstanceSettings::new().loop_start(0.25))
+		.unwrap();
+	for _ in 0..(48000 * 85 / 100) {
+		backend.process();
+	}
+	// before the crossfade starts
+	assert!((backend.process().left - 0.5f32.sqrt()).abs() < 0.001);
+	for _ in 0..(48000 / 10) {
+		backend.process();
+	}
+	// halfway through the crossfade
+	assert!((backend.process().left - 0.5).abs() < 0.001);
+	for _ in 0..(48000 * 495 / 10000) {
+		backend.process();
+	}
+	// right before the loop point, the audio before the
+	// loop start point has faded in completely
+	assert!(backend.process().left.abs() < 0.01);
+}
+
+#[test]
+fn instances_can_start_after_a_delay() {
+	let (mut manager, mut backend) = AudioManager::new_without_audio_thread(Default::default());
+	let mut sound = manager
+		.add_sound(create_long_sound(SoundSettings::new()))
+		.unwrap();
+	let instance = sound
+		.play(InstanceSettings::new().start_delay(0.01))
+		.unwrap();
+	for _ in 0..480 {
+		assert_eq!(backend.process(), Frame::from_mono(0.0));
+	}
+	assert_eq!(instance.state(), InstanceState::WaitingToStart);
+	assert!(backend.process().left > 0.0);
+	assert_eq!(instance.state(), InstanceState::Playing);
+}
+
+#[test]
+fn instances_can_start_on_metronome_intervals() {
+	let (mut manager, mut backend) = AudioManager::new_without_audio_thread(Default::default());
+	let mut sound = manager
+		.add_sound(create_long_sound(SoundSettings::new()))
+		.unwrap();
+	let mut metronome = manager
+		.add_metronome(MetronomeSettings::new().tempo(Tempo(120.0)))
+		.unwrap();
+	metronome.start().unwrap();
+	for _ in 0..100 {
+		backend.process();
+	}
+	let instance = sound
+		.play(InstanceSettings::new().start_on_interval(&metronome, 1.0))
+		.unwrap();
+	// at 120 BPM, the next beat is half a second
+	// after the metronome started
+	for _ in 100..(48000 / 2 - 1) {
+		assert_eq!(backend.process(), Frame::from_mono(0.0));
+	}
+	assert_eq!(instance.state(), InstanceState::WaitingToStart);
+	assert!(backend.process().left > 0.0);
+	assert_eq!(instance.state(), InstanceState::Playing);
+}
+
+#[test]
+fn instances_apply_their_effects() {
+	let (mut manager, mut backend) = AudioManager::new_without_audio_thread(Default::default());
+	let mut sound = manager
+		.add_sound(create_long_sound(SoundSettings::new()))
+		.unwrap();
+	let mut instance = sound
+		.play(InstanceSettings::new().effect(DistortionSettings::new().drive(4.0)))
+		.unwrap();
+	assert!((backend.process().left - 0.25 * 0.5f32.sqrt()).abs() < 0.0001);
+	instance.set_effect_enabled(0, false).unwrap();
+	assert!((backend.process().left - 0.5f32.sqrt()).abs() < 0.0001);
+	instance.set_effect_enabled(0, true).unwrap();
+	instance
+		.set_effect(0, DistortionSettings::new().drive(2.0))
+		.unwrap();
+	assert!((backend.process().left - 0.5 * 0.5f32.sqrt()).abs() < 0.0001);
+}
+
+#[test]
+fn instances_send_audio_to_send_tracks() {
+	let (mut manager, mut backend) = AudioManager::new_without_audio_thread(Default::default());
+	let send_track = manager.add_send_track(SendTrackSettings::new()).unwrap();
+	let mut sound = manager
+		.add_sound(create_long_sound(SoundSettings::new()))
+		.unwrap();
+	let mut instance = sound
+		.play(InstanceSettings::new().sends(InstanceSends::new().add(&send_track, 0.5)))
+		.unwrap();
+	assert!((backend.process().left - 1.5 * 0.5f32.sqrt()).abs() < 0.0001);
+	instance.set_send(&send_track, 1.0).unwrap();
+	backend.process();
+	assert!((backend.process().left - 2.0 * 0.5f32.sqrt()).abs() < 0.0001);
+	instance.remove_send(&send_track).unwrap();
+	assert!((backend.process().left - 0.5f32.sqrt()).abs() < 0.0001);
+}
+
+fn create_manager_without_listener_smoothing() -> (AudioManager, Backend) {
+	AudioManager::new_without_audio_thread(AudioManagerSettings {
+		listener_smoothing: 0.0,
+		..Default::default()
+	})
+}
+
+#[test]
+fn emitters_are_attenuated_and_panned_relative_to_the_listener() {
+	let (mut manager, mut backend) = create_manager_without_listener_smoothing();
+	let mut sound = manager
+		.add_sound(create_long_sound(SoundSettings::new()))
+		.unwrap();
+	// to the right of the listener
+	sound
+		.play(
+			InstanceSettings::new().emitter(
+				EmitterSettings::new()
+					.position([2.0, 0.0, 0.0])
+					.smoothing(0.0),
+			),
+		)
+		.unwrap();
+	let out = backend.process();
+	assert!(out.left.abs() < 0.0001);
+	assert!((out.right - 0.5).abs() < 0.0001);
+	// turn the listener around so the emitter is on the left
+	manager
+		.listener()
+		.set_orientation([0.0, 0.0, 1.0], [0.0, 1.0, 0.0])
+		.unwrap();
+	let out = backend.process();
+	assert!((out.left - 0.5).abs() < 0.0001);
+	assert!(out.right.abs() < 0.0001);
+	// move the listener on top of the emitter
+	manager.listener().set_position([2.0, 0.0, 0.0]).unwrap();
+	let out = backend.process();
+	assert!((out.left - 0.5f32.sqrt()).abs() < 0.0001);
+	assert!((out.right - 0.5f32.sqrt()).abs() < 0.0001);
+}
+
+#[test]
+fn emitters_use_their_rolloff_curves() {
+	let (mut manager, mut backend) = create_manager_without_listener_smoothing();
+	let mut sound = manager
+		.add_sound(create_long_sound(SoundSettings::new()))
+		.unwrap();
+	let mut instance = sound
+		.play(
+			InstanceSettings::new().emitter(
+				EmitterSettings::new()
+					.position([0.0, 0.0, -5.0])
+					.rolloff(Rolloff::Linear)
+					.min_distance(0.0)
+					.max_distance(10.0)
+					.smoothing(0.0),
+			),
+		)
+		.unwrap();
+	assert!((backend.process().left - 0.5 * 0.5f32.sqrt()).abs() < 0.0001);
+	// beyond the max distance
+	instance.set_emitter_position([0.0, 0.0, -20.0]).unwrap();
+	assert!(backend.process().left.abs() < 0.0001);
+	instance.stop(Default::default()).unwrap();
+	backend.process();
+	sound
+		.play(
+			InstanceSettings::new().emitter(
+				EmitterSettings::new()
+					.position([0.0, 0.0, -3.0])
+					.rolloff(Rolloff::Custom(Mapping {
+						input_range: (0.0, 4.0),
+						output_range: (1.0, 0.0),
+						..Default::default()
+					}))
+					.smoothing(0.0),
+			),
+		)
+		.unwrap();
+	assert!((backend.process().left - 0.25 * 0.5f32.sqrt()).abs() < 0.0001);
+}
+
+#[test]
+fn emitter_movement_is_smoothed() {
+	let (mut manager, mut backend) = create_manager_without_listener_smoothing();
+	let mut sound = manager
+		.add_sound(create_long_sound(SoundSettings::new()))
+		.unwrap();
+	let mut instance = sound
+		.play(
+			InstanceSettings::new().emitter(
+				EmitterSettings::new()
+					.position([1.0, 0.0, 0.0])
+					.smoothing(0.01),
+			),
+		)
+		.unwrap();
+	assert!(backend.process().left.abs() < 0.0001);
+	instance.set_emitter_position([-1.0, 0.0, 0.0]).unwrap();
+	// the emitter hasn't jumped to the left yet
+	let out = backend.process();
+	assert!(out.right > out.left);
+	for _ in 0..48000 / 10 {
+		backend.process();
+	}
+	let out = backend.process();
+	assert!((out.left - 1.0).abs() < 0.0001);
+	assert!(out.right.abs() < 0.0001);
+}
+
+#[test]
+fn emitters_use_the_nearest_listener() {
+	let (mut manager, mut backend) = create_manager_without_listener_smoothing();
+	let listener = manager
+		.add_listener(ListenerSettings::new().position([10.0, 0.0, 0.0]))
+		.unwrap();
+	let mut sound = manager
+		.add_sound(create_long_sound(SoundSettings::new()))
+		.unwrap();
+	// right next to the second listener, on its left
+	sound
+		.play(
+			InstanceSettings::new().emitter(
+				EmitterSettings::new()
+					.position([9.0, 0.0, 0.0])
+					.smoothing(0.0),
+			),
+		)
+		.unwrap();
+	let out = backend.process();
+	assert!((out.left - 1.0).abs() < 0.0001);
+	assert!(out.right.abs() < 0.0001);
+	// only the main listener is left, which hears the emitter
+	// far away on its right
+	manager.remove_listener(&listener).unwrap();
+	let out = backend.process();
+	assert!(out.left.abs() < 0.0001);
+	assert!((out.right - 1.0 / 9.0).abs() < 0.0001);
+}
+
+#[test]
+fn emitters_with_invalid_positions_do_not_panic() {
+	let (mut manager, mut backend) = create_manager_without_listener_smoothing();
+	manager
+		.add_listener(ListenerSettings::new().position([10.0, 0.0, 0.0]))
+		.unwrap();
+	let mut sound = manager
+		.add_sound(create_long_sound(SoundSettings::new()))
+		.unwrap();
+	sound
+		.play(
+			InstanceSettings::new().emitter(
+				EmitterSettings::new()
+					.position([f64::NAN, 0.0, 0.0])
+					.smoothing(0.0),
+			),
+		)
+		.unwrap();
+	backend.process();
+}
+
+#[test]
+fn emitters_can_blend_every_listener() {
+	let (mut manager, mut backend) = AudioManager::new_without_audio_thread(AudioManagerSettings {
+		listener_smoothing: 0.0,
+		listener_policy: ListenerPolicy::NormalizedSum,
+		..Default::default()
+	});
+	manager
+		.add_listener(ListenerSettings::new().position([4.0, 0.0, 0.0]))
+		.unwrap();
+	let mut sound = manager
+		.add_sound(create_long_sound(SoundSettings::new()))
+		.unwrap();
+	// halfway between the listeners, on the main listener's right
+	// and the second listener's left
+	sound
+		.play(
+			InstanceSettings::new().emitter(
+				EmitterSettings::new()
+					.position([2.0, 0.0, 0.0])
+					.smoothing(0.0),
+			),
+		)
+		.unwrap();
+	let out = backend.process();
+	assert!((out.left - 0.5 * 0.5f32.sqrt()).abs() < 0.0001);
+	assert!((out.right - 0.5 * 0.5f32.sqrt()).abs() < 0.0001);
+}
+
+#[test]
+fn listener_limit_is_enforced() {
+	let (mut manager, _) = AudioManager::new_without_audio_thread(AudioManagerSettings {
+		num_listeners: 2,
+		..Default::default()
+	});
+	let listener = manager.add_listener(ListenerSettings::new()).unwrap();
+	if let Err(AddListenerError::ListenerLimitReached) =
+		manager.add_listener(ListenerSettings::new())
+	{
+	} else {
+		panic!("AudioManager::add_listener should return Err(AddListenerError::ListenerLimitReached) when the maximum number of listeners is exceeded");
+	}
+	let main_listener = manager.listener();
+	if let Err(RemoveListenerError::NoListenerWithId(_)) = manager.remove_listener(&main_listener) {
+	} else {
+		panic!("AudioManager::remove_listener should not remove the main listener");
+	}
+	manager.remove_listener(&listener).unwrap();
+	manager.add_listener(ListenerSettings::new()).unwrap();
+}
+
+#[test]
+fn moving_emitters_are_doppler_shifted() {
+	let (mut manager, mut backend) = create_manager_without_listener_smoothing();
+	let mut sound = manager
+		.add_sound(create_long_sound(SoundSettings::new()))
+		.unwrap();
+	// approaching the listener at a tenth of the speed of sound
+	let mut instance = sound
+		.play(
+			InstanceSettings::new().playback_rate(2.0).emitter(
+				EmitterSettings::new()
+					.position([0.0, 0.0, -100.0])
+					.velocity([0.0, 0.0, 34.3])
+					.smoothing(0.0),
+			),
+		)
+		.unwrap();
+	for _ in 0..4800 {
+		backend.process();
+	}
+	assert!((instance.position() - 0.2 / 0.9).abs() < 0.0001);
+	// moving away from the listener
+	instance.set_emitter_velocity([0.0, 0.0, -34.3]).unwrap();
+	instance.seek_to(0.0).unwrap();
+	for _ in 0..4800 {
+		backend.process();
+	}
+	assert!((instance.position() - 0.2 / 1.1).abs() < 0.0001);
+	// the listener following the emitter cancels out the effect
+	manager.listener().set_velocity([0.0, 0.0, -34.3]).unwrap();
+	instance.seek_to(0.0).unwrap();
+	for _ in 0..4800 {
+		backend.process();
+	}
+	assert!((instance.position() - 0.2).abs() < 0.0001);
+}
+
+#[test]
+fn occlusion_and_obstruction_reduce_volume() {
+	let (mut manager, mut backend) = AudioManager::new_without_audio_thread(Default::default());
+	let mut sound = manager
+		.add_sound(create_long_sound(SoundSettings::new()))
+		.unwrap();
+	let mut instance = sound
+		.play(InstanceSettings::new().occlusion(OcclusionSettings::new().smoothing(0.0)))
+		.unwrap();
+	assert!((backend.process().left - 0.5f32.sqrt()).abs() < 0.0001);
+	instance.set_occlusion(1.0).unwrap();
+	// give the low-pass filter time to settle
+	for _ in 0..1000 {
+		backend.process();
+	}
+	assert!((backend.process().left - 0.3 * 0.5f32.sqrt()).abs() < 0.0001);
+	instance.set_obstruction(1.0).unwrap();
+	for _ in 0..1000 {
+		backend.process();
+	}
+	assert!((backend.process().left - 0.3 * 0.7 * 0.5f32.sqrt()).abs() < 0.0001);
+	instance.set_occlusion(0.0).unwrap();
+	instance.set_obstruction(0.0).unwrap();
+	assert!((backend.process().left - 0.5f32.sqrt()).abs() < 0.0001);
+}
+
+#[test]
+fn obstruction_filters_out_high_frequencies() {
+	let (mut manager, mut backend) = AudioManager::new_without_audio_thread(Default::default());
+	let mut frame_index = 0;
+	let mut sound = manager
+		.add_sound(Sound::from_fn(
+			48000,
+			1.0,
+			|_| {
+				frame_index += 1;
+				Frame::from_mono(if frame_index % 2 == 0 { 1.0 } else { -1.0 })
+			},
+			SoundSettings {
+				cooldown: None,
+				..Default::default()
+			},
+		))
+		.unwrap();
+	let mut instance = sound
+		.play(
+			InstanceSettings::new().occlusion(
+				OcclusionSettings::new()
+					.obstruction_volume(Mapping {
+						output_range: (1.0, 1.0),
+						..Default::default()
+					})
+					.smoothing(0.0),
+			),
+		)
+		.unwrap();
+	for _ in 0..1000 {
+		backend.process();
+	}
+	assert!(backend.process().left.abs() > 0.5);
+	instance.set_obstruction(1.0).unwrap();
+	for _ in 0..1000 {
+		backend.process();
+	}
+	assert!(backend.process().left.abs() < 0.01);
+}
+
+#[test]
+fn emitters_can_use_hrtf_panning() {
+	let (mut manager, mut backend) = create_manager_without_listener_smoothing();
+	let mut sound = manager
+		.add_sound(create_long_sound(SoundSettings::new()))
+		.unwrap();
+	sound
+		.play(
+			InstanceSettings::new().emitter(
+				EmitterSettings::new()
+					.position([0.0, 0.0, 1.0])
+					.panner(Panner::Hrtf)
+					.smoothing(0.0),
+			),
+		)
+		.unwrap();
+	// without a dataset, the instance falls back to stereo panning
+	let out = backend.process();
+	assert!((out.left - 0.5f32.sqrt()).abs() < 0.0001);
+	assert!((out.right - 0.5f32.sqrt()).abs() < 0.0001);
+	// the emitter is behind the listener
+	manager
+		.set_hrtf_dataset(HrtfDataset::new(
+			48000,
+			vec![
+				HrtfMeasurement {
+					azimuth: 0.0,
+					elevation: 0.0,
+					left: vec![1.0],
+					right: vec![1.0],
+				},
+				HrtfMeasurement {
+					azimuth: 180.0,
+					elevation: 0.0,
+					left: vec![0.25],
+					right: vec![0.75],
+				},
+			],
+		))
+		.unwrap();
+	let out = backend.process();
+	assert!((out.left - 0.25).abs() < 0.0001);
+	assert!((out.right - 0.75).abs() < 0.0001);
+	manager.remove_hrtf_dataset().unwrap();
+	let out = backend.process();
+	assert!((out.left - 0.5f32.sqrt()).abs() < 0.0001);
+}
+
+#[test]
+fn cut_off_instances_free_their_hrtf_state() {
+	let (mut manager, mut backend) = AudioManager::new_without_audio_thread(AudioManagerSettings {
+		num_instances: 1,
+		num_virtual_instances: 0,
+		num_hrtf_instances: 1,
+		listener_smoothing: 0.0,
+		..Default::default()
+	});
+	manager
+		.set_hrtf_dataset(HrtfDataset::new(
+			48000,
+			vec![
+				HrtfMeasurement {
+					azimuth: 0.0,
+					elevation: 0.0,
+					left: vec![1.0],
+					right: vec![1.0],
+				},
+				HrtfMeasurement {
+					azimuth: 180.0,
+					elevation: 0.0,
+					left: vec![0.25],
+					right: vec![0.75],
+				},
+			],
+		))
+		.unwrap();
+	let mut sound = manager
+		.add_sound(create_long_sound(SoundSettings::new()))
+		.unwrap();
+	let settings = InstanceSettings::new().emitter(
+		EmitterSettings::new()
+			.position([0.0, 0.0, 1.0])
+			.panner(Panner::Hrtf)
+			.smoothing(0.0),
+	);
+	// each instance steals the previous one, and from the third
+	// one on, each new instance cuts off the instance that was
+	// stolen before the last one while it's still fading out
+	for _ in 0..4 {
+		sound.play(settings).unwrap();
+		backend.process();
+	}
+	for _ in 0..1000 {
+		backend.process();
+	}
+	let out = backend.process();
+	assert!((out.left - 0.25).abs() < 0.0001);
+	assert!((out.right - 0.75).abs() < 0.0001);
+}
+
+#[test]
+fn instances_use_the_default_pan_law() {
+	let (mut manager, mut backend) = AudioManager::new_without_audio_thread(AudioManagerSettings {
+		pan_law: PanLaw::Linear,
+		..Default::default()
+	});
+	let mut sound = manager
+		.add_sound(create_long_sound(SoundSettings::new()))
+		.unwrap();
+	let mut instance = sound.play(InstanceSettings::new()).unwrap();
+	assert_eq!(backend.process(), Frame::from_mono(0.5));
+	instance.set_panning(0.25).unwrap();
+	assert_eq!(backend.process(), Frame::new(0.75, 0.25));
+}
+
+#[test]
+fn instances_can_override_the_pan_law() {
+	let (mut manager, mut backend) = AudioManager::new_without_audio_thread(Default::default());
+	let mut sound = manager
+		.add_sound(create_long_sound(SoundSettings::new()))
+		.unwrap();
+	sound
+		.play(InstanceSettings::new().pan_law(PanLaw::Compromise))
+		.unwrap();
+	let out = backend.process();
+	assert!((out.left - 0.5f32.powf(0.75)).abs() < 0.0001);
+	assert!((out.right - 0.5f32.powf(0.75)).abs() < 0.0001);
+}
+
+#[test]
+fn balance_pan_law_keeps_stereo_sources_intact() {
+	let (mut manager, mut backend) = AudioManager::new_without_audio_thread(Default::default());
+	let mut sound = manager
+		.add_sound(Sound::from_frames(
+			48000,
+			vec![Frame::new(1.0, -1.0); 48000],
+			Default::default(),
+		))
+		.unwrap();
+	let mut instance = sound
+		.play(InstanceSettings::new().pan_law(PanLaw::Balance))
+		.unwrap();
+	assert_eq!(backend.process(), Frame::new(1.0, -1.0));
+	instance.set_panning(0.75).unwrap();
+	assert_eq!(backend.process(), Frame::new(0.5, -1.0));
+}
+
+#[test]
+fn groups_scale_the_volume_of_their_instances() {
+	let (mut manager, mut backend) = AudioManager::new_without_audio_thread(Default::default());
+	let mut parent = manager.add_group(GroupSettings::new().volume(0.5)).unwrap();
+	let child = manager
+		.add_group(
+			GroupSettings::new()
+				.groups(GroupSet::new().add(&parent))
+				.volume(0.5),
+		)
+		.unwrap();
+	let mut sound = manager
+		.add_sound(create_long_sound(
+			SoundSettings::new().groups(GroupSet::new().add(&child)),
+		))
+		.unwrap();
+	sound.play(InstanceSettings::new()).unwrap();
+	assert!((backend.process().left - 0.25 * 0.5f32.sqrt()).abs() < 0.0001);
+	parent.set_volume(1.0).unwrap();
+	assert!((backend.process().left - 0.5 * 0.5f32.sqrt()).abs() < 0.0001);
+}
+
+#[test]
+fn shared_ancestor_groups_only_scale_instances_once() {
+	let (mut manager, mut backend) = AudioManager::new_without_audio_thread(Default::default());
+	let parent = manager.add_group(GroupSettings::new().volume(0.5)).unwrap();
+	let first_child = manager
+		.add_group(
+			GroupSettings::new()
+				.groups(GroupSet::new().add(&parent))
+				.volume(0.5),
+		)
+		.unwrap();
+	let second_child = manager
+		.add_group(GroupSettings::new().groups(GroupSet::new().add(&parent)))
+		.unwrap();
+	let mut sound = manager
+		.add_sound(create_long_sound(
+			SoundSettings::new().groups(GroupSet::new().add(&first_child).add(&second_child)),
+		))
+		.unwrap();
+	sound.play(InstanceSettings::new()).unwrap();
+	assert!((backend.process().left - 0.25 * 0.5f32.sqrt()).abs() < 0.0001);
+}
+
+#[test]
+fn groups_scale_the_playback_rate_of_their_instances() {
+	let (mut manager, mut backend) = AudioManager::new_without_audio_thread(Default::default());
+	let mut group = manager
+		.add_group(GroupSettings::new().playback_rate(0.5))
+		.unwrap();
+	let mut sound = manager
+		.add_sound(create_long_sound(
+			SoundSettings::new().groups(GroupSet::new().add(&group)),
+		))
+		.unwrap();
+	let instance = sound.play(InstanceSettings::new()).unwrap();
+	for _ in 0..4800 {
+		backend.process();
+	}
+	assert!((instance.position() - 0.05).abs() < 0.001);
+	group.set_playback_rate(2.0).unwrap();
+	for _ in 0..4800 {
+		backend.process();
+	}
+	assert!((instance.position() - 0.25).abs() < 0.001);
+}
+
+#[test]
+fn enforces_group_instance_limits() {
+	let (mut manager, mut backend) = AudioManager::new_without_audio_thread(Default::default());
+	let group = manager
+		.add_group(GroupSettings::new().max_instances(2))
+		.unwrap();
+	let refusing_group = manager
+		.add_group(
+			GroupSettings::new()
+				.max_instances(1)
+				.voice_stealing_policy(VoiceStealingPolicy::Refuse),
+		)
+		.unwrap();
+	let mut first_sound = manager
+		.add_sound(create_long_sound(
+			SoundSettings::new().groups(GroupSet::new().add(&group)),
+		))
+		.unwrap();
+	let mut second_sound = manager
+		.add_sound(create_long_sound(
+			SoundSettings::new().groups(GroupSet::new().add(&group)),
+		))
+		.unwrap();
+	let mut refusing_sound = manager
+		.add_sound(create_long_sound(
+			SoundSettings::new().groups(GroupSet::new().add(&refusing_group)),
+		))
+		.unwrap();
+	let first = first_sound.play(InstanceSettings::new()).unwrap();
+	let second = second_sound.play(InstanceSettings::new()).unwrap();
+	let third = first_sound.play(InstanceSettings::new()).unwrap();
+	let fourth = refusing_sound.play(InstanceSettings::new()).unwrap();
+	let fifth = refusing_sound.play(InstanceSettings::new()).unwrap();
+	backend.process();
+	assert_eq!(first.state(), InstanceState::Stopping);
+	assert_eq!(second.state(), InstanceState::Playing);
+	assert_eq!(third.state(), InstanceState::Playing);
+	assert_eq!(fourth.state(), InstanceState::Playing);
+	assert_eq!(fifth.state(), InstanceState::Stopped);
+}
+
+#[test]
+fn refusing_group_limits_are_checked_before_stealing() {
+	let (mut manager, mut backend) = AudioManager::new_without_audio_thread(Default::default());
+	let stealing_group = manager
+		.add_group(GroupSettings::new().max_instances(1))
+		.unwrap();
+	let refusing_group = manager
+		.add_group(
+			GroupSettings::new()
+				.max_instances(1)
+				.voice_stealing_policy(VoiceStealingPolicy::Refuse),
+		)
+		.unwrap();
+	let mut sound = manager
+		.add_sound(create_long_sound(
+			SoundSettings::new().groups(GroupSet::new().add(&stealing_group).add(&refusing_group)),
+		))
+		.unwrap();
+	let first = sound.play(InstanceSettings::new()).unwrap();
+	let second = sound.play(InstanceSettings::new()).unwrap();
+	backend.process();
+	// the stealing group doesn't stop the first instance
+	// to make room for an instance the refusing group rejects
+	assert_eq!(first.state(), InstanceState::Playing);
+	assert_eq!(second.state(), InstanceState::Stopped);
+}
+
+#[test]
+fn group_handles_report_active_instances_and_sequences() {
+	let (mut manager, mut backend) = AudioManager::new_without_audio_thread(Default::default());
+	let parent = manager.add_group(GroupSettings::new()).unwrap();
+	let child = manager
+		.add_group(GroupSettings::new().groups(GroupSet::new().add(&parent)))
+		.unwrap();
+	let mut sound = manager
+		.add_sound(create_long_sound(
+			SoundSettings::new().groups(GroupSet::new().add(&child)),
+		))
+		.unwrap();
+	let mut instance = sound.play(InstanceSettings::new()).unwrap();
+	sound.play(InstanceSettings::new()).unwrap();
+	let mut sequence =
+		Sequence::<()>::new(SequenceSettings::new().groups(GroupSet::new().add(&parent)));
+	sequence.wait(Duration::Seconds(1.0));
+	let mut sequence = manager
+		.start_sequence(sequence, Default::default())
+		.unwrap();
+	backend.process();
+	assert_eq!(child.num_active_instances(), 2);
+	assert_eq!(parent.num_active_instances(), 2);
+	assert_eq!(child.num_active_sequences(), 0);
+	assert_eq!(parent.num_active_sequences(), 1);
+	instance
+		.stop(StopInstanceSettings::new().fade_tween(None))
+		.unwrap();
+	sequence.stop().unwrap();
+	backend.process();
+	backend.process();
+	assert_eq!(parent.num_active_instances(), 1);
+	assert_eq!(parent.num_active_sequences(), 0);
+}
+
+#[test]
+fn removed_groups_stop_affecting_their_descendants() {
+	let (mut manager, mut backend) = AudioManager::new_without_audio_thread(Default::default());
+	// put the groups on either side of the boundary between
+	// words of the groups' ancestry bit sets
+	for _ in 0..62 {
+		manager.add_group(GroupSettings::new()).unwrap();
+	}
+	let grandparent = manager.add_group(GroupSettings::new().volume(0.5)).unwrap();
+	let parent = manager
+		.add_group(GroupSettings::new().groups(GroupSet::new().add(&grandparent)))
+		.unwrap();
+	let child = manager
+		.add_group(GroupSettings::new().groups(GroupSet::new().add(&parent)))
+		.unwrap();
+	let unrelated = manager.add_group(GroupSettings::new().volume(0.5)).unwrap();
+	let mut child_sound = manager
+		.add_sound(create_long_sound(
+			SoundSettings::new().groups(GroupSet::new().add(&child)),
+		))
+		.unwrap();
+	let mut unrelated_sound = manager
+		.add_sound(create_long_sound(
+			SoundSettings::new().groups(GroupSet::new().add(&unrelated)),
+		))
+		.unwrap();
+	child_sound.play(InstanceSettings::new()).unwrap();
+	unrelated_sound.play(InstanceSettings::new()).unwrap();
+	assert!((backend.process().left - 0.5f32.sqrt()).abs() < 0.0001);
+	manager.remove_group(parent.id()).unwrap();
+	assert!((backend.process().left - 1.5 * 0.5f32.sqrt()).abs() < 0.0001);
+}
+
+#[test]
+fn sequence_steps_take_effect_in_order() {
+	let (mut manager, mut backend) = AudioManager::new_without_audio_thread(Default::default());
+	let sound = manager
+		.add_sound(create_long_sound(SoundSettings::new()))
+		.unwrap();
+	let mut sequence = Sequence::<()>::new(SequenceSettings::new());
+	let instance_id = sequence.play(sound.id(), InstanceSettings::new());
+	sequence.set_instance_volume(instance_id, 0.5.into());
+	manager
+		.start_sequence(sequence, Default::default())
+		.unwrap();
+	// the instance already has the volume set by the next step
+	// when it starts playing
+	assert!((backend.process().left - 0.5 * 0.5f32.sqrt()).abs() < 0.0001);
+}
+
+#[test]
+fn sub_tracks_can_be_rerouted_at_runtime() {
+	let (mut manager, mut backend) = AudioManager::new_without_audio_thread(Default::default());
+	let mut bus = manager
+		.add_sub_track(SubTrackSettings::new().volume(0.5))
+		.unwrap();
+	let mut stem = manager.add_sub_track(SubTrackSettings::new()).unwrap();
+	let mut sound = manager
+		.add_sound(create_long_sound(SoundSettings::new()))
+		.unwrap();
+	sound
+		.play(InstanceSettings::new().track(stem.id()))
+		.unwrap();
+	assert!((backend.process().left - 0.5f32.sqrt()).abs() < 0.0001);
+	stem.set_parent_track(&bus).unwrap();
+	assert!((backend.process().left - 0.5 * 0.5f32.sqrt()).abs() < 0.0001);
+	if let Err(ChangeRoutingError::WouldCreateCycle) = bus.set_parent_track(&stem) {
+	} else {
+		panic!("SubTrackHandle::set_parent_track should return Err(ChangeRoutingError::WouldCreateCycle) when the change would route a track into itself");
+	}
+	stem.set_parent_track(TrackIndex::Main).unwrap();
+	assert!((backend.process().left - 0.5f32.sqrt()).abs() < 0.0001);
+	bus.set_parent_track(&stem).unwrap();
+}
+
+#[test]
+fn sub_track_sends_can_be_edited_at_runtime() {
+	let (mut manager, mut backend) = AudioManager::new_without_audio_thread(Default::default());
+	let first_send = manager.add_send_track(SendTrackSettings::new()).unwrap();
+	let second_send = manager
+		.add_send_track(SendTrackSettings::new().volume(2.0))
+		.unwrap();
+	let mut track = manager
+		.add_sub_track(SubTrackSettings::new().num_sends(1))
+		.unwrap();
+	let mut sound = manager
+		.add_sound(create_long_sound(SoundSettings::new()))
+		.unwrap();
+	sound
+		.play(InstanceSettings::new().track(track.id()))
+		.unwrap();
+	assert!((backend.process().left - 0.5f32.sqrt()).abs() < 0.0001);
+	track.add_send(&first_send, 0.5).unwrap();
+	assert!((backend.process().left - 1.5 * 0.5f32.sqrt()).abs() < 0.0001);
+	if let Err(ChangeRoutingError::SendLimitReached) = track.add_send(&second_send, 0.5) {
+	} else {
+		panic!("SubTrackHandle::add_send should return Err(ChangeRoutingError::SendLimitReached) when the maximum number of sends is exceeded");
+	}
+	track.retarget_send(&first_send, &second_send).unwrap();
+	assert!((backend.process().left - 2.0 * 0.5f32.sqrt()).abs() < 0.0001);
+	track.remove_send(&second_send).unwrap();
+	assert!((backend.process().left - 0.5f32.sqrt()).abs() < 0.0001);
+	if let Err(ChangeRoutingError::NoSendToTrack(_)) = track.remove_send(&second_send) {
+	} else {
+		panic!("SubTrackHandle::remove_send should return Err(ChangeRoutingError::NoSendToTrack) when the track isn't sending to the send track");
+	}
+}
+
+#[test]
+fn sub_track_ducking_can_be_edited_at_runtime() {
+	let (mut manager, mut backend) = AudioManager::new_without_audio_thread(Default::default());
+	let (mut other_manager, _) = AudioManager::new_without_audio_thread(Default::default());
+	let other_track = other_manager
+		.add_sub_track(SubTrackSettings::new())
+		.unwrap();
+	let dialog_track = manager.add_sub_track(SubTrackSettings::new()).unwrap();
+	let stinger_track = manager.add_sub_track(SubTrackSettings::new()).unwrap();
+	let mut music_track = manager
+		.add_sub_track(SubTrackSettings::new().num_ducking_rules(1))
+		.unwrap();
+	if let Err(ChangeDuckingError::NonexistentTriggerTrack(_)) =
+		music_track.add_ducking(DuckingSettings::new(&other_track))
+	{
+	} else {
+		panic!("SubTrackHandle::add_ducking should return Err(ChangeDuckingError::NonexistentTriggerTrack) when the trigger track does not exist");
+	}
+	music_track
+		.add_ducking(
+			DuckingSettings::new(&dialog_track)
+				.trigger(DuckingTrigger::ActiveInstances)
+				.volume(0.5)
+				.attack(0.0)
+				.release(0.0),
+		)
+		.unwrap();
+	if let Err(ChangeDuckingError::DuckingRuleLimitReached) =
+		music_track.add_ducking(DuckingSettings::new(&stinger_track))
+	{
+	} else {
+		panic!("SubTrackHandle::add_ducking should return Err(ChangeDuckingError::DuckingRuleLimitReached) when the maximum number of ducking rules is exceeded");
+	}
+	let mut sound = manager
+		.add_sound(create_long_sound(SoundSettings::new()))
+		.unwrap();
+	sound
+		.play(InstanceSettings::new().track(music_track.id()))
+		.unwrap();
+	// a virtual instance still counts as an active instance
+	sound
+		.play(InstanceSettings::new().track(dialog_track.id()).volume(0.0))
+		.unwrap();
+	// ducking responds to the previous frame of the trigger track
+	assert!((backend.process().left - 0.5f32.sqrt()).abs() < 0.0001);
+	assert!((backend.process().left - 0.5 * 0.5f32.sqrt()).abs() < 0.0001);
+	music_track.remove_ducking(&dialog_track).unwrap();
+	assert!((backend.process().left - 0.5f32.sqrt()).abs() < 0.0001);
+	if let Err(ChangeDuckingError::NoDuckingRule(_)) = music_track.remove_ducking(&dialog_track) {
+	} else {
+		panic!("SubTrackHandle::remove_ducking should return Err(ChangeDuckingError::NoDuckingRule) when the track has no ducking rule with the trigger track");
+	}
+}
+
+#[test]
+fn removing_a_send_track_frees_up_sends_to_it() {
+	let (mut manager, mut backend) = AudioManager::new_without_audio_thread(Default::default());
+	let first_send = manager.add_send_track(SendTrackSettings::new()).unwrap();
+	let mut track = manager
+		.add_sub_track(
+			SubTrackSettings::new()
+				.num_sends(1)
+				.sends(TrackSends::new().add(&first_send, 1.0)),
+		)
+		.unwrap();
+	manager.remove_send_track(first_send.id()).unwrap();
+	let second_send = manager.add_send_track(SendTrackSettings::new()).unwrap();
+	track.add_send(&second_send, 0.5).unwrap();
+	let mut sound = manager
+		.add_sound(create_long_sound(SoundSettings::new()))
+		.unwrap();
+	sound
+		.play(InstanceSettings::new().track(track.id()))
+		.unwrap();
+	assert!((backend.process().left - 1.5 * 0.5f32.sqrt()).abs() < 0.0001);
+}
+
+#[test]
+fn send_tracks_can_be_routed_through_other_tracks() {
+	let (mut manager, mut backend) = AudioManager::new_without_audio_thread(Default::default());
+	let mut bus = manager
+		.add_send_track(SendTrackSettings::new().volume(0.5))
+		.unwrap();
+	let reverb = manager
+		.add_send_track(SendTrackSettings::new().parent_track(&bus))
+		.unwrap();
+	let track = manager
+		.add_sub_track(SubTrackSettings::new().sends(TrackSends::new().add(&reverb, 1.0)))
+		.unwrap();
+	let mut sound = manager
+		.add_sound(create_long_sound(SoundSettings::new()))
+		.unwrap();
+	sound
+		.play(InstanceSettings::new().track(track.id()))
+		.unwrap();
+	assert!((backend.process().left - 1.5 * 0.5f32.sqrt()).abs() < 0.0001);
+	if let Err(ChangeRoutingError::WouldCreateCycle) = bus.set_parent_track(&reverb) {
+	} else {
+		panic!("SendTrackHandle::set_parent_track should return Err(ChangeRoutingError::WouldCreateCycle) when the change would route a track into itself");
+	}
+	if let Err(ChangeRoutingError::WouldCreateCycle) = bus.add_send(&reverb, 1.0) {
+	} else {
+		panic!("SendTrackHandle::add_send should return Err(ChangeRoutingError::WouldCreateCycle) when the change would route a track into itself");
+	}
+}
+
+#[test]
+fn returns_error_on_nonexistent_send_track_parent() {
+	let (mut manager, _) = AudioManager::new_without_audio_thread(Default::default());
+	let (mut other_manager, _) = AudioManager::new_without_audio_thread(Default::default());
+	let other_track = other_manager
+		.add_send_track(SendTrackSettings::new())
+		.unwrap();
+	if let Err(AddSendTrackError::NonexistentParentTrack(_)) =
+		manager.add_send_track(SendTrackSettings::new().parent_track(&other_track))
+	{
+	} else {
+		panic!("AudioManager::add_send_track should return Err(AddSendTrackError::NonexistentParentTrack) when the parent track does not exist");
+	}
+}
+
+#[test]
+fn returns_error_on_nonexistent_sidechain_track() {
+	let (mut manager, _) = AudioManager::new_without_audio_thread(Default::default());
+	let (mut other_manager, _) = AudioManager::new_without_audio_thread(Default::default());
+	let other_track = other_manager
+		.add_sub_track(SubTrackSettings::new())
+		.unwrap();
+	let mut track = manager.add_sub_track(SubTrackSettings::new()).unwrap();
+	assert!(track
+		.add_effect(
+			Distortion::new(DistortionSettings::new()),
+			EffectSettings::new().sidechain(other_track.id()),
+		)
+		.is_err());
+	assert!(manager
+		.main_track()
+		.add_effect(
+			Distortion::new(DistortionSettings::new()),
+			EffectSettings::new().sidechain(other_track.id()),
+		)
+		.is_err());
+}
+
+#[test]
+fn main_track_effects_record_their_sidechain_inputs() {
+	let (mut manager, _) = AudioManager::new_without_audio_thread(Default::default());
+	let sub_track = manager.add_sub_track(SubTrackSettings::new()).unwrap();
+	let mut main_track = manager.main_track();
+	let effect = main_track
+		.add_effect(
+			Distortion::new(DistortionSettings::new()),
+			EffectSettings::new().sidechain(sub_track.id()),
+		)
+		.unwrap();
+	let has_sidechain = |manager: &AudioManager| {
+		TrackRouting::lock(&manager.track_routing)
+			.clone()
+			.remove_sidechain(TrackIndex::Main, effect.id())
+	};
+	assert!(has_sidechain(&manager));
+	main_track.remove_effect(effect.id()).unwrap();
+	assert!(!has_sidechain(&manager));
+}
+
+#[test]
+fn instances_play_every_sample_exactly_at_the_device_sample_rate() {
+	let (mut manager, mut backend) = AudioManager::new_without_audio_thread(Default::default());
+	let frames = (0..48000)
+		.map(|i| Frame::from_mono(((i * 7919) % 1000) as f32 / 1000.0))
+		.collect::<Vec<_>>();
+	let mut sound = manager
+		.add_sound(Sound::from_frames(
+			48000,
+			frames.clone(),
+			SoundSettings::new().resampling_quality(ResamplingQuality::Sinc),
+		))
+		.unwrap();
+	let mut instance = sound.play(InstanceSettings::new()).unwrap();
+	for frame in &frames[..1000] {
+		assert_eq!(backend.process(), frame.panned(0.5));
+	}
+	instance.seek_to(0.5).unwrap();
+	backend.process();
+	for frame in &frames[24001..25000] {
+		assert_eq!(backend.process(), frame.panned(0.5));
+	}
+}
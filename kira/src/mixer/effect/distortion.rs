@@ -92,6 +92,12 @@ impl Distortion {
 			drive: CachedValue::new(settings.drive, 1.0),
 		}
 	}
+
+	/// Changes the distortion's settings.
+	pub(crate) fn set_settings(&mut self, settings: DistortionSettings) {
+		self.kind = settings.kind;
+		self.drive.set(settings.drive);
+	}
 }
 
 impl Effect for Distortion {
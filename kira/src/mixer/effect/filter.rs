@@ -108,6 +108,13 @@ impl Filter {
 			ic2eq: Frame::from_mono(0.0),
 		}
 	}
+
+	/// Changes the filter's settings without resetting its state.
+	pub(crate) fn set_settings(&mut self, settings: FilterSettings) {
+		self.mode = settings.mode;
+		self.cutoff.set(settings.cutoff);
+		self.resonance.set(settings.resonance);
+	}
 }
 
 impl Effect for Filter {
@@ -12,7 +12,7 @@ use std::fmt::Debug;
 
 use uuid::Uuid;
 
-use crate::{frame::Frame, parameter::Parameters, Value};
+use crate::{frame::Frame, mixer::TrackIndex, parameter::Parameters, Value};
 
 /// A unique identifier for an effect.
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
@@ -55,6 +55,11 @@ pub struct EffectSettings {
 	/// (affected) signal to output. 0.0 is fully dry,
 	/// 1.0 is fully wet.
 	pub mix: Value<f64>,
+	/// The track whose output the effect receives as a
+	/// sidechain (key) input, if any.
+	///
+	/// See [`Effect::process_with_sidechain`] for more details.
+	pub sidechain: Option<TrackIndex>,
 }
 
 impl EffectSettings {
@@ -85,6 +90,15 @@ impl EffectSettings {
 			..self
 		}
 	}
+
+	/// Sets the track whose output the effect receives as a
+	/// sidechain (key) input.
+	pub fn sidechain(self, track: impl Into<TrackIndex>) -> Self {
+		Self {
+			sidechain: Some(track.into()),
+			..self
+		}
+	}
 }
 
 impl Default for EffectSettings {
@@ -93,6 +107,7 @@ impl Default for EffectSettings {
 			id: None,
 			enabled: true,
 			mix: Value::Fixed(1.0),
+			sidechain: None,
 		}
 	}
 }
@@ -112,4 +127,26 @@ pub trait Effect: Send + Debug {
 	/// This is useful in conjunction with [`CachedValue`](crate::CachedValue)s,
 	/// which can respond to parameter changes and update their value accordingly.
 	fn process(&mut self, dt: f64, input: Frame, parameters: &Parameters) -> Frame;
+
+	/// Transforms an input frame using the output of another
+	/// track as a key signal.
+	///
+	/// This is called instead of [`process`](Effect::process) when
+	/// the effect has a [`sidechain`](EffectSettings::sidechain) track.
+	/// `sidechain` is the output of that track for the current frame.
+	/// If the sidechain track depends on this effect's track (for example,
+	/// if it's the main track or one of this track's parent tracks),
+	/// `sidechain` is the output of that track for the previous frame instead.
+	///
+	/// By default, this ignores the sidechain input and calls
+	/// [`process`](Effect::process).
+	fn process_with_sidechain(
+		&mut self,
+		dt: f64,
+		input: Frame,
+		sidechain: Frame,
+		parameters: &Parameters,
+	) -> Frame {
+		self.process(dt, input, parameters)
+	}
 }
@@ -2,12 +2,17 @@ use basedrop::Owned;
 
 use crate::{frame::Frame, parameter::Parameters, CachedValue};
 
-use super::effect::{Effect, EffectSettings};
+use super::{
+	effect::{Effect, EffectSettings},
+	TrackIndex,
+};
 
 pub(crate) struct EffectSlot {
 	effect: Owned<Box<dyn Effect>>,
 	pub enabled: bool,
 	pub mix: CachedValue<f64>,
+	sidechain: Option<TrackIndex>,
+	sidechain_input: Frame,
 }
 
 impl EffectSlot {
@@ -16,13 +21,32 @@ impl EffectSlot {
 			effect,
 			enabled: settings.enabled,
 			mix: CachedValue::new(settings.mix, 1.0).with_valid_range(0.0..1.0),
+			sidechain: settings.sidechain,
+			sidechain_input: Frame::from_mono(0.0),
 		}
 	}
 
+	/// The track the effect receives a sidechain input from, if any.
+	pub fn sidechain(&self) -> Option<TrackIndex> {
+		self.sidechain
+	}
+
+	/// Sets the sidechain input the effect will receive
+	/// the next time it's processed.
+	pub fn set_sidechain_input(&mut self, sidechain_input: Frame) {
+		self.sidechain_input = sidechain_input;
+	}
+
 	pub(super) fn process(&mut self, dt: f64, input: Frame, parameters: &Parameters) -> Frame {
 		self.mix.update(parameters);
 		if self.enabled {
-			let wet = self.effect.process(dt, input, parameters);
+			let wet = match self.sidechain {
+				Some(_) => {
+					self.effect
+						.process_with_sidechain(dt, input, self.sidechain_input, parameters)
+				}
+				None => self.effect.process(dt, input, parameters),
+			};
 			input + (wet - input) * self.mix.value() as f32
 		} else {
 			input
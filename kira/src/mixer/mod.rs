@@ -17,6 +17,9 @@
 //! routed to. These are useful for applying effects to sounds
 //! from multiple sub-tracks.
 //!
+//! Send tracks can have their own parent tracks and sends, so
+//! a reverb send track can feed a shared bus, for example.
+//!
 //! Each instance of a sound or arrangement can have its audio
 //! sent to the main track, a sub-track, or a send track.
 //!
@@ -67,14 +70,88 @@
 //!        ▼
 //!   Output audio
 //! ```
+//!
+//! ## Ducking
+//!
+//! Sub-tracks and send tracks can automatically lower their volume
+//! while other tracks are playing audio by setting
+//! [`SubTrackSettings::ducking`] or [`SendTrackSettings::ducking`].
+//! For example, the music track can duck while the dialog track
+//! has signal above a threshold:
+//!
+//! ```no_run
+//! # use kira::{manager::AudioManager, mixer::{DuckingSettings, SubTrackSettings, TrackDucking}};
+//! # let mut audio_manager = AudioManager::new(Default::default())?;
+//! let dialog_track = audio_manager.add_sub_track(SubTrackSettings::new())?;
+//! let music_track = audio_manager.add_sub_track(
+//!     SubTrackSettings::new().ducking(
+//!         TrackDucking::new().add_rule(
+//!             DuckingSettings::new(&dialog_track)
+//!                 .volume(0.3)
+//!                 .attack(0.1)
+//!                 .release(1.0),
+//!         ),
+//!     ),
+//! )?;
+//! # Result::<(), Box<dyn std::error::Error>>::Ok(())
+//! ```
+//!
+//! Each rule has its own trigger track, and the track is ducked by
+//! every triggered rule at once. Rules can be added, changed, and
+//! removed later using the track's handle:
+//!
+//! ```no_run
+//! # use kira::{manager::AudioManager, mixer::{DuckingSettings, SubTrackSettings}};
+//! # let mut audio_manager = AudioManager::new(Default::default())?;
+//! # let dialog_track = audio_manager.add_sub_track(SubTrackSettings::new())?;
+//! # let mut music_track = audio_manager.add_sub_track(SubTrackSettings::new())?;
+//! let stinger_track = audio_manager.add_sub_track(SubTrackSettings::new())?;
+//! music_track.add_ducking(DuckingSettings::new(&stinger_track).volume(0.5))?;
+//! music_track.remove_ducking(&dialog_track)?;
+//! # Result::<(), Box<dyn std::error::Error>>::Ok(())
+//! ```
+//!
+//! ## Sidechains
+//!
+//! An effect can receive the output of another track as a key input
+//! by setting [`EffectSettings::sidechain`](effect::EffectSettings::sidechain).
+//! The built-in effects ignore this input, but custom effects
+//! (like a compressor that ducks music under dialog) can use it by
+//! implementing [`Effect::process_with_sidechain`](effect::Effect::process_with_sidechain).
+//! The mixer processes tracks in an order where each sidechain track
+//! is processed before the tracks that use it, so the key input is
+//! from the same frame of audio whenever possible.
+//!
+//! ## Changing routing at runtime
+//!
+//! A sub-track or send track can be moved to a different parent
+//! track, and its sends can be added, removed, or moved to other
+//! send tracks, using its [`SubTrackHandle`] or [`SendTrackHandle`].
+//! Changes that would make audio flow in a loop are rejected with
+//! [`ChangeRoutingError::WouldCreateCycle`], and tracks are always
+//! processed after every track that's routed into them.
+//!
+//! ```no_run
+//! # use kira::{manager::AudioManager, mixer::{SendTrackSettings, SubTrackSettings}};
+//! # let mut audio_manager = AudioManager::new(Default::default())?;
+//! let combat_bus = audio_manager.add_sub_track(SubTrackSettings::new())?;
+//! let reverb = audio_manager.add_send_track(SendTrackSettings::new())?;
+//! let mut drums = audio_manager.add_sub_track(SubTrackSettings::new())?;
+//! drums.set_parent_track(&combat_bus)?;
+//! drums.add_send(&reverb, 0.25)?;
+//! # Result::<(), Box<dyn std::error::Error>>::Ok(())
+//! ```
 
 pub mod effect;
 pub(crate) mod effect_slot;
 mod track;
 
 pub use track::{
-	handle::{MainTrackHandle, SendTrackHandle, SubTrackHandle},
+	ducking::{DuckingSettings, DuckingTrigger, TrackDucking},
+	handle::{
+		ChangeDuckingError, ChangeRoutingError, MainTrackHandle, SendTrackHandle, SubTrackHandle,
+	},
 	sends::TrackSends,
 	SendTrackId, SendTrackSettings, SubTrackId, SubTrackSettings, TrackIndex,
 };
-pub(crate) use track::{Track, TrackKind};
+pub(crate) use track::{handle::push_processing_order, routing, Track, TrackKind};
@@ -0,0 +1,198 @@
+use indexmap::IndexMap;
+
+use crate::static_container::index_map::StaticIndexMap;
+
+use super::TrackIndex;
+
+/// What makes a track duck.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(
+	feature = "serde_support",
+	derive(serde::Serialize, serde::Deserialize)
+)]
+pub enum DuckingTrigger {
+	/// Duck when the output level of the trigger track is
+	/// above a threshold (as a volume factor, not in decibels).
+	Signal {
+		/// The output level that causes ducking.
+		threshold: f64,
+	},
+	/// Duck whenever the trigger track or one of its child tracks
+	/// is receiving audio from instances or audio streams, even
+	/// if that audio is silent. Instances that are virtual because
+	/// they're too quiet to hear also count.
+	ActiveInstances,
+}
+
+impl Default for DuckingTrigger {
+	fn default() -> Self {
+		Self::Signal { threshold: 0.01 }
+	}
+}
+
+/// Settings for automatically lowering the volume of a track
+/// while another track is playing audio.
+///
+/// For example, a music track can be set to duck while
+/// the dialog track is playing so voice lines are easier to hear.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(
+	feature = "serde_support",
+	derive(serde::Serialize, serde::Deserialize)
+)]
+pub struct DuckingSettings {
+	/// The track that causes this track to duck.
+	pub trigger_track: TrackIndex,
+	/// What makes the track duck.
+	pub trigger: DuckingTrigger,
+	/// The volume the track is multiplied by when it's fully ducked.
+	pub volume: f64,
+	/// How long it takes for the track to fully duck (in seconds).
+	pub attack: f64,
+	/// How long it takes for the track to return to its normal
+	/// volume after the trigger stops (in seconds).
+	pub release: f64,
+}
+
+impl DuckingSettings {
+	/// Creates a new `DuckingSettings` that ducks when the
+	/// trigger track is playing audio.
+	pub fn new(trigger_track: impl Into<TrackIndex>) -> Self {
+		Self {
+			trigger_track: trigger_track.into(),
+			trigger: DuckingTrigger::default(),
+			volume: 0.25,
+			attack: 0.05,
+			release: 0.5,
+		}
+	}
+
+	/// Sets what makes the track duck.
+	pub fn trigger(self, trigger: DuckingTrigger) -> Self {
+		Self { trigger, ..self }
+	}
+
+	/// Sets the volume the track is multiplied by when it's fully ducked.
+	pub fn volume(self, volume: f64) -> Self {
+		Self { volume, ..self }
+	}
+
+	/// Sets how long it takes for the track to fully duck (in seconds).
+	pub fn attack(self, attack: f64) -> Self {
+		Self { attack, ..self }
+	}
+
+	/// Sets how long it takes for the track to return to its normal
+	/// volume after the trigger stops (in seconds).
+	pub fn release(self, release: f64) -> Self {
+		Self { release, ..self }
+	}
+}
+
+/// A set of rules for automatically lowering the volume of a
+/// track, each with a different trigger track.
+#[derive(Debug, Clone)]
+#[cfg_attr(
+	feature = "serde_support",
+	derive(serde::Serialize, serde::Deserialize),
+	serde(default)
+)]
+pub struct TrackDucking {
+	rules: IndexMap<TrackIndex, DuckingSettings>,
+}
+
+impl TrackDucking {
+	/// Creates a new, empty `TrackDucking` struct.
+	pub fn new() -> Self {
+		Self {
+			rules: IndexMap::new(),
+		}
+	}
+
+	/// Returns an iterator over the ducking rules.
+	pub fn iter(&self) -> indexmap::map::Values<'_, TrackIndex, DuckingSettings> {
+		self.rules.values()
+	}
+
+	/// Adds a ducking rule. If there's already a rule with
+	/// the same trigger track, it's replaced.
+	pub fn add_rule(mut self, settings: DuckingSettings) -> Self {
+		self.rules.insert(settings.trigger_track, settings);
+		self
+	}
+
+	/// Returns the number of ducking rules.
+	pub fn len(&self) -> usize {
+		self.rules.len()
+	}
+
+	/// Returns `true` if there are no ducking rules.
+	pub fn is_empty(&self) -> bool {
+		self.rules.is_empty()
+	}
+
+	/// Converts the rules to a map of duckers (by trigger track)
+	/// that can hold at least `capacity` rules without allocating.
+	pub(crate) fn to_map(&self, capacity: usize) -> StaticIndexMap<TrackIndex, Ducker> {
+		let mut map = StaticIndexMap::new(capacity.max(self.len()));
+		for settings in self.iter() {
+			map.try_insert(settings.trigger_track, Ducker::new(*settings))
+				.ok();
+		}
+		map
+	}
+}
+
+impl Default for TrackDucking {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+/// The state of a track's ducking on the audio thread.
+pub(crate) struct Ducker {
+	settings: DuckingSettings,
+	/// How far the track is ducked, from 0 (not ducked)
+	/// to 1 (fully ducked).
+	amount: f64,
+}
+
+impl Ducker {
+	pub fn new(settings: DuckingSettings) -> Self {
+		Self {
+			settings,
+			amount: 0.0,
+		}
+	}
+
+	/// Changes the ducking rule without resetting how far
+	/// the track is ducked.
+	pub fn set_settings(&mut self, settings: DuckingSettings) {
+		self.settings = settings;
+	}
+
+	pub fn trigger(&self) -> DuckingTrigger {
+		self.settings.trigger
+	}
+
+	/// The volume the track should be multiplied by.
+	pub fn volume(&self) -> f64 {
+		1.0 + (self.settings.volume - 1.0) * self.amount
+	}
+
+	pub fn update(&mut self, dt: f64, triggered: bool) {
+		if triggered {
+			self.amount = if self.settings.attack > 0.0 {
+				(self.amount + dt / self.settings.attack).min(1.0)
+			} else {
+				1.0
+			};
+		} else {
+			self.amount = if self.settings.release > 0.0 {
+				(self.amount - dt / self.settings.release).max(0.0)
+			} else {
+				0.0
+			};
+		}
+	}
+}
@@ -1,5 +1,7 @@
 //! An interface for controlling mixer tracks.
 
+use std::sync::{Arc, Mutex};
+
 use basedrop::Owned;
 use indexmap::IndexSet;
 use thiserror::Error;
@@ -14,6 +16,8 @@ use crate::{
 };
 
 use super::{
+	ducking::{DuckingSettings, TrackDucking},
+	routing::TrackRouting,
 	SendTrackId, SendTrackSettings, SubTrackId, SubTrackSettings, TrackIndex,
 	MAIN_TRACK_NUM_EFFECTS,
 };
@@ -30,6 +34,9 @@ pub enum AddEffectError {
 	/// No effect with the specified ID exists on this track.
 	#[error("No effect with the specified ID exists on this track")]
 	NoEffectWithId(EffectId),
+	/// The track to use as the effect's sidechain does not exist.
+	#[error("The track to use as the effect's sidechain does not exist")]
+	NonexistentSidechainTrack(TrackIndex),
 	/// A command could not be sent to the audio thread.
 	#[error("Could not send the command to the audio thread.")]
 	CommandProducerError(#[from] CommandError),
@@ -47,37 +54,234 @@ pub enum RemoveEffectError {
 	CommandProducerError(#[from] CommandError),
 }
 
-/// Allows you to control the main mixer track.
-pub struct MainTrackHandle {
+/// Something that can go wrong when using a [`SubTrackHandle`]
+/// or [`SendTrackHandle`] to change where a mixer track's
+/// output is routed.
+#[derive(Debug, Error)]
+pub enum ChangeRoutingError {
+	/// The track to route audio to does not exist.
+	#[error("The track to route audio to does not exist")]
+	NonexistentTrack(TrackIndex),
+	/// The track is not sending audio to the specified send track.
+	#[error("The track is not sending audio to the specified send track")]
+	NoSendToTrack(SendTrackId),
+	/// The change would make audio flow in a loop.
+	#[error("Cannot route the track's output because the audio would flow in a loop")]
+	WouldCreateCycle,
+	/// The maximum send limit for this track has been reached.
+	#[error("Cannot add a send because the max number of sends for this track has been reached")]
+	SendLimitReached,
+	/// A command could not be sent to the audio thread.
+	#[error("Could not send the command to the audio thread.")]
+	CommandProducerError(#[from] CommandError),
+}
+
+/// Something that can go wrong when using a [`SubTrackHandle`]
+/// or [`SendTrackHandle`] to change a mixer track's ducking rules.
+#[derive(Debug, Error)]
+pub enum ChangeDuckingError {
+	/// The track that would trigger the ducking does not exist.
+	#[error("The ducking trigger track does not exist")]
+	NonexistentTriggerTrack(TrackIndex),
+	/// The track has no ducking rule with the specified trigger track.
+	#[error("The track has no ducking rule with the specified trigger track")]
+	NoDuckingRule(TrackIndex),
+	/// The maximum ducking rule limit for this track has been reached.
+	#[error("Cannot add a ducking rule because the max number of ducking rules for this track has been reached")]
+	DuckingRuleLimitReached,
+	/// A command could not be sent to the audio thread.
+	#[error("Could not send the command to the audio thread.")]
+	CommandProducerError(#[from] CommandError),
+}
+
+/// Tells the mixer what order to process tracks in after
+/// the routing between them changes.
+pub(crate) fn push_processing_order(
+	command_producer: &mut CommandProducer,
+	routing: &TrackRouting,
+	resource_collector_handle: &basedrop::Handle,
+) -> Result<(), CommandError> {
+	command_producer.push(
+		MixerCommand::SetProcessingOrder(Owned::new(
+			resource_collector_handle,
+			routing.processing_order(),
+		))
+		.into(),
+	)
+}
+
+/// The parts of a track handle that are the same for every
+/// kind of mixer track.
+pub(crate) struct TrackController {
+	index: TrackIndex,
 	command_producer: CommandProducer,
+	routing: Arc<Mutex<TrackRouting>>,
 	active_effect_ids: IndexSet<EffectId>,
+	ducking_trigger_tracks: IndexSet<TrackIndex>,
+	max_ducking_rules: usize,
 	sample_rate: u32,
 	resource_collector_handle: basedrop::Handle,
 }
 
-impl MainTrackHandle {
-	pub(crate) fn new(
+impl TrackController {
+	pub fn new(
+		index: TrackIndex,
+		num_effects: usize,
 		command_producer: CommandProducer,
+		routing: Arc<Mutex<TrackRouting>>,
 		sample_rate: u32,
 		resource_collector_handle: basedrop::Handle,
 	) -> Self {
 		Self {
+			index,
 			command_producer,
-			active_effect_ids: IndexSet::with_capacity(MAIN_TRACK_NUM_EFFECTS),
+			routing,
+			active_effect_ids: IndexSet::with_capacity(num_effects),
+			ducking_trigger_tracks: IndexSet::new(),
+			max_ducking_rules: 0,
 			sample_rate,
 			resource_collector_handle,
 		}
 	}
 
-	/// Sets the volume of the main track.
-	///
-	/// This acts as a "master volume" control for all sounds.
-	pub fn set_volume(&mut self, volume: impl Into<Value<f64>>) -> Result<(), CommandError> {
+	/// Sets the ducking rules the track was created with and
+	/// how many it can have at once.
+	pub fn with_ducking(self, ducking: &TrackDucking, num_ducking_rules: usize) -> Self {
+		Self {
+			ducking_trigger_tracks: ducking
+				.iter()
+				.map(|settings| settings.trigger_track)
+				.collect(),
+			max_ducking_rules: num_ducking_rules.max(ducking.len()),
+			..self
+		}
+	}
+
+	pub fn set_volume(&mut self, volume: Value<f64>) -> Result<(), CommandError> {
 		self.command_producer
-			.push(MixerCommand::SetTrackVolume(TrackIndex::Main, volume.into()).into())
+			.push(MixerCommand::SetTrackVolume(self.index, volume).into())
+	}
+
+	pub fn set_parent_track(&mut self, parent_track: TrackIndex) -> Result<(), ChangeRoutingError> {
+		let mut routing = TrackRouting::lock(&self.routing);
+		if !routing.contains(parent_track) {
+			return Err(ChangeRoutingError::NonexistentTrack(parent_track));
+		}
+		if routing.would_create_cycle(self.index, parent_track) {
+			return Err(ChangeRoutingError::WouldCreateCycle);
+		}
+		self.command_producer
+			.push(MixerCommand::SetTrackParent(self.index, parent_track).into())?;
+		routing.set_parent_track(self.index, parent_track);
+		push_processing_order(
+			&mut self.command_producer,
+			&routing,
+			&self.resource_collector_handle,
+		)?;
+		Ok(())
+	}
+
+	pub fn add_send(
+		&mut self,
+		send_track: SendTrackId,
+		volume: Value<f64>,
+	) -> Result<(), ChangeRoutingError> {
+		let mut routing = TrackRouting::lock(&self.routing);
+		let new_send = !routing.has_send(self.index, send_track);
+		if new_send {
+			if !routing.contains(send_track.into()) {
+				return Err(ChangeRoutingError::NonexistentTrack(send_track.into()));
+			}
+			if routing.send_limit_reached(self.index) {
+				return Err(ChangeRoutingError::SendLimitReached);
+			}
+			if routing.would_create_cycle(self.index, send_track.into()) {
+				return Err(ChangeRoutingError::WouldCreateCycle);
+			}
+		}
+		self.command_producer
+			.push(MixerCommand::SetTrackSend(self.index, send_track, volume).into())?;
+		if new_send {
+			routing.add_send(self.index, send_track);
+			push_processing_order(
+				&mut self.command_producer,
+				&routing,
+				&self.resource_collector_handle,
+			)?;
+		}
+		Ok(())
+	}
+
+	pub fn remove_send(&mut self, send_track: SendTrackId) -> Result<(), ChangeRoutingError> {
+		let mut routing = TrackRouting::lock(&self.routing);
+		if !routing.has_send(self.index, send_track) {
+			return Err(ChangeRoutingError::NoSendToTrack(send_track));
+		}
+		self.command_producer
+			.push(MixerCommand::RemoveTrackSend(self.index, send_track).into())?;
+		routing.remove_send(self.index, send_track);
+		push_processing_order(
+			&mut self.command_producer,
+			&routing,
+			&self.resource_collector_handle,
+		)?;
+		Ok(())
+	}
+
+	pub fn retarget_send(
+		&mut self,
+		from: SendTrackId,
+		to: SendTrackId,
+	) -> Result<(), ChangeRoutingError> {
+		let mut routing = TrackRouting::lock(&self.routing);
+		if !routing.has_send(self.index, from) {
+			return Err(ChangeRoutingError::NoSendToTrack(from));
+		}
+		if !routing.contains(to.into()) {
+			return Err(ChangeRoutingError::NonexistentTrack(to.into()));
+		}
+		if routing.would_create_cycle(self.index, to.into()) {
+			return Err(ChangeRoutingError::WouldCreateCycle);
+		}
+		self.command_producer
+			.push(MixerCommand::RetargetTrackSend(self.index, from, to).into())?;
+		routing.remove_send(self.index, from);
+		routing.add_send(self.index, to);
+		push_processing_order(
+			&mut self.command_producer,
+			&routing,
+			&self.resource_collector_handle,
+		)?;
+		Ok(())
+	}
+
+	pub fn add_ducking(&mut self, settings: DuckingSettings) -> Result<(), ChangeDuckingError> {
+		let trigger_track = settings.trigger_track;
+		let new_rule = !self.ducking_trigger_tracks.contains(&trigger_track);
+		if new_rule {
+			if !TrackRouting::lock(&self.routing).contains(trigger_track) {
+				return Err(ChangeDuckingError::NonexistentTriggerTrack(trigger_track));
+			}
+			if self.ducking_trigger_tracks.len() >= self.max_ducking_rules {
+				return Err(ChangeDuckingError::DuckingRuleLimitReached);
+			}
+		}
+		self.command_producer
+			.push(MixerCommand::SetTrackDucking(self.index, settings).into())?;
+		self.ducking_trigger_tracks.insert(trigger_track);
+		Ok(())
+	}
+
+	pub fn remove_ducking(&mut self, trigger_track: TrackIndex) -> Result<(), ChangeDuckingError> {
+		if !self.ducking_trigger_tracks.contains(&trigger_track) {
+			return Err(ChangeDuckingError::NoDuckingRule(trigger_track));
+		}
+		self.command_producer
+			.push(MixerCommand::RemoveTrackDucking(self.index, trigger_track).into())?;
+		self.ducking_trigger_tracks.shift_remove(&trigger_track);
+		Ok(())
 	}
 
-	/// Adds an effect to the track.
 	pub fn add_effect(
 		&mut self,
 		mut effect: impl Effect + 'static,
@@ -86,17 +290,24 @@ impl MainTrackHandle {
 		if self.active_effect_ids.len() >= self.active_effect_ids.capacity() {
 			return Err(AddEffectError::EffectLimitReached);
 		}
+		let mut routing = TrackRouting::lock(&self.routing);
+		if let Some(sidechain) = settings.sidechain {
+			if !routing.contains(sidechain) {
+				return Err(AddEffectError::NonexistentSidechainTrack(sidechain));
+			}
+		}
 		let effect_id = settings.id.unwrap_or(EffectId::new());
 		let handle = EffectHandle::new(
 			effect_id,
-			TrackIndex::Main,
+			self.index,
 			&settings,
 			self.command_producer.clone(),
 		);
 		effect.init(self.sample_rate);
+		let sidechain = settings.sidechain;
 		self.command_producer.push(
 			MixerCommand::AddEffect(
-				TrackIndex::Main,
+				self.index,
 				effect_id,
 				Owned::new(&self.resource_collector_handle, Box::new(effect)),
 				settings,
@@ -104,28 +315,85 @@ impl MainTrackHandle {
 			.into(),
 		)?;
 		self.active_effect_ids.insert(effect_id);
+		if let Some(sidechain) = sidechain {
+			routing.add_sidechain(self.index, effect_id, sidechain);
+			push_processing_order(
+				&mut self.command_producer,
+				&routing,
+				&self.resource_collector_handle,
+			)?;
+		}
 		Ok(handle)
 	}
 
-	/// Removes an effect from the track.
-	pub fn remove_effect(&mut self, id: impl Into<EffectId>) -> Result<(), RemoveEffectError> {
-		let id = id.into();
+	pub fn remove_effect(&mut self, id: EffectId) -> Result<(), RemoveEffectError> {
 		if !self.active_effect_ids.remove(&id) {
 			return Err(RemoveEffectError::NoEffectWithId(id));
 		}
+		let mut routing = TrackRouting::lock(&self.routing);
 		self.command_producer
-			.push(MixerCommand::RemoveEffect(TrackIndex::Main, id).into())?;
+			.push(MixerCommand::RemoveEffect(self.index, id).into())?;
+		if routing.remove_sidechain(self.index, id) {
+			push_processing_order(
+				&mut self.command_producer,
+				&routing,
+				&self.resource_collector_handle,
+			)?;
+		}
 		Ok(())
 	}
 }
 
+/// Allows you to control the main mixer track.
+pub struct MainTrackHandle {
+	controller: TrackController,
+}
+
+impl MainTrackHandle {
+	pub(crate) fn new(
+		command_producer: CommandProducer,
+		routing: Arc<Mutex<TrackRouting>>,
+		sample_rate: u32,
+		resource_collector_handle: basedrop::Handle,
+	) -> Self {
+		Self {
+			controller: TrackController::new(
+				TrackIndex::Main,
+				MAIN_TRACK_NUM_EFFECTS,
+				command_producer,
+				routing,
+				sample_rate,
+				resource_collector_handle,
+			),
+		}
+	}
+
+	/// Sets the volume of the main track.
+	///
+	/// This acts as a "master volume" control for all sounds.
+	pub fn set_volume(&mut self, volume: impl Into<Value<f64>>) -> Result<(), CommandError> {
+		self.controller.set_volume(volume.into())
+	}
+
+	/// Adds an effect to the track.
+	pub fn add_effect(
+		&mut self,
+		effect: impl Effect + 'static,
+		settings: EffectSettings,
+	) -> Result<EffectHandle, AddEffectError> {
+		self.controller.add_effect(effect, settings)
+	}
+
+	/// Removes an effect from the track.
+	pub fn remove_effect(&mut self, id: impl Into<EffectId>) -> Result<(), RemoveEffectError> {
+		self.controller.remove_effect(id.into())
+	}
+}
+
 /// Allows you to control a mixer sub-track.
 pub struct SubTrackHandle {
 	id: SubTrackId,
-	command_producer: CommandProducer,
-	active_effect_ids: IndexSet<EffectId>,
-	sample_rate: u32,
-	resource_collector_handle: basedrop::Handle,
+	controller: TrackController,
 }
 
 impl SubTrackHandle {
@@ -133,15 +401,21 @@ impl SubTrackHandle {
 		id: SubTrackId,
 		settings: &SubTrackSettings,
 		command_producer: CommandProducer,
+		routing: Arc<Mutex<TrackRouting>>,
 		sample_rate: u32,
 		resource_collector_handle: basedrop::Handle,
 	) -> Self {
 		Self {
 			id,
-			command_producer,
-			active_effect_ids: IndexSet::with_capacity(settings.num_effects),
-			sample_rate,
-			resource_collector_handle,
+			controller: TrackController::new(
+				id.into(),
+				settings.num_effects,
+				command_producer,
+				routing,
+				sample_rate,
+				resource_collector_handle,
+			)
+			.with_ducking(&settings.ducking, settings.num_ducking_rules),
 		}
 	}
 
@@ -152,59 +426,86 @@ impl SubTrackHandle {
 
 	/// Sets the volume of the track.
 	pub fn set_volume(&mut self, volume: impl Into<Value<f64>>) -> Result<(), CommandError> {
-		self.command_producer
-			.push(MixerCommand::SetTrackVolume(self.id.into(), volume.into()).into())
+		self.controller.set_volume(volume.into())
+	}
+
+	/// Changes the track that this track's output is routed to.
+	pub fn set_parent_track(
+		&mut self,
+		parent_track: impl Into<TrackIndex>,
+	) -> Result<(), ChangeRoutingError> {
+		self.controller.set_parent_track(parent_track.into())
+	}
+
+	/// Routes this track's output to a send track.
+	///
+	/// If the track is already routed to the send track,
+	/// this changes the volume of the send instead.
+	pub fn add_send(
+		&mut self,
+		send_track: impl Into<SendTrackId>,
+		volume: impl Into<Value<f64>>,
+	) -> Result<(), ChangeRoutingError> {
+		self.controller.add_send(send_track.into(), volume.into())
+	}
+
+	/// Stops routing this track's output to a send track.
+	pub fn remove_send(
+		&mut self,
+		send_track: impl Into<SendTrackId>,
+	) -> Result<(), ChangeRoutingError> {
+		self.controller.remove_send(send_track.into())
+	}
+
+	/// Moves a send from one send track to another, keeping
+	/// the volume of the send.
+	///
+	/// If the track is already routed to the new send track,
+	/// that send is replaced.
+	pub fn retarget_send(
+		&mut self,
+		from: impl Into<SendTrackId>,
+		to: impl Into<SendTrackId>,
+	) -> Result<(), ChangeRoutingError> {
+		self.controller.retarget_send(from.into(), to.into())
+	}
+
+	/// Adds a rule for automatically lowering the volume of
+	/// the track while another track is playing audio.
+	///
+	/// If the track already has a ducking rule with the same
+	/// trigger track, this changes that rule instead.
+	pub fn add_ducking(&mut self, settings: DuckingSettings) -> Result<(), ChangeDuckingError> {
+		self.controller.add_ducking(settings)
+	}
+
+	/// Removes the ducking rule with the given trigger track.
+	pub fn remove_ducking(
+		&mut self,
+		trigger_track: impl Into<TrackIndex>,
+	) -> Result<(), ChangeDuckingError> {
+		self.controller.remove_ducking(trigger_track.into())
 	}
 
 	/// Adds an effect to the track.
 	pub fn add_effect(
 		&mut self,
-		mut effect: impl Effect + 'static,
+		effect: impl Effect + 'static,
 		settings: EffectSettings,
 	) -> Result<EffectHandle, AddEffectError> {
-		if self.active_effect_ids.len() >= self.active_effect_ids.capacity() {
-			return Err(AddEffectError::EffectLimitReached);
-		}
-		let effect_id = settings.id.unwrap_or(EffectId::new());
-		let handle = EffectHandle::new(
-			effect_id,
-			self.id.into(),
-			&settings,
-			self.command_producer.clone(),
-		);
-		effect.init(self.sample_rate);
-		self.command_producer.push(
-			MixerCommand::AddEffect(
-				self.id.into(),
-				effect_id,
-				Owned::new(&self.resource_collector_handle, Box::new(effect)),
-				settings,
-			)
-			.into(),
-		)?;
-		self.active_effect_ids.insert(effect_id);
-		Ok(handle)
+		self.controller.add_effect(effect, settings)
 	}
 
 	/// Removes an effect from the track.
 	pub fn remove_effect(&mut self, id: impl Into<EffectId>) -> Result<(), RemoveEffectError> {
-		let id = id.into();
-		if !self.active_effect_ids.remove(&id) {
-			return Err(RemoveEffectError::NoEffectWithId(id));
-		}
-		self.command_producer
-			.push(MixerCommand::RemoveEffect(self.id.into(), id).into())?;
-		Ok(())
+		self.controller.remove_effect(id.into())
 	}
 }
 
 /// Allows you to control a mixer send track.
 pub struct SendTrackHandle {
 	id: SendTrackId,
-	command_producer: CommandProducer,
-	active_effect_ids: IndexSet<EffectId>,
-	sample_rate: u32,
-	resource_collector_handle: basedrop::Handle,
+	controller: TrackController,
 }
 
 impl SendTrackHandle {
@@ -212,15 +513,21 @@ impl SendTrackHandle {
 		id: SendTrackId,
 		settings: &SendTrackSettings,
 		command_producer: CommandProducer,
+		routing: Arc<Mutex<TrackRouting>>,
 		sample_rate: u32,
 		resource_collector_handle: basedrop::Handle,
 	) -> Self {
 		Self {
 			id,
-			command_producer,
-			active_effect_ids: IndexSet::with_capacity(settings.num_effects),
-			sample_rate,
-			resource_collector_handle,
+			controller: TrackController::new(
+				id.into(),
+				settings.num_effects,
+				command_producer,
+				routing,
+				sample_rate,
+				resource_collector_handle,
+			)
+			.with_ducking(&settings.ducking, settings.num_ducking_rules),
 		}
 	}
 
@@ -231,48 +538,78 @@ impl SendTrackHandle {
 
 	/// Sets the volume of the track.
 	pub fn set_volume(&mut self, volume: impl Into<Value<f64>>) -> Result<(), CommandError> {
-		self.command_producer
-			.push(MixerCommand::SetTrackVolume(self.id.into(), volume.into()).into())
+		self.controller.set_volume(volume.into())
+	}
+
+	/// Changes the track that this track's output is routed to.
+	pub fn set_parent_track(
+		&mut self,
+		parent_track: impl Into<TrackIndex>,
+	) -> Result<(), ChangeRoutingError> {
+		self.controller.set_parent_track(parent_track.into())
+	}
+
+	/// Routes this track's output to a send track.
+	///
+	/// If the track is already routed to the send track,
+	/// this changes the volume of the send instead.
+	pub fn add_send(
+		&mut self,
+		send_track: impl Into<SendTrackId>,
+		volume: impl Into<Value<f64>>,
+	) -> Result<(), ChangeRoutingError> {
+		self.controller.add_send(send_track.into(), volume.into())
+	}
+
+	/// Stops routing this track's output to a send track.
+	pub fn remove_send(
+		&mut self,
+		send_track: impl Into<SendTrackId>,
+	) -> Result<(), ChangeRoutingError> {
+		self.controller.remove_send(send_track.into())
+	}
+
+	/// Moves a send from one send track to another, keeping
+	/// the volume of the send.
+	///
+	/// If the track is already routed to the new send track,
+	/// that send is replaced.
+	pub fn retarget_send(
+		&mut self,
+		from: impl Into<SendTrackId>,
+		to: impl Into<SendTrackId>,
+	) -> Result<(), ChangeRoutingError> {
+		self.controller.retarget_send(from.into(), to.into())
+	}
+
+	/// Adds a rule for automatically lowering the volume of
+	/// the track while another track is playing audio.
+	///
+	/// If the track already has a ducking rule with the same
+	/// trigger track, this changes that rule instead.
+	pub fn add_ducking(&mut self, settings: DuckingSettings) -> Result<(), ChangeDuckingError> {
+		self.controller.add_ducking(settings)
+	}
+
+	/// Removes the ducking rule with the given trigger track.
+	pub fn remove_ducking(
+		&mut self,
+		trigger_track: impl Into<TrackIndex>,
+	) -> Result<(), ChangeDuckingError> {
+		self.controller.remove_ducking(trigger_track.into())
 	}
 
 	/// Adds an effect to the track.
 	pub fn add_effect(
 		&mut self,
-		mut effect: impl Effect + 'static,
+		effect: impl Effect + 'static,
 		settings: EffectSettings,
 	) -> Result<EffectHandle, AddEffectError> {
-		if self.active_effect_ids.len() >= self.active_effect_ids.capacity() {
-			return Err(AddEffectError::EffectLimitReached);
-		}
-		let effect_id = settings.id.unwrap_or(EffectId::new());
-		let handle = EffectHandle::new(
-			effect_id,
-			self.id.into(),
-			&settings,
-			self.command_producer.clone(),
-		);
-		effect.init(self.sample_rate);
-		self.command_producer.push(
-			MixerCommand::AddEffect(
-				self.id.into(),
-				effect_id,
-				Owned::new(&self.resource_collector_handle, Box::new(effect)),
-				settings,
-			)
-			.into(),
-		)?;
-		self.active_effect_ids.insert(effect_id);
-		Ok(handle)
+		self.controller.add_effect(effect, settings)
 	}
 
 	/// Removes an effect from the track.
 	pub fn remove_effect(&mut self, id: impl Into<EffectId>) -> Result<(), RemoveEffectError> {
-		let id = id.into();
-		if !self.active_effect_ids.remove(&id) {
-			return Err(RemoveEffectError::NoEffectWithId(id));
-		}
-		self.command_producer
-			.push(MixerCommand::RemoveEffect(self.id.into(), id).into())?;
-		Ok(())
+		self.controller.remove_effect(id.into())
 	}
 }
@@ -1,11 +1,13 @@
+pub mod ducking;
 pub mod handle;
+pub(crate) mod routing;
 pub mod sends;
 pub mod settings;
 
-use indexmap::IndexMap;
 pub use settings::*;
 
 use basedrop::Owned;
+use ducking::{Ducker, DuckingSettings, DuckingTrigger};
 use handle::{SendTrackHandle, SubTrackHandle};
 use uuid::Uuid;
 
@@ -21,6 +23,13 @@ use super::{
 
 pub(crate) const MAIN_TRACK_NUM_EFFECTS: usize = 8;
 
+/// How long it takes for a track's measured output level
+/// to fall most of the way (about 63%) to a lower level (in seconds).
+///
+/// This keeps the level from dropping every time the waveform
+/// crosses zero.
+const LEVEL_RELEASE: f64 = 0.1;
+
 /// A unique identifier for a sub-track.
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 #[cfg_attr(
@@ -95,6 +104,8 @@ pub enum TrackIndex {
 	/// Send tracks are useful for routing multiple sub-tracks
 	/// into the same set of effects. This can save processing
 	/// power and avoid redundant effect configuration.
+	/// Like sub-tracks, send tracks can be routed to a parent
+	/// track and other send tracks.
 	Send(SendTrackId),
 }
 
@@ -133,10 +144,12 @@ pub(crate) enum TrackKind {
 	Sub {
 		id: SubTrackId,
 		parent_track: TrackIndex,
-		sends: IndexMap<SendTrackId, CachedValue<f64>>,
+		sends: StaticIndexMap<SendTrackId, CachedValue<f64>>,
 	},
 	Send {
 		id: SendTrackId,
+		parent_track: TrackIndex,
+		sends: StaticIndexMap<SendTrackId, CachedValue<f64>>,
 	},
 }
 
@@ -144,7 +157,13 @@ pub(crate) struct Track {
 	kind: TrackKind,
 	volume: CachedValue<f64>,
 	effect_slots: StaticIndexMap<EffectId, EffectSlot>,
+	/// The track's ducking rules, by trigger track.
+	duckers: StaticIndexMap<TrackIndex, Ducker>,
 	input: Frame,
+	output: Frame,
+	received_input: bool,
+	active: bool,
+	level: f64,
 }
 
 impl Track {
@@ -153,7 +172,12 @@ impl Track {
 			kind: TrackKind::Main,
 			volume: CachedValue::new(Value::Fixed(1.0), 1.0),
 			effect_slots: StaticIndexMap::new(MAIN_TRACK_NUM_EFFECTS),
+			duckers: StaticIndexMap::new(0),
 			input: Frame::from_mono(0.0),
+			output: Frame::from_mono(0.0),
+			received_input: false,
+			active: false,
+			level: 0.0,
 		}
 	}
 
@@ -162,20 +186,34 @@ impl Track {
 			kind: TrackKind::Sub {
 				id,
 				parent_track: settings.parent_track,
-				sends: settings.sends.to_map(),
+				sends: settings.sends.to_map(settings.num_sends),
 			},
 			volume: CachedValue::new(settings.volume, 1.0),
 			effect_slots: StaticIndexMap::new(settings.num_effects),
+			duckers: settings.ducking.to_map(settings.num_ducking_rules),
 			input: Frame::from_mono(0.0),
+			output: Frame::from_mono(0.0),
+			received_input: false,
+			active: false,
+			level: 0.0,
 		}
 	}
 
 	pub fn new_send_track(id: SendTrackId, settings: SendTrackSettings) -> Self {
 		Self {
-			kind: TrackKind::Send { id },
+			kind: TrackKind::Send {
+				id,
+				parent_track: settings.parent_track,
+				sends: settings.sends.to_map(settings.num_sends),
+			},
 			volume: CachedValue::new(settings.volume, 1.0),
 			effect_slots: StaticIndexMap::new(settings.num_effects),
+			duckers: settings.ducking.to_map(settings.num_ducking_rules),
 			input: Frame::from_mono(0.0),
+			output: Frame::from_mono(0.0),
+			received_input: false,
+			active: false,
+			level: 0.0,
 		}
 	}
 
@@ -183,7 +221,23 @@ impl Track {
 		match &self.kind {
 			TrackKind::Main => None,
 			TrackKind::Sub { parent_track, .. } => Some(*parent_track),
-			TrackKind::Send { .. } => Some(TrackIndex::Main),
+			TrackKind::Send { parent_track, .. } => Some(*parent_track),
+		}
+	}
+
+	fn sends(&self) -> Option<&StaticIndexMap<SendTrackId, CachedValue<f64>>> {
+		match &self.kind {
+			TrackKind::Main => None,
+			TrackKind::Sub { sends, .. } => Some(sends),
+			TrackKind::Send { sends, .. } => Some(sends),
+		}
+	}
+
+	fn sends_mut(&mut self) -> Option<&mut StaticIndexMap<SendTrackId, CachedValue<f64>>> {
+		match &mut self.kind {
+			TrackKind::Main => None,
+			TrackKind::Sub { sends, .. } => Some(sends),
+			TrackKind::Send { sends, .. } => Some(sends),
 		}
 	}
 
@@ -191,10 +245,91 @@ impl Track {
 		&self.kind
 	}
 
+	/// Returns the number of send tracks this track is routed to.
+	pub fn num_sends(&self) -> usize {
+		self.sends().map_or(0, |sends| sends.len())
+	}
+
+	/// Returns the send track and send volume at an index
+	/// in this track's list of sends.
+	pub fn send(&self, index: usize) -> Option<(SendTrackId, f64)> {
+		self.sends()
+			.and_then(|sends| sends.get_index(index))
+			.map(|(id, volume)| (*id, volume.value()))
+	}
+
+	/// Returns the number of effects on this track.
+	pub fn num_effects(&self) -> usize {
+		self.effect_slots.len()
+	}
+
+	/// Returns the track that the effect at an index in this
+	/// track's effect chain receives a sidechain input from, if any.
+	pub fn effect_sidechain(&self, index: usize) -> Option<TrackIndex> {
+		self.effect_slots
+			.get_index(index)
+			.and_then(|(_, effect_slot)| effect_slot.sidechain())
+	}
+
+	/// Sets the sidechain input of the effect at an index
+	/// in this track's effect chain.
+	pub fn set_effect_sidechain_input(&mut self, index: usize, input: Frame) {
+		if let Some((_, effect_slot)) = self.effect_slots.get_index_mut(index) {
+			effect_slot.set_sidechain_input(input);
+		}
+	}
+
+	/// Returns the output of the track from the last time
+	/// it was processed.
+	pub fn output(&self) -> Frame {
+		self.output
+	}
+
 	pub fn set_volume(&mut self, volume: Value<f64>) {
 		self.volume.set(volume);
 	}
 
+	/// Changes the track that this track's output is routed to.
+	pub fn set_parent_track(&mut self, index: TrackIndex) {
+		match &mut self.kind {
+			TrackKind::Main => {}
+			TrackKind::Sub { parent_track, .. } => *parent_track = index,
+			TrackKind::Send { parent_track, .. } => *parent_track = index,
+		}
+	}
+
+	/// Routes this track's output to a send track, or changes
+	/// the send volume if it's already routed to that send track.
+	pub fn set_send(&mut self, send_track: SendTrackId, volume: Value<f64>) {
+		if let Some(sends) = self.sends_mut() {
+			match sends.get_mut(&send_track) {
+				Some(send_volume) => send_volume.set(volume),
+				None => {
+					sends
+						.try_insert(send_track, CachedValue::new(volume, 1.0))
+						.ok();
+				}
+			}
+		}
+	}
+
+	/// Stops routing this track's output to a send track.
+	pub fn remove_send(&mut self, send_track: SendTrackId) {
+		if let Some(sends) = self.sends_mut() {
+			sends.shift_remove(&send_track);
+		}
+	}
+
+	/// Moves a send to a different send track, keeping
+	/// its volume.
+	pub fn retarget_send(&mut self, from: SendTrackId, to: SendTrackId) {
+		if let Some(sends) = self.sends_mut() {
+			if let Some(volume) = sends.shift_remove(&from) {
+				sends.try_insert(to, volume).ok();
+			}
+		}
+	}
+
 	pub fn add_effect(
 		&mut self,
 		id: EffectId,
@@ -217,9 +352,69 @@ impl Track {
 		self.input += input;
 	}
 
+	/// Marks the track as receiving audio from instances or
+	/// audio streams (directly or through a child track) this frame.
+	pub fn mark_active(&mut self) {
+		self.received_input = true;
+	}
+
+	/// Returns true if the track received audio from instances
+	/// or audio streams the last time it was processed.
+	pub fn active(&self) -> bool {
+		self.active
+	}
+
+	/// Returns true if the track's output would make a track
+	/// that ducks in response to it duck.
+	pub fn triggers_ducking(&self, trigger: DuckingTrigger) -> bool {
+		match trigger {
+			DuckingTrigger::Signal { threshold } => self.level > threshold,
+			DuckingTrigger::ActiveInstances => self.active,
+		}
+	}
+
+	/// Returns the number of ducking rules this track has.
+	pub fn num_ducking_rules(&self) -> usize {
+		self.duckers.len()
+	}
+
+	/// Returns the trigger track and trigger of the ducking rule
+	/// at an index in this track's list of ducking rules.
+	pub fn ducking_rule(&self, index: usize) -> Option<(TrackIndex, DuckingTrigger)> {
+		self.duckers
+			.get_index(index)
+			.map(|(trigger_track, ducker)| (*trigger_track, ducker.trigger()))
+	}
+
+	/// Adds a ducking rule, or changes the settings of the rule
+	/// with the same trigger track if there is one.
+	pub fn set_ducking(&mut self, settings: DuckingSettings) {
+		match self.duckers.get_mut(&settings.trigger_track) {
+			Some(ducker) => ducker.set_settings(settings),
+			None => {
+				self.duckers
+					.try_insert(settings.trigger_track, Ducker::new(settings))
+					.ok();
+			}
+		}
+	}
+
+	/// Removes the ducking rule with the given trigger track.
+	pub fn remove_ducking(&mut self, trigger_track: TrackIndex) {
+		self.duckers.shift_remove(&trigger_track);
+	}
+
+	/// Moves the ducking rule at an index towards or away
+	/// from its ducked volume.
+	pub fn update_ducking(&mut self, index: usize, dt: f64, triggered: bool) {
+		if let Some((_, ducker)) = self.duckers.get_index_mut(index) {
+			ducker.update(dt, triggered);
+		}
+	}
+
 	pub fn process(&mut self, dt: f64, parameters: &Parameters) -> Frame {
 		self.volume.update(parameters);
-		if let TrackKind::Sub { sends, .. } = &mut self.kind {
+		if let Some(sends) = self.sends_mut() {
 			for (_, volume) in sends {
 				volume.update(parameters);
 			}
@@ -229,6 +424,16 @@ impl Track {
 		for (_, effect_slot) in &mut self.effect_slots {
 			input = effect_slot.process(dt, input, parameters);
 		}
-		input * (self.volume.value() as f32)
+		let ducking_volume = self
+			.duckers
+			.iter()
+			.map(|(_, ducker)| ducker.volume())
+			.product::<f64>();
+		let output = input * ((self.volume.value() * ducking_volume) as f32);
+		self.active = std::mem::take(&mut self.received_input);
+		let peak = output.left.abs().max(output.right.abs()) as f64;
+		self.level = peak.max(self.level * (-dt / LEVEL_RELEASE).exp());
+		self.output = output;
+		output
 	}
 }
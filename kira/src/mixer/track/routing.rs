@@ -0,0 +1,240 @@
+use std::{
+	collections::VecDeque,
+	sync::{Mutex, MutexGuard, PoisonError},
+};
+
+use indexmap::{IndexMap, IndexSet};
+
+use crate::mixer::effect::EffectId;
+
+use super::{SendTrackId, TrackIndex};
+
+/// Where a track's output goes.
+#[derive(Debug, Clone)]
+struct TrackRoutes {
+	parent_track: Option<TrackIndex>,
+	sends: IndexSet<SendTrackId>,
+	max_sends: usize,
+	/// The tracks this track's effects receive sidechain inputs from.
+	sidechains: IndexMap<EffectId, TrackIndex>,
+}
+
+/// Keeps track of how mixer tracks are routed to each other
+/// on the main thread so routing changes that would create
+/// a cycle can be rejected before they reach the audio thread,
+/// and so the order the audio thread processes tracks in
+/// can be worked out without allocating.
+#[derive(Debug, Clone)]
+pub(crate) struct TrackRouting {
+	tracks: IndexMap<TrackIndex, TrackRoutes>,
+	/// The tracks the main track's effects receive sidechain inputs from.
+	main_sidechains: IndexMap<EffectId, TrackIndex>,
+}
+
+impl TrackRouting {
+	pub fn new() -> Self {
+		Self {
+			tracks: IndexMap::new(),
+			main_sidechains: IndexMap::new(),
+		}
+	}
+
+	pub fn add_track(
+		&mut self,
+		index: TrackIndex,
+		parent_track: Option<TrackIndex>,
+		sends: impl IntoIterator<Item = SendTrackId>,
+		max_sends: usize,
+	) {
+		self.tracks.insert(
+			index,
+			TrackRoutes {
+				parent_track,
+				sends: sends.into_iter().collect(),
+				max_sends,
+				sidechains: IndexMap::new(),
+			},
+		);
+	}
+
+	/// Removes a track. If it's a send track, other tracks
+	/// stop sending audio to it.
+	pub fn remove_track(&mut self, index: TrackIndex) {
+		self.tracks.shift_remove(&index);
+		if let TrackIndex::Send(id) = index {
+			for routes in self.tracks.values_mut() {
+				routes.sends.shift_remove(&id);
+			}
+		}
+	}
+
+	/// Locks shared routing information. The routing only holds
+	/// plain data, so it's still usable if a thread panicked
+	/// while holding the lock.
+	pub fn lock(routing: &Mutex<Self>) -> MutexGuard<'_, Self> {
+		routing.lock().unwrap_or_else(PoisonError::into_inner)
+	}
+
+	/// Returns true if the track exists.
+	pub fn contains(&self, index: TrackIndex) -> bool {
+		index == TrackIndex::Main || self.tracks.contains_key(&index)
+	}
+
+	/// Returns true if audio from one track reaches another
+	/// track, directly or through other tracks.
+	pub fn reaches(&self, from: TrackIndex, to: TrackIndex) -> bool {
+		let mut stack = vec![from];
+		let mut visited = IndexSet::new();
+		while let Some(index) = stack.pop() {
+			if index == to {
+				return true;
+			}
+			if !visited.insert(index) {
+				continue;
+			}
+			if let Some(routes) = self.tracks.get(&index) {
+				stack.extend(routes.parent_track);
+				stack.extend(routes.sends.iter().map(|id| TrackIndex::Send(*id)));
+			}
+		}
+		false
+	}
+
+	/// Returns true if routing a track's output to another
+	/// track would make audio flow in a loop.
+	pub fn would_create_cycle(&self, from: TrackIndex, to: TrackIndex) -> bool {
+		self.reaches(to, from)
+	}
+
+	pub fn set_parent_track(&mut self, index: TrackIndex, parent_track: TrackIndex) {
+		if let Some(routes) = self.tracks.get_mut(&index) {
+			routes.parent_track = Some(parent_track);
+		}
+	}
+
+	/// Returns true if the track is sending audio to a send track.
+	pub fn has_send(&self, index: TrackIndex, send_track: SendTrackId) -> bool {
+		match self.tracks.get(&index) {
+			Some(routes) => routes.sends.contains(&send_track),
+			None => false,
+		}
+	}
+
+	/// Returns true if the track can't send audio to any
+	/// more send tracks.
+	pub fn send_limit_reached(&self, index: TrackIndex) -> bool {
+		match self.tracks.get(&index) {
+			Some(routes) => routes.sends.len() >= routes.max_sends,
+			None => true,
+		}
+	}
+
+	pub fn add_send(&mut self, index: TrackIndex, send_track: SendTrackId) {
+		if let Some(routes) = self.tracks.get_mut(&index) {
+			routes.sends.insert(send_track);
+		}
+	}
+
+	pub fn remove_send(&mut self, index: TrackIndex, send_track: SendTrackId) {
+		if let Some(routes) = self.tracks.get_mut(&index) {
+			routes.sends.shift_remove(&send_track);
+		}
+	}
+
+	fn sidechains_mut(&mut self, index: TrackIndex) -> Option<&mut IndexMap<EffectId, TrackIndex>> {
+		match index {
+			TrackIndex::Main => Some(&mut self.main_sidechains),
+			index => self
+				.tracks
+				.get_mut(&index)
+				.map(|routes| &mut routes.sidechains),
+		}
+	}
+
+	pub fn add_sidechain(&mut self, index: TrackIndex, effect_id: EffectId, sidechain: TrackIndex) {
+		if let Some(sidechains) = self.sidechains_mut(index) {
+			sidechains.insert(effect_id, sidechain);
+		}
+	}
+
+	/// Forgets the sidechain input of an effect. Returns true
+	/// if the effect had a sidechain input.
+	pub fn remove_sidechain(&mut self, index: TrackIndex, effect_id: EffectId) -> bool {
+		match self.sidechains_mut(index) {
+			Some(sidechains) => sidechains.shift_remove(&effect_id).is_some(),
+			None => false,
+		}
+	}
+
+	/// Returns the order to process sub-tracks and send tracks in
+	/// so that each track is processed after the tracks it depends on.
+	///
+	/// A track depends on the tracks that are routed into it and
+	/// the tracks its effects receive sidechain inputs from.
+	/// If tracks depend on each other in a cycle (which can only happen
+	/// with sidechains), one of them is processed first and receives
+	/// the previous frame of audio from the others.
+	pub fn processing_order(&self) -> Vec<TrackIndex> {
+		let num_tracks = self.tracks.len();
+		// for each track (by position in `self.tracks`), the tracks
+		// that have to wait for it to be processed
+		let mut dependents = vec![vec![]; num_tracks];
+		let mut num_dependencies = vec![0; num_tracks];
+		for (i, (index, routes)) in self.tracks.iter().enumerate() {
+			let outputs = routes
+				.parent_track
+				.into_iter()
+				.chain(routes.sends.iter().map(|id| TrackIndex::Send(*id)));
+			for output in outputs {
+				if let Some(j) = self.tracks.get_index_of(&output) {
+					dependents[i].push(j);
+					num_dependencies[j] += 1;
+				}
+			}
+			for sidechain in routes.sidechains.values() {
+				if sidechain == index {
+					continue;
+				}
+				// the main track is always processed last, so
+				// a sidechain from it uses the previous frame
+				if let Some(j) = self.tracks.get_index_of(sidechain) {
+					dependents[j].push(i);
+					num_dependencies[i] += 1;
+				}
+			}
+		}
+		let mut ready = (0..num_tracks)
+			.filter(|i| num_dependencies[*i] == 0)
+			.collect::<VecDeque<_>>();
+		let mut processed = vec![false; num_tracks];
+		let mut order = Vec::with_capacity(num_tracks);
+		let mut first_unprocessed = 0;
+		while order.len() < num_tracks {
+			let i = match ready.pop_front() {
+				Some(i) => i,
+				// every remaining track is waiting on a cycle, so
+				// break it at the first remaining track
+				None => {
+					while processed[first_unprocessed] {
+						first_unprocessed += 1;
+					}
+					first_unprocessed
+				}
+			};
+			// a track that was processed to break a cycle can
+			// become ready again once its dependencies are processed
+			if processed[i] {
+				continue;
+			}
+			processed[i] = true;
+			order.extend(self.tracks.get_index(i).map(|(index, _)| *index));
+			for j in &dependents[i] {
+				num_dependencies[*j] -= 1;
+				if num_dependencies[*j] == 0 {
+					ready.push_back(*j);
+				}
+			}
+		}
+		order
+	}
+}
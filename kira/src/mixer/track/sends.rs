@@ -1,6 +1,6 @@
 use indexmap::IndexMap;
 
-use crate::{CachedValue, Value};
+use crate::{static_container::index_map::StaticIndexMap, CachedValue, Value};
 
 use super::SendTrackId;
 
@@ -40,10 +40,22 @@ impl TrackSends {
 		self
 	}
 
-	pub(crate) fn to_map(&self) -> IndexMap<SendTrackId, CachedValue<f64>> {
-		let mut map = IndexMap::new();
+	/// Returns the number of send tracks in the map.
+	pub fn len(&self) -> usize {
+		self.sends.len()
+	}
+
+	/// Returns `true` if the map has no send tracks.
+	pub fn is_empty(&self) -> bool {
+		self.sends.is_empty()
+	}
+
+	/// Converts the sends to a map that can hold at least
+	/// `capacity` sends without allocating.
+	pub(crate) fn to_map(&self, capacity: usize) -> StaticIndexMap<SendTrackId, CachedValue<f64>> {
+		let mut map = StaticIndexMap::new(capacity.max(self.len()));
 		for (id, volume) in self.iter() {
-			map.insert(*id, CachedValue::new(*volume, 1.0));
+			map.try_insert(*id, CachedValue::new(*volume, 1.0)).ok();
 		}
 		map
 	}
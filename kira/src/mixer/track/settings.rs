@@ -1,6 +1,6 @@
 use crate::Value;
 
-use super::{sends::TrackSends, SendTrackId, SubTrackId, TrackIndex};
+use super::{ducking::TrackDucking, sends::TrackSends, SendTrackId, SubTrackId, TrackIndex};
 
 /// Settings for a mixer sub-track.
 #[derive(Debug, Clone)]
@@ -19,8 +19,17 @@ pub struct SubTrackSettings {
 	pub sends: TrackSends,
 	/// The volume of the track.
 	pub volume: Value<f64>,
+	/// The maximum number of send tracks this track can be
+	/// routed to at once.
+	pub num_sends: usize,
 	/// The maximum number of effects this track can hold.
 	pub num_effects: usize,
+	/// Rules for automatically lowering the volume of the
+	/// track while other tracks are playing audio.
+	pub ducking: TrackDucking,
+	/// The maximum number of ducking rules this track can have
+	/// at once.
+	pub num_ducking_rules: usize,
 }
 
 impl SubTrackSettings {
@@ -59,6 +68,12 @@ impl SubTrackSettings {
 		}
 	}
 
+	/// Sets the maximum number of send tracks this track can be
+	/// routed to at once.
+	pub fn num_sends(self, num_sends: usize) -> Self {
+		Self { num_sends, ..self }
+	}
+
 	/// Sets the maximum number of effects this track can hold.
 	pub fn num_effects(self, num_effects: usize) -> Self {
 		Self {
@@ -66,6 +81,21 @@ impl SubTrackSettings {
 			..self
 		}
 	}
+
+	/// Sets the rules for automatically lowering the volume of
+	/// the track while other tracks are playing audio.
+	pub fn ducking(self, ducking: TrackDucking) -> Self {
+		Self { ducking, ..self }
+	}
+
+	/// Sets the maximum number of ducking rules this track can
+	/// have at once.
+	pub fn num_ducking_rules(self, num_ducking_rules: usize) -> Self {
+		Self {
+			num_ducking_rules,
+			..self
+		}
+	}
 }
 
 impl Default for SubTrackSettings {
@@ -75,7 +105,10 @@ impl Default for SubTrackSettings {
 			parent_track: TrackIndex::Main,
 			sends: TrackSends::new(),
 			volume: Value::Fixed(1.0),
+			num_sends: 4,
 			num_effects: 10,
+			ducking: TrackDucking::new(),
+			num_ducking_rules: 4,
 		}
 	}
 }
@@ -90,10 +123,24 @@ impl Default for SubTrackSettings {
 pub struct SendTrackSettings {
 	/// The unique identifier for the track.
 	pub id: Option<SendTrackId>,
+	/// The track that this track's output will be routed to.
+	pub parent_track: TrackIndex,
+	/// The send tracks that this track will be routed to (in
+	/// addition to the parent track).
+	pub sends: TrackSends,
 	/// The volume of the track.
 	pub volume: Value<f64>,
+	/// The maximum number of send tracks this track can be
+	/// routed to at once.
+	pub num_sends: usize,
 	/// The maximum number of effects this track can hold.
 	pub num_effects: usize,
+	/// Rules for automatically lowering the volume of the
+	/// track while other tracks are playing audio.
+	pub ducking: TrackDucking,
+	/// The maximum number of ducking rules this track can have
+	/// at once.
+	pub num_ducking_rules: usize,
 }
 
 impl SendTrackSettings {
@@ -110,6 +157,20 @@ impl SendTrackSettings {
 		}
 	}
 
+	/// Sets the track that this track's output will be routed to.
+	pub fn parent_track(self, parent_track: impl Into<TrackIndex>) -> Self {
+		Self {
+			parent_track: parent_track.into(),
+			..self
+		}
+	}
+
+	/// Sets the send tracks that this track will be routed to (in
+	/// addition to the parent track).
+	pub fn sends(self, sends: TrackSends) -> Self {
+		Self { sends, ..self }
+	}
+
 	/// Sets the volume of the track.
 	pub fn volume(self, volume: impl Into<Value<f64>>) -> Self {
 		Self {
@@ -118,6 +179,12 @@ impl SendTrackSettings {
 		}
 	}
 
+	/// Sets the maximum number of send tracks this track can be
+	/// routed to at once.
+	pub fn num_sends(self, num_sends: usize) -> Self {
+		Self { num_sends, ..self }
+	}
+
 	/// Sets the maximum number of effects this track can hold.
 	pub fn num_effects(self, num_effects: usize) -> Self {
 		Self {
@@ -125,14 +192,34 @@ impl SendTrackSettings {
 			..self
 		}
 	}
+
+	/// Sets the rules for automatically lowering the volume of
+	/// the track while other tracks are playing audio.
+	pub fn ducking(self, ducking: TrackDucking) -> Self {
+		Self { ducking, ..self }
+	}
+
+	/// Sets the maximum number of ducking rules this track can
+	/// have at once.
+	pub fn num_ducking_rules(self, num_ducking_rules: usize) -> Self {
+		Self {
+			num_ducking_rules,
+			..self
+		}
+	}
 }
 
 impl Default for SendTrackSettings {
 	fn default() -> Self {
 		Self {
 			id: None,
+			parent_track: TrackIndex::Main,
+			sends: TrackSends::new(),
 			volume: Value::Fixed(1.0),
+			num_sends: 4,
 			num_effects: 10,
+			ducking: TrackDucking::new(),
+			num_ducking_rules: 4,
 		}
 	}
 }
@@ -7,7 +7,8 @@ pub(crate) use playables::Playables;
 
 use crate::{
 	arrangement::{handle::ArrangementHandle, Arrangement, ArrangementId},
-	group::{groups::Groups, GroupId},
+	group::{groups::Groups, GroupId, GroupSet},
+	instance::VoiceStealingPolicy,
 	mixer::TrackIndex,
 	sound::{handle::SoundHandle, Sound, SoundId},
 };
@@ -63,6 +64,15 @@ impl<'a> Playable<'a> {
 		}
 	}
 
+	/// Returns the sample rate of the playable's audio data,
+	/// if it has a single sample rate.
+	pub fn sample_rate(&self) -> Option<u32> {
+		match self {
+			Playable::Sound(sound) => Some(sound.sample_rate()),
+			Playable::Arrangement(_) => None,
+		}
+	}
+
 	pub fn default_track(&self) -> TrackIndex {
 		match self {
 			Playable::Sound(sound) => sound.default_track(),
@@ -77,6 +87,20 @@ impl<'a> Playable<'a> {
 		}
 	}
 
+	pub fn default_loop_crossfade(&self) -> f64 {
+		match self {
+			Playable::Sound(sound) => sound.default_loop_crossfade(),
+			Playable::Arrangement(_) => 0.0,
+		}
+	}
+
+	pub fn groups(&self) -> &GroupSet {
+		match self {
+			Playable::Sound(sound) => sound.groups(),
+			Playable::Arrangement(arrangement) => arrangement.groups(),
+		}
+	}
+
 	pub fn is_in_group(&self, id: GroupId, all_groups: &Groups) -> bool {
 		match self {
 			Playable::Sound(sound) => sound.is_in_group(id, all_groups),
@@ -98,6 +122,13 @@ impl<'a> PlayableMut<'a> {
 		}
 	}
 
+	pub fn instance_limit(&self) -> Option<(usize, VoiceStealingPolicy)> {
+		match self {
+			PlayableMut::Sound(sound) => sound.instance_limit(),
+			PlayableMut::Arrangement(arrangement) => arrangement.instance_limit(),
+		}
+	}
+
 	pub fn start_cooldown(&mut self) {
 		match self {
 			PlayableMut::Sound(sound) => {
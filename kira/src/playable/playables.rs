@@ -3,7 +3,7 @@ use basedrop::Owned;
 use crate::{
 	arrangement::{Arrangement, ArrangementId},
 	command::ResourceCommand,
-	sound::{Sound, SoundId},
+	sound::{ResamplingQuality, Sound, SoundId},
 	static_container::index_map::StaticIndexMap,
 	Frame,
 };
@@ -53,14 +53,21 @@ impl Playables {
 		}
 	}
 
-	pub fn frame_at_position(&self, id: PlayableId, position: f64) -> Option<Frame> {
+	pub fn frame_at_position(
+		&self,
+		id: PlayableId,
+		position: f64,
+		sample_index: Option<i64>,
+		step: f64,
+		quality: Option<ResamplingQuality>,
+	) -> Option<Frame> {
 		match id {
 			PlayableId::Sound(id) => self
 				.sound(id)
-				.map(|sound| sound.get_frame_at_position(position)),
-			PlayableId::Arrangement(id) => self
-				.arrangement(id)
-				.map(|arrangement| arrangement.get_frame_at_position(position, &self.sounds)),
+				.map(|sound| sound.get_resampled_frame(position, sample_index, step, quality)),
+			PlayableId::Arrangement(id) => self.arrangement(id).map(|arrangement| {
+				arrangement.get_frame_at_position(position, step, quality, &self.sounds)
+			}),
 		}
 	}
 
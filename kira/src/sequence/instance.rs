@@ -6,7 +6,8 @@ use ringbuf::Producer;
 use uuid::Uuid;
 
 use crate::{
-	group::{groups::Groups, GroupId},
+	group::{groups::Groups, GroupId, GroupSet},
+	instance::InstanceSettings,
 	metronome::{MetronomeId, Metronomes},
 	static_container::vec::StaticVec,
 	Tempo,
@@ -86,6 +87,16 @@ impl SequenceInstance {
 		self.public_state.clone()
 	}
 
+	/// Returns the instance settings of a step that plays a sound
+	/// or arrangement.
+	pub(crate) fn instance_settings(&self, step_index: usize) -> Option<&InstanceSettings> {
+		match self.sequence.steps.get(step_index) {
+			Some(SequenceStep::PlaySound(_, _, settings))
+			| Some(SequenceStep::PlayRandom(_, _, settings)) => Some(settings),
+			_ => None,
+		}
+	}
+
 	fn set_state(&mut self, state: SequenceInstanceState) {
 		self.state = state;
 		self.public_state.store(state, Ordering::Relaxed);
@@ -175,14 +186,26 @@ impl SequenceInstance {
 								}
 								self.start_step(self.position + 1);
 							}
-							SequenceStep::PlayRandom(choices, id, settings) => {
+							SequenceStep::PlaySound(playable_id, id, _) => {
+								if !self.muted {
+									output_command_queue
+										.try_push(SequenceOutputCommand::PlaySound(
+											*playable_id,
+											*id,
+											self.position,
+										))
+										.ok();
+								}
+								self.start_step(self.position + 1);
+							}
+							SequenceStep::PlayRandom(choices, id, _) => {
 								if !self.muted {
 									let choice_index = thread_rng().gen_range(0..choices.len());
 									output_command_queue
 										.try_push(SequenceOutputCommand::PlaySound(
 											choices[choice_index],
 											*id,
-											*settings,
+											self.position,
 										))
 										.ok();
 								}
@@ -209,6 +232,10 @@ impl SequenceInstance {
 		}
 	}
 
+	pub(crate) fn groups(&self) -> &GroupSet {
+		self.sequence.groups()
+	}
+
 	pub(crate) fn is_in_group(&self, parent_id: GroupId, groups: &Groups) -> bool {
 		self.sequence.is_in_group(parent_id, groups)
 	}
@@ -250,7 +250,9 @@ impl Default for SequenceInstanceSettings {
 	derive(serde::Serialize, serde::Deserialize)
 )]
 pub(crate) enum SequenceOutputCommand {
-	PlaySound(PlayableId, InstanceId, InstanceSettings),
+	/// Plays a sound or arrangement with the instance settings
+	/// of the step at the given index.
+	PlaySound(PlayableId, InstanceId, usize),
 	SetInstanceVolume(InstanceId, Value<f64>),
 	SetInstancePlaybackRate(InstanceId, Value<f64>),
 	SetInstancePanning(InstanceId, Value<f64>),
@@ -286,7 +288,8 @@ pub(crate) enum SequenceStep<CustomEvent: Clone + Eq + Hash> {
 	Wait(Duration),
 	WaitForInterval(f64),
 	RunCommand(SequenceOutputCommand),
-	PlayRandom(Vec<PlayableId>, InstanceId, InstanceSettings),
+	PlaySound(PlayableId, InstanceId, Box<InstanceSettings>),
+	PlayRandom(Vec<PlayableId>, InstanceId, Box<InstanceSettings>),
 	EmitCustomEvent(CustomEvent),
 }
 
@@ -400,8 +403,11 @@ impl<CustomEvent: Clone + Eq + Hash> Sequence<CustomEvent> {
 		settings: InstanceSettings,
 	) -> InstanceId {
 		let id = settings.id.unwrap_or(InstanceId::new());
-		self.steps
-			.push(SequenceOutputCommand::PlaySound(playable.into(), id, settings).into());
+		self.steps.push(SequenceStep::PlaySound(
+			playable.into(),
+			id,
+			Box::new(settings),
+		));
 		id
 	}
 
@@ -414,7 +420,7 @@ impl<CustomEvent: Clone + Eq + Hash> Sequence<CustomEvent> {
 	) -> InstanceId {
 		let id = settings.id.unwrap_or(InstanceId::new());
 		self.steps
-			.push(SequenceStep::PlayRandom(choices, id, settings).into());
+			.push(SequenceStep::PlayRandom(choices, id, Box::new(settings)));
 		id
 	}
 
@@ -629,8 +635,11 @@ impl<CustomEvent: Clone + Eq + Hash> Sequence<CustomEvent> {
 				SequenceStep::Wait(duration) => SequenceStep::Wait(*duration),
 				SequenceStep::WaitForInterval(interval) => SequenceStep::WaitForInterval(*interval),
 				SequenceStep::RunCommand(command) => SequenceStep::RunCommand(*command),
+				SequenceStep::PlaySound(playable_id, id, settings) => {
+					SequenceStep::PlaySound(*playable_id, *id, settings.clone())
+				}
 				SequenceStep::PlayRandom(choices, id, settings) => {
-					SequenceStep::PlayRandom(choices.clone(), *id, *settings)
+					SequenceStep::PlayRandom(choices.clone(), *id, settings.clone())
 				}
 				SequenceStep::EmitCustomEvent(event) => {
 					SequenceStep::EmitCustomEvent(events.get_index_of(event).unwrap())
@@ -686,11 +695,6 @@ impl RawSequence {
 		for step in steps {
 			match step {
 				SequenceStep::RunCommand(command) => match command {
-					SequenceOutputCommand::PlaySound(_, id, _) => {
-						if *id == old_id {
-							*id = new_id;
-						}
-					}
 					SequenceOutputCommand::SetInstanceVolume(id, _) => {
 						if *id == old_id {
 							*id = new_id;
@@ -723,7 +727,7 @@ impl RawSequence {
 					}
 					_ => {}
 				},
-				SequenceStep::PlayRandom(_, id, _) => {
+				SequenceStep::PlaySound(_, id, _) | SequenceStep::PlayRandom(_, id, _) => {
 					if *id == old_id {
 						*id = new_id;
 					}
@@ -741,14 +745,7 @@ impl RawSequence {
 	fn update_instance_ids(&mut self) {
 		for i in 0..self.steps.len() {
 			match &self.steps[i] {
-				SequenceStep::RunCommand(command) => match command {
-					SequenceOutputCommand::PlaySound(_, id, _) => {
-						let old_id = *id;
-						Self::convert_ids(&mut self.steps, old_id, InstanceId::new());
-					}
-					_ => {}
-				},
-				SequenceStep::PlayRandom(_, id, _) => {
+				SequenceStep::PlaySound(_, id, _) | SequenceStep::PlayRandom(_, id, _) => {
 					let old_id = *id;
 					Self::convert_ids(&mut self.steps, old_id, InstanceId::new());
 				}
@@ -0,0 +1,281 @@
+//! Offline generators for creating placeholder sounds.
+//!
+//! These are meant for prototyping UI blips and sound effects
+//! before real assets exist. Oscillators are not band-limited,
+//! so high frequency square and saw waves will alias.
+
+use std::f64::consts::PI;
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::Frame;
+
+/// Scales the output of the pink noise filter to roughly
+/// the same range as white noise.
+const PINK_NOISE_GAIN: f64 = 0.25;
+
+/// The shape of an oscillator's wave.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(
+	feature = "serde_support",
+	derive(serde::Serialize, serde::Deserialize)
+)]
+pub enum Waveform {
+	/// A sine wave.
+	Sine,
+	/// A square wave.
+	Square,
+	/// A sawtooth wave that rises from -1 to 1.
+	Saw,
+	/// A triangle wave.
+	Triangle,
+}
+
+impl Waveform {
+	/// Gets the value of the wave at a phase from 0 to 1.
+	fn value(self, phase: f64) -> f64 {
+		match self {
+			Waveform::Sine => (phase * 2.0 * PI).sin(),
+			Waveform::Square => {
+				if phase < 0.5 {
+					1.0
+				} else {
+					-1.0
+				}
+			}
+			Waveform::Saw => phase * 2.0 - 1.0,
+			Waveform::Triangle => 1.0 - 4.0 * ((phase + 0.25).fract() - 0.5).abs(),
+		}
+	}
+}
+
+/// What a [`Generator`] produces before its amplitude
+/// and envelope are applied.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(
+	feature = "serde_support",
+	derive(serde::Serialize, serde::Deserialize)
+)]
+pub enum GeneratorSource {
+	/// An oscillator with a fixed frequency (in Hz).
+	Oscillator {
+		/// The shape of the oscillator's wave.
+		waveform: Waveform,
+		/// The frequency of the oscillator (in Hz).
+		frequency: f64,
+	},
+	/// An oscillator whose frequency (in Hz) sweeps linearly
+	/// from a start frequency to an end frequency over the
+	/// length of the sound.
+	Chirp {
+		/// The shape of the oscillator's wave.
+		waveform: Waveform,
+		/// The frequency at the start of the sound (in Hz).
+		start_frequency: f64,
+		/// The frequency at the end of the sound (in Hz).
+		end_frequency: f64,
+	},
+	/// Noise with equal power at every frequency.
+	WhiteNoise,
+	/// Noise whose power falls off by 3dB per octave.
+	PinkNoise,
+}
+
+/// An attack-decay-sustain-release envelope.
+///
+/// The release phase ends at the end of the sound.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(
+	feature = "serde_support",
+	derive(serde::Serialize, serde::Deserialize),
+	serde(default)
+)]
+pub struct Envelope {
+	/// How long it takes for the amplitude to rise
+	/// from 0 to 1 (in seconds).
+	pub attack: f64,
+	/// How long it takes for the amplitude to fall from 1 to
+	/// the sustain level after the attack (in seconds).
+	pub decay: f64,
+	/// The amplitude to hold after the decay.
+	pub sustain: f64,
+	/// How long it takes for the amplitude to fall to 0
+	/// at the end of the sound (in seconds).
+	pub release: f64,
+}
+
+impl Envelope {
+	/// Creates a new envelope.
+	pub fn new(attack: f64, decay: f64, sustain: f64, release: f64) -> Self {
+		Self {
+			attack,
+			decay,
+			sustain,
+			release,
+		}
+	}
+
+	/// Gets the amplitude of the envelope before the release
+	/// phase starts.
+	fn held_amplitude(&self, time: f64) -> f64 {
+		if time < self.attack {
+			return time / self.attack;
+		}
+		let time = time - self.attack;
+		if time < self.decay {
+			return 1.0 + (self.sustain - 1.0) * (time / self.decay);
+		}
+		self.sustain
+	}
+
+	/// Gets the amplitude of the envelope at a point in time
+	/// for a sound of the given duration.
+	pub fn amplitude(&self, time: f64, duration: f64) -> f64 {
+		let release_start = (duration - self.release).max(0.0);
+		if time < release_start {
+			return self.held_amplitude(time);
+		}
+		// release from wherever the envelope was when the
+		// release started, even if it was still attacking
+		let release_progress = ((time - release_start) / self.release).min(1.0);
+		self.held_amplitude(release_start) * (1.0 - release_progress)
+	}
+}
+
+impl Default for Envelope {
+	fn default() -> Self {
+		Self {
+			attack: 0.01,
+			decay: 0.1,
+			sustain: 0.5,
+			release: 0.1,
+		}
+	}
+}
+
+/// Describes a procedurally generated sound.
+///
+/// Use with [`Sound::from_generator`](super::Sound::from_generator).
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(
+	feature = "serde_support",
+	derive(serde::Serialize, serde::Deserialize)
+)]
+pub struct Generator {
+	/// What the generator produces.
+	pub source: GeneratorSource,
+	/// The peak amplitude of the generated sound.
+	pub amplitude: f64,
+	/// The envelope to shape the generated sound with, if any.
+	pub envelope: Option<Envelope>,
+	/// The seed used for noise generators.
+	pub seed: u64,
+}
+
+impl Generator {
+	/// Creates a new generator with the given source.
+	pub fn new(source: GeneratorSource) -> Self {
+		Self {
+			source,
+			amplitude: 1.0,
+			envelope: None,
+			seed: 0,
+		}
+	}
+
+	/// Creates a generator for an oscillator with
+	/// a fixed frequency (in Hz).
+	pub fn oscillator(waveform: Waveform, frequency: f64) -> Self {
+		Self::new(GeneratorSource::Oscillator {
+			waveform,
+			frequency,
+		})
+	}
+
+	/// Creates a generator for an oscillator whose frequency
+	/// sweeps from a start frequency to an end frequency (in Hz).
+	pub fn chirp(waveform: Waveform, start_frequency: f64, end_frequency: f64) -> Self {
+		Self::new(GeneratorSource::Chirp {
+			waveform,
+			start_frequency,
+			end_frequency,
+		})
+	}
+
+	/// Creates a generator for white noise.
+	pub fn white_noise() -> Self {
+		Self::new(GeneratorSource::WhiteNoise)
+	}
+
+	/// Creates a generator for pink noise.
+	pub fn pink_noise() -> Self {
+		Self::new(GeneratorSource::PinkNoise)
+	}
+
+	/// Sets the peak amplitude of the generated sound.
+	pub fn amplitude(self, amplitude: f64) -> Self {
+		Self { amplitude, ..self }
+	}
+
+	/// Sets the envelope to shape the generated sound with.
+	pub fn envelope(self, envelope: Envelope) -> Self {
+		Self {
+			envelope: Some(envelope),
+			..self
+		}
+	}
+
+	/// Sets the seed used for noise generators.
+	pub fn seed(self, seed: u64) -> Self {
+		Self { seed, ..self }
+	}
+
+	pub(crate) fn generate(&self, sample_rate: u32, duration: f64) -> Vec<Frame> {
+		let num_frames = (duration * sample_rate as f64).round() as usize;
+		let dt = 1.0 / sample_rate as f64;
+		let mut rng = StdRng::seed_from_u64(self.seed);
+		let mut phase = 0.0;
+		let mut pink_noise_state = [0.0; 3];
+		(0..num_frames)
+			.map(|i| {
+				let time = i as f64 * dt;
+				let value = match self.source {
+					GeneratorSource::Oscillator {
+						waveform,
+						frequency,
+					} => {
+						let value = waveform.value(phase);
+						phase = (phase + frequency * dt).fract();
+						value
+					}
+					GeneratorSource::Chirp {
+						waveform,
+						start_frequency,
+						end_frequency,
+					} => {
+						let value = waveform.value(phase);
+						let frequency =
+							start_frequency + (end_frequency - start_frequency) * (time / duration);
+						phase = (phase + frequency * dt).fract();
+						value
+					}
+					GeneratorSource::WhiteNoise => rng.gen_range(-1.0..=1.0),
+					GeneratorSource::PinkNoise => {
+						// Paul Kellet's economy pink noise filter
+						let white: f64 = rng.gen_range(-1.0..=1.0);
+						pink_noise_state[0] = 0.99765 * pink_noise_state[0] + white * 0.0990460;
+						pink_noise_state[1] = 0.96300 * pink_noise_state[1] + white * 0.2965164;
+						pink_noise_state[2] = 0.57000 * pink_noise_state[2] + white * 1.0526913;
+						let pink = pink_noise_state.iter().sum::<f64>() + white * 0.1848;
+						(pink * PINK_NOISE_GAIN).clamp(-1.0, 1.0)
+					}
+				};
+				let envelope_amplitude = match &self.envelope {
+					Some(envelope) => envelope.amplitude(time, duration),
+					None => 1.0,
+				};
+				Frame::from_mono((value * self.amplitude * envelope_amplitude) as f32)
+			})
+			.collect()
+	}
+}
@@ -12,28 +12,40 @@ use crate::{
 	mixer::TrackIndex,
 };
 
+use basedrop::Owned;
+
 use super::{Sound, SoundId};
 
 /// Allows you to control a sound.
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct SoundHandle {
 	id: SoundId,
 	duration: f64,
+	sample_rate: u32,
 	default_track: TrackIndex,
 	semantic_duration: Option<f64>,
 	default_loop_start: Option<f64>,
+	default_loop_crossfade: f64,
 	command_producer: CommandProducer,
+	resource_collector_handle: basedrop::Handle,
 }
 
 impl SoundHandle {
-	pub(crate) fn new(sound: &Sound, command_producer: CommandProducer) -> Self {
+	pub(crate) fn new(
+		sound: &Sound,
+		command_producer: CommandProducer,
+		resource_collector_handle: basedrop::Handle,
+	) -> Self {
 		Self {
 			id: sound.id(),
 			duration: sound.duration(),
+			sample_rate: sound.sample_rate(),
 			default_track: sound.default_track(),
 			semantic_duration: sound.semantic_duration(),
 			default_loop_start: sound.default_loop_start(),
+			default_loop_crossfade: sound.default_loop_crossfade(),
 			command_producer,
+			resource_collector_handle,
 		}
 	}
 
@@ -66,14 +78,26 @@ impl SoundHandle {
 		self.default_loop_start
 	}
 
+	/// Returns how long (in seconds) instances of this sound
+	/// will crossfade the audio around the loop points by default.
+	pub fn default_loop_crossfade(&self) -> f64 {
+		self.default_loop_crossfade
+	}
+
 	/// Plays the sound.
 	pub fn play(&mut self, settings: InstanceSettings) -> Result<InstanceHandle, CommandError> {
 		let id = settings.id.unwrap_or(InstanceId::new());
 		let instance = Instance::new(
 			self.id.into(),
 			self.duration,
+			Some(self.sample_rate),
 			None,
-			settings.into_internal(self.duration, self.default_loop_start, self.default_track),
+			settings.into_internal(
+				self.duration,
+				self.default_loop_start,
+				self.default_loop_crossfade,
+				self.default_track,
+			),
 		);
 		let handle = InstanceHandle::new(
 			id,
@@ -81,6 +105,7 @@ impl SoundHandle {
 			instance.public_position(),
 			self.command_producer.clone(),
 		);
+		let instance = Owned::new(&self.resource_collector_handle, Some(instance));
 		self.command_producer
 			.push(InstanceCommand::Play(id, instance).into())?;
 		Ok(handle)
@@ -104,3 +129,22 @@ impl SoundHandle {
 			.push(InstanceCommand::StopInstancesOf(self.id.into(), settings).into())
 	}
 }
+
+impl std::fmt::Debug for SoundHandle {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		#[derive(Debug)]
+		struct ResourceCollectorHandle;
+
+		f.debug_struct("SoundHandle")
+			.field("id", &self.id)
+			.field("duration", &self.duration)
+			.field("sample_rate", &self.sample_rate)
+			.field("default_track", &self.default_track)
+			.field("semantic_duration", &self.semantic_duration)
+			.field("default_loop_start", &self.default_loop_start)
+			.field("default_loop_crossfade", &self.default_loop_crossfade)
+			.field("command_producer", &self.command_producer)
+			.field("resource_collector_handle", &ResourceCollectorHandle)
+			.finish()
+	}
+}
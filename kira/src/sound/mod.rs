@@ -1,36 +1,59 @@
 //! A chunk of audio data.
 
 pub mod error;
+pub mod generator;
 pub mod handle;
 mod id;
+mod resampling;
 mod settings;
+#[cfg(test)]
+mod tests;
+pub mod waveform;
 
 pub use id::SoundId;
+pub use resampling::ResamplingQuality;
 pub use settings::SoundSettings;
 
+use generator::Generator;
+use waveform::WaveformSummary;
+
 use crate::{
 	frame::Frame,
 	group::{groups::Groups, GroupId, GroupSet},
+	instance::VoiceStealingPolicy,
 	mixer::TrackIndex,
-	util,
 };
 
-use std::fmt::{Debug, Formatter};
+use std::{
+	fmt::{Debug, Formatter},
+	ops::Range,
+	sync::Arc,
+};
 
 #[cfg(any(feature = "mp3", feature = "ogg", feature = "flac", feature = "wav"))]
 use std::{fs::File, path::Path};
 
+/// How far (in samples) the playback position can be from
+/// a whole sample while still being considered exactly on it.
+pub(crate) const EXACT_PLAYBACK_EPSILON: f64 = 1.0e-6;
+
 /// A piece of audio that can be played by an [`AudioManager`](crate::manager::AudioManager).
 #[derive(Clone)]
 pub struct Sound {
 	id: SoundId,
 	sample_rate: u32,
-	frames: Vec<Frame>,
+	frames: Arc<Vec<Frame>>,
+	frame_range: Range<usize>,
 	duration: f64,
 	default_track: TrackIndex,
 	cooldown: Option<f64>,
+	max_instances: Option<usize>,
+	voice_stealing_policy: VoiceStealingPolicy,
 	semantic_duration: Option<f64>,
 	default_loop_start: Option<f64>,
+	default_loop_crossfade: f64,
+	resampling_quality: ResamplingQuality,
+	resample_on_add: Option<ResamplingQuality>,
 	groups: GroupSet,
 	cooldown_timer: f64,
 }
@@ -42,17 +65,54 @@ impl Sound {
 		Self {
 			id: settings.id.unwrap_or(SoundId::new()),
 			sample_rate,
-			frames,
+			frame_range: 0..frames.len(),
+			frames: Arc::new(frames),
 			duration,
 			default_track: settings.default_track,
 			cooldown: settings.cooldown,
+			max_instances: settings.max_instances,
+			voice_stealing_policy: settings.voice_stealing_policy,
 			semantic_duration: settings.semantic_duration,
 			default_loop_start: settings.default_loop_start,
+			default_loop_crossfade: settings.default_loop_crossfade,
+			resampling_quality: settings.resampling_quality,
+			resample_on_add: settings.resample_on_add,
 			groups: settings.groups,
 			cooldown_timer: 0.0,
 		}
 	}
 
+	/// Creates a new sound by calling a function for each frame.
+	///
+	/// The function is given the time of the frame (in seconds)
+	/// from the start of the sound.
+	pub fn from_fn(
+		sample_rate: u32,
+		duration: f64,
+		mut f: impl FnMut(f64) -> Frame,
+		settings: SoundSettings,
+	) -> Self {
+		let num_frames = (duration * sample_rate as f64).round() as usize;
+		let frames = (0..num_frames)
+			.map(|i| f(i as f64 / sample_rate as f64))
+			.collect();
+		Self::from_frames(sample_rate, frames, settings)
+	}
+
+	/// Creates a new sound from a [`Generator`].
+	pub fn from_generator(
+		sample_rate: u32,
+		duration: f64,
+		generator: Generator,
+		settings: SoundSettings,
+	) -> Self {
+		Self::from_frames(
+			sample_rate,
+			generator.generate(sample_rate, duration),
+			settings,
+		)
+	}
+
 	/// Decodes a sound from an mp3 file.
 	#[cfg(feature = "mp3")]
 	pub fn from_mp3_file<P>(
@@ -274,6 +334,81 @@ impl Sound {
 		Err(error::SoundFromFileError::UnsupportedAudioFileFormat)
 	}
 
+	/// Converts the sound's audio data to a different sample rate.
+	///
+	/// This is a slow operation, so it should be done before
+	/// the sound is added to an [`AudioManager`](crate::manager::AudioManager).
+	/// Instances of a sound whose sample rate matches the audio
+	/// device's sample rate don't need to interpolate between
+	/// samples when they're played at their normal playback rate.
+	pub fn resample(&mut self, sample_rate: u32, quality: ResamplingQuality) {
+		if sample_rate == self.sample_rate {
+			return;
+		}
+		let step = self.sample_rate as f64 / sample_rate as f64;
+		let num_frames = (self.frames().len() as f64 / step).ceil() as usize;
+		let frames: Vec<Frame> = (0..num_frames)
+			.map(|i| quality.resample(self.frames(), i as f64 * step, step))
+			.collect();
+		self.frame_range = 0..frames.len();
+		self.frames = Arc::new(frames);
+		self.sample_rate = sample_rate;
+		self.duration = num_frames as f64 / sample_rate as f64;
+	}
+
+	/// Creates a new sound that plays a time range (in seconds)
+	/// of this sound's audio data.
+	///
+	/// The new sound shares the audio data with this sound rather
+	/// than copying it, but it has its own ID and settings, so it
+	/// can have its own loop points, cooldown, default track, and so on.
+	/// The range is clamped to the length of this sound.
+	///
+	/// Resampling a slice gives it its own copy of the audio data.
+	pub fn slice(&self, start: f64, end: f64, settings: SoundSettings) -> Self {
+		let len = self.frame_range.len();
+		let to_frame_index =
+			|time: f64| ((time * self.sample_rate as f64).round().max(0.0) as usize).min(len);
+		let start = to_frame_index(start);
+		let end = to_frame_index(end).max(start);
+		let frame_range = self.frame_range.start + start..self.frame_range.start + end;
+		Self {
+			id: settings.id.unwrap_or(SoundId::new()),
+			sample_rate: self.sample_rate,
+			frames: self.frames.clone(),
+			duration: frame_range.len() as f64 / self.sample_rate as f64,
+			frame_range,
+			default_track: settings.default_track,
+			cooldown: settings.cooldown,
+			max_instances: settings.max_instances,
+			voice_stealing_policy: settings.voice_stealing_policy,
+			semantic_duration: settings.semantic_duration,
+			default_loop_start: settings.default_loop_start,
+			default_loop_crossfade: settings.default_loop_crossfade,
+			resampling_quality: settings.resampling_quality,
+			resample_on_add: settings.resample_on_add,
+			groups: settings.groups,
+			cooldown_timer: 0.0,
+		}
+	}
+
+	/// Gets the audio data this sound plays.
+	pub fn frames(&self) -> &[Frame] {
+		&self.frames[self.frame_range.clone()]
+	}
+
+	/// Computes a min/max/RMS overview of the sound's audio data
+	/// for drawing waveforms.
+	///
+	/// The summary has `num_levels` levels. The first level has
+	/// one point for every `base_resolution` samples, and each
+	/// level after that covers twice as many samples per point.
+	/// This can be slow for long sounds, so the result should be
+	/// stored rather than recomputed every time it's needed.
+	pub fn waveform_summary(&self, base_resolution: usize, num_levels: usize) -> WaveformSummary {
+		WaveformSummary::new(self.sample_rate, self.frames(), base_resolution, num_levels)
+	}
+
 	/// Gets the unique identifier for this sound.
 	pub fn id(&self) -> SoundId {
 		self.id
@@ -306,33 +441,77 @@ impl Sound {
 		self.default_loop_start
 	}
 
+	/// Returns how long (in seconds) instances of this sound
+	/// will crossfade the audio around the loop points by default.
+	pub fn default_loop_crossfade(&self) -> f64 {
+		self.default_loop_crossfade
+	}
+
+	/// Gets the sample rate of the sound's audio data.
+	pub fn sample_rate(&self) -> u32 {
+		self.sample_rate
+	}
+
+	/// Returns the quality the sound should be resampled with
+	/// to match the audio device's sample rate when it's added
+	/// to an [`AudioManager`](crate::manager::AudioManager), if any.
+	pub fn resample_on_add(&self) -> Option<ResamplingQuality> {
+		self.resample_on_add
+	}
+
+	/// Returns the resampling quality instances of this
+	/// sound will use by default.
+	pub fn resampling_quality(&self) -> ResamplingQuality {
+		self.resampling_quality
+	}
+
 	/// Gets the frame of this sound at an arbitrary time
 	/// in seconds, interpolating between samples if necessary.
 	pub fn get_frame_at_position(&self, position: f64) -> Frame {
+		self.get_resampled_frame(position, None, 0.0, None)
+	}
+
+	/// Gets the frame of this sound at an arbitrary time
+	/// in seconds.
+	///
+	/// `sample_index` is the playback position as a whole number
+	/// of samples, if the caller knows it's exactly on a sample.
+	/// `step` is how far (in seconds) the playback position
+	/// moves each frame, and `quality` overrides the sound's
+	/// default resampling quality.
+	pub(crate) fn get_resampled_frame(
+		&self,
+		position: f64,
+		sample_index: Option<i64>,
+		step: f64,
+		quality: Option<ResamplingQuality>,
+	) -> Frame {
 		let sample_position = self.sample_rate as f64 * position;
-		let fraction = (sample_position % 1.0) as f32;
-		let current_sample_index = sample_position as usize;
-		let previous = if current_sample_index == 0 {
-			Frame::from_mono(0.0)
-		} else {
-			*self
-				.frames
-				.get(current_sample_index - 1)
-				.unwrap_or(&Frame::from_mono(0.0))
-		};
-		let current = *self
-			.frames
-			.get(current_sample_index)
-			.unwrap_or(&Frame::from_mono(0.0));
-		let next_1 = *self
-			.frames
-			.get(current_sample_index + 1)
-			.unwrap_or(&Frame::from_mono(0.0));
-		let next_2 = *self
-			.frames
-			.get(current_sample_index + 2)
-			.unwrap_or(&Frame::from_mono(0.0));
-		util::interpolate_frame(previous, current, next_1, next_2, fraction)
+		let sample_step = self.sample_rate as f64 * step.abs();
+		// if the playback position lands exactly on every sample,
+		// there's nothing to interpolate
+		if let Some(sample_index) = sample_index {
+			if (sample_step - 1.0).abs() < EXACT_PLAYBACK_EPSILON {
+				return ResamplingQuality::Nearest.resample(
+					self.frames(),
+					sample_index as f64,
+					1.0,
+				);
+			}
+		}
+		quality.unwrap_or(self.resampling_quality).resample(
+			self.frames(),
+			sample_position,
+			sample_step,
+		)
+	}
+
+	/// Returns the maximum number of instances of this sound
+	/// that can be playing at a time and what to do when a new
+	/// instance is started at that limit, if there is a limit.
+	pub(crate) fn instance_limit(&self) -> Option<(usize, VoiceStealingPolicy)> {
+		self.max_instances
+			.map(|max_instances| (max_instances, self.voice_stealing_policy))
 	}
 
 	/// Starts the cooldown timer for the sound.
@@ -365,13 +544,18 @@ impl Sound {
 
 impl Debug for Sound {
 	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-		f.debug_struct(&format!("Sound ({} frames)", self.frames.len()))
+		f.debug_struct(&format!("Sound ({} frames)", self.frame_range.len()))
 			.field("sample_rate", &self.sample_rate)
 			.field("duration", &self.duration)
 			.field("default_track", &self.default_track)
 			.field("cooldown", &self.cooldown)
+			.field("max_instances", &self.max_instances)
+			.field("voice_stealing_policy", &self.voice_stealing_policy)
 			.field("semantic_duration", &self.semantic_duration)
 			.field("default_loop_start", &self.default_loop_start)
+			.field("default_loop_crossfade", &self.default_loop_crossfade)
+			.field("resampling_quality", &self.resampling_quality)
+			.field("resample_on_add", &self.resample_on_add)
 			.field("groups", &self.groups)
 			.field("cooldown_timer", &self.cooldown_timer)
 			.finish()
@@ -0,0 +1,119 @@
+use std::f64::consts::PI;
+
+use crate::{util, Frame};
+
+/// The number of zero crossings of the sinc kernel on each
+/// side of the interpolated point when no anti-aliasing
+/// is needed.
+const SINC_ZERO_CROSSINGS: f64 = 8.0;
+/// The maximum number of samples on each side of the
+/// interpolated point that the sinc kernel will read.
+///
+/// The kernel widens as the cutoff frequency lowers, so this
+/// puts an upper bound on the cost of very high playback rates.
+const MAX_SINC_HALF_WIDTH: f64 = 64.0;
+
+/// How frames that fall between the samples of a sound
+/// are calculated.
+///
+/// Higher quality modes sound cleaner when a sound is played
+/// back at a different playback rate than normal or at a sample
+/// rate that differs from the audio device's, but they take more
+/// processing power.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+#[cfg_attr(
+	feature = "serde_support",
+	derive(serde::Serialize, serde::Deserialize)
+)]
+pub enum ResamplingQuality {
+	/// Uses the sample closest to the playback position.
+	///
+	/// This is the cheapest mode, but it adds a lot of
+	/// distortion to resampled sounds.
+	Nearest,
+	/// Linearly interpolates between the two samples around
+	/// the playback position.
+	Linear,
+	/// Uses 4-point, 3rd-order Hermite interpolation.
+	#[default]
+	Hermite,
+	/// Uses a windowed sinc kernel whose cutoff frequency follows
+	/// the playback rate.
+	///
+	/// This is the only mode that filters out frequencies that
+	/// would alias when a sound is pitched up or when its sample
+	/// rate is higher than the audio device's. It is also by far
+	/// the most expensive mode.
+	Sinc,
+}
+
+impl ResamplingQuality {
+	/// Gets the frame at a fractional sample index of `frames`.
+	///
+	/// `step` is the number of samples the playback position
+	/// advances by for each output frame, which determines how much
+	/// the sinc kernel needs to filter out.
+	pub(crate) fn resample(self, frames: &[Frame], sample_position: f64, step: f64) -> Frame {
+		let index = sample_position.floor() as i64;
+		let fraction = sample_position - index as f64;
+		match self {
+			ResamplingQuality::Nearest => frame_at_index(frames, sample_position.round() as i64),
+			ResamplingQuality::Linear => {
+				let current = frame_at_index(frames, index);
+				let next = frame_at_index(frames, index + 1);
+				current + (next - current) * fraction as f32
+			}
+			ResamplingQuality::Hermite => util::interpolate_frame(
+				frame_at_index(frames, index - 1),
+				frame_at_index(frames, index),
+				frame_at_index(frames, index + 1),
+				frame_at_index(frames, index + 2),
+				fraction as f32,
+			),
+			ResamplingQuality::Sinc => sinc_interpolate(frames, sample_position, step),
+		}
+	}
+}
+
+fn frame_at_index(frames: &[Frame], index: i64) -> Frame {
+	if index < 0 {
+		return Frame::from_mono(0.0);
+	}
+	*frames.get(index as usize).unwrap_or(&Frame::from_mono(0.0))
+}
+
+fn sinc(x: f64) -> f64 {
+	if x == 0.0 {
+		1.0
+	} else {
+		(PI * x).sin() / (PI * x)
+	}
+}
+
+/// A Blackman window, where `x` ranges from -1 to 1.
+fn blackman_window(x: f64) -> f64 {
+	0.42 + 0.5 * (PI * x).cos() + 0.08 * (2.0 * PI * x).cos()
+}
+
+fn sinc_interpolate(frames: &[Frame], sample_position: f64, step: f64) -> Frame {
+	// the cutoff frequency as a fraction of the sound's nyquist
+	// frequency. when the playback position advances by more than
+	// one sample per frame, frequencies above the output's nyquist
+	// frequency have to be removed
+	let cutoff = if step > 1.0 { 1.0 / step } else { 1.0 };
+	let half_width = (SINC_ZERO_CROSSINGS / cutoff).min(MAX_SINC_HALF_WIDTH);
+	let first_index = (sample_position - half_width).ceil() as i64;
+	let last_index = (sample_position + half_width).floor() as i64;
+	let mut out = Frame::from_mono(0.0);
+	let mut total_weight = 0.0;
+	for index in first_index..=last_index {
+		let x = index as f64 - sample_position;
+		let weight = cutoff * sinc(cutoff * x) * blackman_window(x / half_width);
+		out += frame_at_index(frames, index) * weight as f32;
+		total_weight += weight;
+	}
+	if total_weight == 0.0 {
+		return out;
+	}
+	out / total_weight as f32
+}
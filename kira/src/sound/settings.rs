@@ -1,6 +1,6 @@
-use crate::{group::GroupSet, mixer::TrackIndex};
+use crate::{group::GroupSet, instance::VoiceStealingPolicy, mixer::TrackIndex};
 
-use super::SoundId;
+use super::{ResamplingQuality, SoundId};
 
 /// Settings for a [`Sound`](crate::sound::Sound).
 #[derive(Debug, Clone)]
@@ -22,6 +22,12 @@ pub struct SoundSettings {
 	/// is played multiple times at the exact same point in time,
 	/// resulting in the sound being louder than normal.
 	pub cooldown: Option<f64>,
+	/// The maximum number of instances of this sound that can
+	/// be playing at a time, if any.
+	pub max_instances: Option<usize>,
+	/// What to do when a new instance of this sound is started
+	/// while the maximum number of instances are already playing.
+	pub voice_stealing_policy: VoiceStealingPolicy,
 	/// How long the sound is musically.
 	///
 	/// For example, a recording of a 2-bar drum fill
@@ -37,6 +43,25 @@ pub struct SoundSettings {
 	/// the point an instance should jump back to when it reaches
 	/// the end.
 	pub default_loop_start: Option<f64>,
+	/// How long (in seconds) instances of this sound should
+	/// crossfade the audio before the loop end point with the
+	/// audio before the loop start point by default.
+	///
+	/// This hides clicks caused by loop points that don't line
+	/// up perfectly.
+	pub default_loop_crossfade: f64,
+	/// How instances of this sound calculate frames that fall
+	/// between samples, unless the instance overrides it.
+	pub resampling_quality: ResamplingQuality,
+	/// Whether the sound should be converted to the audio device's
+	/// sample rate when it's added to the
+	/// [`AudioManager`](crate::manager::AudioManager), and if so,
+	/// the resampling quality to use.
+	///
+	/// This is done once on the main thread, which saves the
+	/// cost of interpolating between samples for every instance of
+	/// the sound played at its normal playback rate.
+	pub resample_on_add: Option<ResamplingQuality>,
 	/// The groups this sound belongs to.
 	pub groups: GroupSet,
 }
@@ -71,6 +96,24 @@ impl SoundSettings {
 		}
 	}
 
+	/// Sets the maximum number of instances of this sound
+	/// that can be playing at a time.
+	pub fn max_instances(self, max_instances: usize) -> Self {
+		Self {
+			max_instances: Some(max_instances),
+			..self
+		}
+	}
+
+	/// Sets what to do when a new instance of this sound is started
+	/// while the maximum number of instances are already playing.
+	pub fn voice_stealing_policy(self, voice_stealing_policy: VoiceStealingPolicy) -> Self {
+		Self {
+			voice_stealing_policy,
+			..self
+		}
+	}
+
 	/// Sets the semantic duration of the sound.
 	pub fn semantic_duration(self, semantic_duration: f64) -> Self {
 		Self {
@@ -87,6 +130,34 @@ impl SoundSettings {
 		}
 	}
 
+	/// Sets how long instances of this sound will crossfade
+	/// the audio around the loop points by default.
+	pub fn default_loop_crossfade(self, default_loop_crossfade: f64) -> Self {
+		Self {
+			default_loop_crossfade,
+			..self
+		}
+	}
+
+	/// Sets the resampling quality instances of this sound
+	/// will use by default.
+	pub fn resampling_quality(self, resampling_quality: ResamplingQuality) -> Self {
+		Self {
+			resampling_quality,
+			..self
+		}
+	}
+
+	/// Sets the sound to be converted to the audio device's
+	/// sample rate with the given quality when it's added to
+	/// the [`AudioManager`](crate::manager::AudioManager).
+	pub fn resample_on_add(self, quality: ResamplingQuality) -> Self {
+		Self {
+			resample_on_add: Some(quality),
+			..self
+		}
+	}
+
 	/// Sets the group this sound belongs to.
 	pub fn groups(self, groups: impl Into<GroupSet>) -> Self {
 		Self {
@@ -102,8 +173,13 @@ impl Default for SoundSettings {
 			id: None,
 			default_track: TrackIndex::Main,
 			cooldown: Some(0.0001),
+			max_instances: None,
+			voice_stealing_policy: VoiceStealingPolicy::default(),
 			semantic_duration: None,
 			default_loop_start: None,
+			default_loop_crossfade: 0.0,
+			resampling_quality: ResamplingQuality::default(),
+			resample_on_add: None,
 			groups: GroupSet::new(),
 		}
 	}
@@ -0,0 +1,190 @@
+use std::sync::Arc;
+
+use crate::Frame;
+
+use super::{
+	generator::{Envelope, Generator, Waveform},
+	ResamplingQuality, Sound,
+};
+
+fn create_test_sound() -> Sound {
+	let frames = (0..32).map(|i| Frame::new(i as f32, -(i as f32))).collect();
+	Sound::from_frames(1, frames, Default::default())
+}
+
+#[test]
+fn resampling_preserves_samples_at_whole_positions() {
+	let sound = create_test_sound();
+	for quality in [
+		ResamplingQuality::Nearest,
+		ResamplingQuality::Linear,
+		ResamplingQuality::Hermite,
+		ResamplingQuality::Sinc,
+	]
+	.iter()
+	{
+		let frame = sound.get_resampled_frame(16.0, None, 1.0, Some(*quality));
+		assert!(
+			(frame.left - 16.0).abs() < 0.001 && (frame.right + 16.0).abs() < 0.001,
+			"{:?} resampling should output the original sample at whole sample positions",
+			quality
+		);
+	}
+}
+
+#[test]
+fn sinc_resampling_filters_aliasing_frequencies() {
+	// a signal at the nyquist frequency will alias when
+	// it's played back at double speed
+	let frames = (0..256)
+		.map(|i| Frame::from_mono(if i % 2 == 0 { 1.0 } else { -1.0 }))
+		.collect();
+	let sound = Sound::from_frames(1, frames, Default::default());
+	let hermite = sound.get_resampled_frame(128.0, None, 2.0, Some(ResamplingQuality::Hermite));
+	let sinc = sound.get_resampled_frame(128.0, None, 2.0, Some(ResamplingQuality::Sinc));
+	assert_eq!(hermite, Frame::from_mono(1.0));
+	assert!(sinc.left.abs() < 0.01);
+}
+
+#[test]
+fn resample_changes_sample_rate_and_preserves_duration() {
+	let mut sound = Sound::from_frames(
+		44100,
+		vec![Frame::from_mono(0.5); 44100],
+		Default::default(),
+	);
+	sound.resample(48000, ResamplingQuality::Linear);
+	assert_eq!(sound.sample_rate(), 48000);
+	assert_eq!(sound.frames().len(), 48000);
+	assert!((sound.duration() - 1.0).abs() < 0.001);
+	assert_eq!(sound.frames()[24000], Frame::from_mono(0.5));
+}
+
+#[test]
+fn waveform_summary_levels_cover_the_whole_sound() {
+	let sound = create_test_sound();
+	let summary = sound.waveform_summary(5, 3);
+	assert_eq!(summary.num_frames, 32);
+	assert_eq!(summary.levels.len(), 3);
+	let samples_per_point = summary
+		.levels
+		.iter()
+		.map(|level| level.samples_per_point)
+		.collect::<Vec<_>>();
+	assert_eq!(samples_per_point, vec![5, 10, 20]);
+	let num_points = summary
+		.levels
+		.iter()
+		.map(|level| level.left.len())
+		.collect::<Vec<_>>();
+	assert_eq!(num_points, vec![7, 4, 2]);
+	let last_point = summary.levels[0].left[6];
+	assert_eq!(last_point.min, 30.0);
+	assert_eq!(last_point.max, 31.0);
+	assert!((last_point.rms - ((30.0f32.powi(2) + 31.0f32.powi(2)) / 2.0).sqrt()).abs() < 0.001);
+	let coarsest_right = summary.levels[2].right[1];
+	assert_eq!(coarsest_right.min, -31.0);
+	assert_eq!(coarsest_right.max, -20.0);
+}
+
+#[test]
+fn from_fn_calls_the_function_for_each_frame() {
+	let sound = Sound::from_fn(
+		4,
+		2.0,
+		|time| Frame::new(time as f32, 1.0),
+		Default::default(),
+	);
+	assert_eq!(sound.frames().len(), 8);
+	assert_eq!(sound.duration(), 2.0);
+	assert_eq!(sound.frames()[3], Frame::new(0.75, 1.0));
+}
+
+#[test]
+fn oscillators_produce_the_expected_waveforms() {
+	for (waveform, expected) in [
+		(Waveform::Sine, [0.0, 1.0, 0.0, -1.0]),
+		(Waveform::Square, [1.0, 1.0, -1.0, -1.0]),
+		(Waveform::Saw, [-1.0, -0.5, 0.0, 0.5]),
+		(Waveform::Triangle, [0.0, 1.0, 0.0, -1.0]),
+	]
+	.iter()
+	{
+		let sound = Sound::from_generator(
+			4,
+			1.0,
+			Generator::oscillator(*waveform, 1.0).amplitude(0.5),
+			Default::default(),
+		);
+		for (frame, expected) in sound.frames().iter().zip(expected.iter()) {
+			assert!((frame.left - 0.5 * *expected as f32).abs() < 0.0001);
+		}
+	}
+}
+
+#[test]
+fn envelopes_shape_the_amplitude_of_generated_sounds() {
+	let envelope = Envelope::new(1.0, 1.0, 0.5, 1.0);
+	assert_eq!(envelope.amplitude(0.5, 4.0), 0.5);
+	assert_eq!(envelope.amplitude(1.5, 4.0), 0.75);
+	assert_eq!(envelope.amplitude(2.5, 4.0), 0.5);
+	assert_eq!(envelope.amplitude(3.5, 4.0), 0.25);
+	// a release that starts during the attack begins
+	// from the level the attack reached
+	assert_eq!(envelope.amplitude(1.0, 1.5), 0.25);
+}
+
+#[test]
+fn noise_generators_are_deterministic_and_in_range() {
+	for generator in [Generator::white_noise(), Generator::pink_noise()].iter() {
+		let a = Sound::from_generator(48000, 0.1, generator.seed(1), Default::default());
+		let b = Sound::from_generator(48000, 0.1, generator.seed(1), Default::default());
+		assert_eq!(a.frames(), b.frames());
+		assert!(a
+			.frames()
+			.iter()
+			.all(|frame| frame.left >= -1.0 && frame.left <= 1.0));
+	}
+}
+
+#[test]
+fn slices_play_a_range_of_the_original_sound() {
+	let sound = create_test_sound();
+	let slice = sound.slice(10.0, 20.0, Default::default());
+	assert_ne!(slice.id(), sound.id());
+	assert_eq!(slice.duration(), 10.0);
+	assert_eq!(slice.frames().len(), 10);
+	assert!(Arc::ptr_eq(&slice.frames, &sound.frames));
+	assert_eq!(slice.get_frame_at_position(0.0), Frame::new(10.0, -10.0));
+	assert_eq!(slice.get_frame_at_position(9.0), Frame::new(19.0, -19.0));
+	// slices of slices are relative to the parent slice,
+	// and ranges are clamped to the length of the sound
+	let sub_slice = slice.slice(5.0, 100.0, Default::default());
+	assert_eq!(sub_slice.frames()[0], Frame::new(15.0, -15.0));
+	assert_eq!(sub_slice.frames().len(), 5);
+	// reading past the end of a slice doesn't leak into
+	// the rest of the audio data
+	assert_eq!(sub_slice.get_frame_at_position(5.0), Frame::from_mono(0.0));
+}
+
+#[test]
+fn waveform_summary_picks_the_coarsest_level_with_enough_points() {
+	let sound = create_test_sound();
+	let summary = sound.waveform_summary(5, 3);
+	assert_eq!(
+		summary.level_for_num_points(4).unwrap().samples_per_point,
+		10
+	);
+	assert_eq!(
+		summary.level_for_num_points(5).unwrap().samples_per_point,
+		5
+	);
+	assert_eq!(
+		summary.level_for_num_points(100).unwrap().samples_per_point,
+		5
+	);
+	assert!(sound
+		.waveform_summary(5, 0)
+		.level_for_num_points(1)
+		.is_none());
+}
@@ -0,0 +1,166 @@
+//! Overviews of a sound's audio data for drawing waveforms.
+
+use crate::Frame;
+
+/// The minimum, maximum, and RMS level of a span of samples
+/// in one channel.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(
+	feature = "serde_support",
+	derive(serde::Serialize, serde::Deserialize)
+)]
+pub struct WaveformPoint {
+	/// The lowest sample in the span.
+	pub min: f32,
+	/// The highest sample in the span.
+	pub max: f32,
+	/// The root mean square of the samples in the span.
+	pub rms: f32,
+}
+
+/// One resolution of a [`WaveformSummary`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+	feature = "serde_support",
+	derive(serde::Serialize, serde::Deserialize)
+)]
+pub struct WaveformLevel {
+	/// The number of samples each point covers.
+	///
+	/// The last point of each channel may cover fewer samples
+	/// if the length of the sound isn't a multiple of this.
+	pub samples_per_point: usize,
+	/// The points for the left channel.
+	pub left: Vec<WaveformPoint>,
+	/// The points for the right channel.
+	pub right: Vec<WaveformPoint>,
+}
+
+/// A multi-resolution min/max/RMS overview of a
+/// [`Sound`](super::Sound)'s audio data.
+///
+/// Each level covers twice as many samples per point
+/// as the one before it, so UIs can pick the level that
+/// best matches their zoom level.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+	feature = "serde_support",
+	derive(serde::Serialize, serde::Deserialize)
+)]
+pub struct WaveformSummary {
+	/// The sample rate of the summarized audio data.
+	pub sample_rate: u32,
+	/// The number of frames in the summarized audio data.
+	pub num_frames: usize,
+	/// The levels of the summary, from finest to coarsest.
+	pub levels: Vec<WaveformLevel>,
+}
+
+impl WaveformSummary {
+	pub(crate) fn new(
+		sample_rate: u32,
+		frames: &[Frame],
+		base_resolution: usize,
+		num_levels: usize,
+	) -> Self {
+		let base_resolution = base_resolution.max(1);
+		let mut levels = vec![];
+		if num_levels > 0 {
+			let mut left = vec![];
+			let mut right = vec![];
+			for chunk in frames.chunks(base_resolution) {
+				left.push(Accumulator::from_samples(
+					chunk.iter().map(|frame| frame.left),
+				));
+				right.push(Accumulator::from_samples(
+					chunk.iter().map(|frame| frame.right),
+				));
+			}
+			let mut samples_per_point = base_resolution;
+			loop {
+				levels.push(WaveformLevel {
+					samples_per_point,
+					left: left.iter().map(Accumulator::point).collect(),
+					right: right.iter().map(Accumulator::point).collect(),
+				});
+				if levels.len() >= num_levels {
+					break;
+				}
+				left = Accumulator::merge_pairs(&left);
+				right = Accumulator::merge_pairs(&right);
+				samples_per_point *= 2;
+			}
+		}
+		Self {
+			sample_rate,
+			num_frames: frames.len(),
+			levels,
+		}
+	}
+
+	/// Returns the coarsest level that still has at least
+	/// `num_points` points, or the finest level if none do.
+	pub fn level_for_num_points(&self, num_points: usize) -> Option<&WaveformLevel> {
+		self.levels
+			.iter()
+			.rev()
+			.find(|level| level.left.len() >= num_points)
+			.or_else(|| self.levels.first())
+	}
+}
+
+/// Running statistics for a span of samples, kept
+/// so that spans can be merged without losing precision.
+#[derive(Debug, Copy, Clone)]
+struct Accumulator {
+	min: f32,
+	max: f32,
+	sum_of_squares: f64,
+	num_samples: usize,
+}
+
+impl Accumulator {
+	fn from_samples(samples: impl Iterator<Item = f32>) -> Self {
+		let mut accumulator = Self {
+			min: f32::INFINITY,
+			max: f32::NEG_INFINITY,
+			sum_of_squares: 0.0,
+			num_samples: 0,
+		};
+		for sample in samples {
+			accumulator.min = accumulator.min.min(sample);
+			accumulator.max = accumulator.max.max(sample);
+			accumulator.sum_of_squares += (sample as f64).powi(2);
+			accumulator.num_samples += 1;
+		}
+		accumulator
+	}
+
+	fn merge(self, other: Self) -> Self {
+		Self {
+			min: self.min.min(other.min),
+			max: self.max.max(other.max),
+			sum_of_squares: self.sum_of_squares + other.sum_of_squares,
+			num_samples: self.num_samples + other.num_samples,
+		}
+	}
+
+	fn merge_pairs(accumulators: &[Self]) -> Vec<Self> {
+		accumulators
+			.chunks(2)
+			.map(|pair| match pair {
+				[a, b] => a.merge(*b),
+				[a] => *a,
+				_ => unreachable!(),
+			})
+			.collect()
+	}
+
+	fn point(&self) -> WaveformPoint {
+		WaveformPoint {
+			min: self.min,
+			max: self.max,
+			rms: (self.sum_of_squares / self.num_samples as f64).sqrt() as f32,
+		}
+	}
+}
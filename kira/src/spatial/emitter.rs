@@ -0,0 +1,398 @@
+use crate::parameter::Mapping;
+
+use super::{listener::Listener, scene::SpatialScene, smoothing_amount, ListenerPolicy, Vec3};
+
+/// The fastest the listener or an emitter can move towards or
+/// away from the other, as a fraction of the speed of sound
+/// (divided by the Doppler factor).
+///
+/// This keeps the Doppler effect from producing infinite or
+/// negative playback rates.
+const MAX_DOPPLER_SPEED: f64 = 0.9;
+
+/// How the volume of an emitter falls off as it gets
+/// further from the listener.
+#[derive(Debug, Copy, Clone, Default)]
+#[cfg_attr(
+	feature = "serde_support",
+	derive(serde::Serialize, serde::Deserialize)
+)]
+pub enum Rolloff {
+	/// The volume falls off linearly from 1 at the minimum
+	/// distance to 0 at the maximum distance.
+	Linear,
+	/// The volume is inversely proportional to the distance,
+	/// starting at 1 at the minimum distance.
+	///
+	/// This is how sound behaves in the real world.
+	#[default]
+	Inverse,
+	/// The distance (in the same units as positions) is mapped
+	/// to a volume using a [`Mapping`].
+	///
+	/// Volumes below 0 are treated as 0.
+	Custom(Mapping),
+}
+
+impl Rolloff {
+	/// Returns the volume of an emitter at a distance
+	/// from the listener.
+	fn volume(self, distance: f64, min_distance: f64, max_distance: f64) -> f64 {
+		let distance = distance.max(min_distance).min(max_distance);
+		let volume = match self {
+			Rolloff::Linear => {
+				if max_distance <= min_distance {
+					1.0
+				} else {
+					1.0 - (distance - min_distance) / (max_distance - min_distance)
+				}
+			}
+			Rolloff::Inverse => {
+				if distance <= 0.0 {
+					1.0
+				} else {
+					min_distance / distance
+				}
+			}
+			Rolloff::Custom(mapping) => mapping.map(distance),
+		};
+		volume.max(0.0)
+	}
+}
+
+/// How an emitter's direction from the listener is turned
+/// into stereo audio.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+#[cfg_attr(
+	feature = "serde_support",
+	derive(serde::Serialize, serde::Deserialize)
+)]
+pub enum Panner {
+	/// Pans the sound between the left and right speakers.
+	#[default]
+	Stereo,
+	/// Filters the sound with head-related impulse responses
+	/// from the audio manager's [`HrtfDataset`](super::hrtf::HrtfDataset).
+	///
+	/// This falls back to stereo panning if there's no dataset
+	/// or too many instances are already using HRTF panning.
+	Hrtf,
+}
+
+/// Settings for an emitter, which positions an instance
+/// in 3D space.
+#[derive(Debug, Copy, Clone)]
+#[cfg_attr(
+	feature = "serde_support",
+	derive(serde::Serialize, serde::Deserialize),
+	serde(default)
+)]
+pub struct EmitterSettings {
+	/// The position of the emitter.
+	pub position: Vec3,
+	/// The velocity of the emitter (in units per second).
+	///
+	/// This is only used for the Doppler effect.
+	pub velocity: Vec3,
+	/// How strong the Doppler effect is for this emitter.
+	///
+	/// `1.0` is a realistic Doppler effect, `0.0` disables it,
+	/// and higher values exaggerate it.
+	pub doppler_factor: f64,
+	/// How the volume of the emitter falls off with distance.
+	pub rolloff: Rolloff,
+	/// How the emitter's direction from the listener is turned
+	/// into stereo audio.
+	pub panner: Panner,
+	/// The distance the volume of the emitter starts
+	/// falling off at.
+	pub min_distance: f64,
+	/// The distance the volume of the emitter stops
+	/// falling off at.
+	///
+	/// With [`Rolloff::Linear`], the emitter is silent at
+	/// this distance and beyond.
+	pub max_distance: f64,
+	/// How long it takes for the emitter to move most of the way
+	/// (about 63%) to a new position, or to change most of the
+	/// way to a new velocity (in seconds).
+	///
+	/// This hides the jumps between position updates
+	/// from the main thread.
+	pub smoothing: f64,
+}
+
+impl EmitterSettings {
+	/// Creates a new `EmitterSettings` with the default settings.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Sets the position of the emitter.
+	pub fn position(self, position: impl Into<Vec3>) -> Self {
+		Self {
+			position: position.into(),
+			..self
+		}
+	}
+
+	/// Sets the velocity of the emitter (in units per second).
+	pub fn velocity(self, velocity: impl Into<Vec3>) -> Self {
+		Self {
+			velocity: velocity.into(),
+			..self
+		}
+	}
+
+	/// Sets how strong the Doppler effect is for this emitter.
+	pub fn doppler_factor(self, doppler_factor: f64) -> Self {
+		Self {
+			doppler_factor,
+			..self
+		}
+	}
+
+	/// Sets how the volume of the emitter falls off with distance.
+	pub fn rolloff(self, rolloff: Rolloff) -> Self {
+		Self { rolloff, ..self }
+	}
+
+	/// Sets how the emitter's direction from the listener is
+	/// turned into stereo audio.
+	pub fn panner(self, panner: Panner) -> Self {
+		Self { panner, ..self }
+	}
+
+	/// Sets the distance the volume of the emitter starts
+	/// falling off at.
+	pub fn min_distance(self, min_distance: f64) -> Self {
+		Self {
+			min_distance,
+			..self
+		}
+	}
+
+	/// Sets the distance the volume of the emitter stops
+	/// falling off at.
+	pub fn max_distance(self, max_distance: f64) -> Self {
+		Self {
+			max_distance,
+			..self
+		}
+	}
+
+	/// Sets how long it takes for the emitter to move most
+	/// of the way to a new position (in seconds).
+	pub fn smoothing(self, smoothing: f64) -> Self {
+		Self { smoothing, ..self }
+	}
+}
+
+impl Default for EmitterSettings {
+	fn default() -> Self {
+		Self {
+			position: Vec3::ZERO,
+			velocity: Vec3::ZERO,
+			doppler_factor: 1.0,
+			rolloff: Rolloff::default(),
+			panner: Panner::default(),
+			min_distance: 1.0,
+			max_distance: 100.0,
+			smoothing: 0.05,
+		}
+	}
+}
+
+/// How an emitter sounds from one listener's point of view.
+#[derive(Debug, Copy, Clone)]
+struct Spatialization {
+	distance: f64,
+	volume: f64,
+	panning: f64,
+	direction: Vec3,
+	doppler_playback_rate: f64,
+}
+
+/// The state of an instance's emitter on the audio thread.
+#[derive(Debug, Clone)]
+pub(crate) struct Emitter {
+	settings: EmitterSettings,
+	position: Vec3,
+	velocity: Vec3,
+	volume: f64,
+	panning: f64,
+	direction: Vec3,
+	doppler_playback_rate: f64,
+}
+
+impl Emitter {
+	pub fn new(settings: EmitterSettings) -> Self {
+		Self {
+			settings,
+			position: settings.position,
+			velocity: settings.velocity,
+			volume: 1.0,
+			panning: 0.5,
+			direction: Vec3::ZERO,
+			doppler_playback_rate: 1.0,
+		}
+	}
+
+	pub fn set_position(&mut self, position: Vec3) {
+		self.settings.position = position;
+	}
+
+	pub fn set_velocity(&mut self, velocity: Vec3) {
+		self.settings.velocity = velocity;
+	}
+
+	/// The volume of the emitter relative to the listener.
+	pub fn volume(&self) -> f64 {
+		self.volume
+	}
+
+	/// The panning of the emitter relative to the listener,
+	/// where 0 is hard left and 1 is hard right.
+	pub fn panning(&self) -> f64 {
+		self.panning
+	}
+
+	pub fn panner(&self) -> Panner {
+		self.settings.panner
+	}
+
+	/// The direction of the emitter from the listener's
+	/// point of view.
+	pub fn direction(&self) -> Vec3 {
+		self.direction
+	}
+
+	/// The amount the playback rate of the instance should be
+	/// multiplied by to simulate the Doppler effect.
+	pub fn doppler_playback_rate(&self) -> f64 {
+		self.doppler_playback_rate
+	}
+
+	pub fn update(&mut self, dt: f64, scene: &SpatialScene) {
+		let smoothing_amount = smoothing_amount(dt, self.settings.smoothing);
+		self.position = self.position.lerp(self.settings.position, smoothing_amount);
+		self.velocity = self.velocity.lerp(self.settings.velocity, smoothing_amount);
+		let speed_of_sound = scene.speed_of_sound();
+		// spatialize the emitter once for each listener, keeping track
+		// of the nearest listener and the sums needed to blend them
+		let mut nearest: Option<Spatialization> = None;
+		let mut total_volume = 0.0;
+		let mut weighted_sum = Spatialization {
+			distance: 0.0,
+			volume: 0.0,
+			panning: 0.0,
+			direction: Vec3::ZERO,
+			doppler_playback_rate: 0.0,
+		};
+		for listener in scene.listeners() {
+			let spatialization = self.spatialize(listener, speed_of_sound);
+			let is_nearest = match nearest {
+				Some(nearest) => spatialization.distance.total_cmp(&nearest.distance).is_lt(),
+				None => true,
+			};
+			if is_nearest {
+				nearest = Some(spatialization);
+			}
+			let weight = spatialization.volume;
+			total_volume += weight;
+			weighted_sum.distance += spatialization.distance * weight;
+			weighted_sum.volume += spatialization.volume * weight;
+			weighted_sum.panning += spatialization.panning * weight;
+			weighted_sum.direction += spatialization.direction * weight;
+			weighted_sum.doppler_playback_rate += spatialization.doppler_playback_rate * weight;
+		}
+		let nearest = match nearest {
+			Some(nearest) => nearest,
+			None => return,
+		};
+		let spatialization = match scene.listener_policy() {
+			ListenerPolicy::Nearest => nearest,
+			ListenerPolicy::NormalizedSum => {
+				Self::blend(weighted_sum, total_volume).unwrap_or(Spatialization {
+					volume: 0.0,
+					..nearest
+				})
+			}
+		};
+		self.volume = spatialization.volume;
+		self.panning = spatialization.panning;
+		self.direction = spatialization.direction;
+		self.doppler_playback_rate = spatialization.doppler_playback_rate;
+	}
+
+	/// Calculates how the emitter sounds to a single listener.
+	fn spatialize(&self, listener: &Listener, speed_of_sound: f64) -> Spatialization {
+		let offset = self.position - listener.position();
+		let distance = offset.length();
+		Spatialization {
+			distance,
+			volume: self.settings.rolloff.volume(
+				distance,
+				self.settings.min_distance,
+				self.settings.max_distance,
+			),
+			panning: 0.5 + 0.5 * offset.normalized().dot(listener.right()),
+			direction: listener.to_local(offset).normalized(),
+			doppler_playback_rate: self.calculate_doppler_playback_rate(
+				offset,
+				listener,
+				speed_of_sound,
+			),
+		}
+	}
+
+	/// Blends how the emitter sounds to every listener, weighting
+	/// each listener by the emitter's volume for that listener.
+	///
+	/// `weighted_sum` is the sum of each listener's spatialization
+	/// multiplied by its weight, and `total_volume` is the sum of
+	/// the weights.
+	///
+	/// Returns `None` if the emitter is silent for every listener.
+	fn blend(weighted_sum: Spatialization, total_volume: f64) -> Option<Spatialization> {
+		if total_volume <= 0.0 {
+			return None;
+		}
+		Some(Spatialization {
+			distance: weighted_sum.distance / total_volume,
+			volume: weighted_sum.volume / total_volume,
+			panning: weighted_sum.panning / total_volume,
+			direction: weighted_sum.direction.normalized(),
+			doppler_playback_rate: weighted_sum.doppler_playback_rate / total_volume,
+		})
+	}
+
+	/// Calculates the Doppler shift from the speeds the listener
+	/// and emitter are moving towards each other.
+	fn calculate_doppler_playback_rate(
+		&self,
+		offset: Vec3,
+		listener: &Listener,
+		speed_of_sound: f64,
+	) -> f64 {
+		let doppler_factor = self.settings.doppler_factor;
+		if doppler_factor <= 0.0 || speed_of_sound <= 0.0 {
+			return 1.0;
+		}
+		let emitter_to_listener = (-offset).normalized();
+		if emitter_to_listener == Vec3::ZERO {
+			return 1.0;
+		}
+		let max_speed = MAX_DOPPLER_SPEED * speed_of_sound / doppler_factor;
+		let listener_speed = listener
+			.velocity()
+			.dot(emitter_to_listener)
+			.clamp(-max_speed, max_speed);
+		let emitter_speed = self
+			.velocity
+			.dot(emitter_to_listener)
+			.clamp(-max_speed, max_speed);
+		(speed_of_sound - doppler_factor * listener_speed)
+			/ (speed_of_sound - doppler_factor * emitter_speed)
+	}
+}
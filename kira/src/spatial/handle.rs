@@ -0,0 +1,55 @@
+//! An interface for controlling listeners.
+
+use crate::command::{
+	producer::{CommandError, CommandProducer},
+	SpatialCommand,
+};
+
+use super::{ListenerId, Vec3};
+
+/// Allows you to control a listener.
+pub struct ListenerHandle {
+	id: ListenerId,
+	command_producer: CommandProducer,
+}
+
+impl ListenerHandle {
+	pub(crate) fn new(id: ListenerId, command_producer: CommandProducer) -> Self {
+		Self {
+			id,
+			command_producer,
+		}
+	}
+
+	/// Returns the ID of the listener.
+	pub fn id(&self) -> ListenerId {
+		self.id
+	}
+
+	/// Sets the position of the listener.
+	pub fn set_position(&mut self, position: impl Into<Vec3>) -> Result<(), CommandError> {
+		self.command_producer
+			.push(SpatialCommand::SetListenerPosition(self.id, position.into()).into())
+	}
+
+	/// Sets the velocity of the listener (in units per second).
+	///
+	/// This is only used for the Doppler effect.
+	pub fn set_velocity(&mut self, velocity: impl Into<Vec3>) -> Result<(), CommandError> {
+		self.command_producer
+			.push(SpatialCommand::SetListenerVelocity(self.id, velocity.into()).into())
+	}
+
+	/// Sets the direction the listener is facing and the direction
+	/// of the top of the listener's head.
+	///
+	/// The two directions should be perpendicular to each other.
+	pub fn set_orientation(
+		&mut self,
+		forward: impl Into<Vec3>,
+		up: impl Into<Vec3>,
+	) -> Result<(), CommandError> {
+		self.command_producer
+			.push(SpatialCommand::SetListenerOrientation(self.id, forward.into(), up.into()).into())
+	}
+}
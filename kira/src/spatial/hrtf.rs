@@ -0,0 +1,301 @@
+//! Head-related impulse responses for rendering spatial audio
+//! for headphones.
+
+use std::path::PathBuf;
+#[cfg(any(feature = "mp3", feature = "ogg", feature = "flac", feature = "wav"))]
+use std::{fs::read_dir, path::Path};
+
+use thiserror::Error;
+
+#[cfg(any(feature = "mp3", feature = "ogg", feature = "flac", feature = "wav"))]
+use crate::sound::{error::SoundFromFileError, Sound};
+use crate::{sound::ResamplingQuality, Frame};
+
+use super::Vec3;
+
+/// The maximum length of an impulse response in an
+/// [`HrtfDataset`] (in samples).
+///
+/// Longer impulse responses are cut off.
+pub const MAX_HRTF_LENGTH: usize = 256;
+
+/// How often the HRTF panner looks up a new impulse response
+/// for an instance's direction (in samples).
+///
+/// The panner crossfades between the old and new impulse
+/// responses over this many samples.
+const HRTF_UPDATE_INTERVAL: usize = 64;
+
+/// The maximum number of measured directions that are
+/// blended together to get the impulse response for a
+/// direction between them.
+const MAX_INTERPOLATED_MEASUREMENTS: usize = 3;
+
+/// Something that can go wrong when loading an [`HrtfDataset`]
+/// from a directory.
+#[derive(Debug, Error)]
+pub enum HrtfDatasetFromDirError {
+	/// An error occurred when reading the directory.
+	#[error("{0}")]
+	IoError(#[from] std::io::Error),
+
+	/// An error occurred when loading an impulse response.
+	#[cfg(any(feature = "mp3", feature = "ogg", feature = "flac", feature = "wav"))]
+	#[error("{0}")]
+	SoundFromFileError(#[from] SoundFromFileError),
+
+	/// A file name isn't in the `<azimuth>_<elevation>` format.
+	#[error("The file name of {0} should be in the format <azimuth>_<elevation>")]
+	InvalidFileName(PathBuf),
+
+	/// The impulse responses don't all have the same sample rate.
+	#[error("All of the impulse responses should have the same sample rate")]
+	MismatchedSampleRates,
+
+	/// The directory doesn't have any impulse responses.
+	#[error("The directory does not contain any impulse responses")]
+	NoImpulseResponses,
+}
+
+/// A pair of head-related impulse responses measured
+/// from one direction.
+#[derive(Debug, Clone)]
+pub struct HrtfMeasurement {
+	/// The angle of the direction in the horizontal plane (in degrees).
+	///
+	/// 0 is straight ahead, and positive angles are to the right.
+	pub azimuth: f64,
+	/// The angle of the direction above the horizontal plane
+	/// (in degrees).
+	pub elevation: f64,
+	/// The impulse response for the left ear.
+	pub left: Vec<f32>,
+	/// The impulse response for the right ear.
+	pub right: Vec<f32>,
+}
+
+type ImpulseResponse = [Frame; MAX_HRTF_LENGTH];
+
+/// A set of head-related impulse responses measured from
+/// different directions around a listener.
+///
+/// Datasets can be loaded from a directory of stereo audio files
+/// with [`HrtfDataset::from_dir`], or created from measurements
+/// loaded some other way (for example, from a SOFA file) with
+/// [`HrtfDataset::new`].
+#[derive(Debug, Clone)]
+pub struct HrtfDataset {
+	sample_rate: u32,
+	length: usize,
+	directions: Vec<Vec3>,
+	impulse_responses: Vec<ImpulseResponse>,
+}
+
+impl HrtfDataset {
+	/// Creates a dataset from measurements with the given sample rate.
+	pub fn new(sample_rate: u32, measurements: Vec<HrtfMeasurement>) -> Self {
+		let mut length = 0;
+		let mut directions = Vec::with_capacity(measurements.len());
+		let mut impulse_responses = Vec::with_capacity(measurements.len());
+		for measurement in measurements {
+			let azimuth = measurement.azimuth.to_radians();
+			let elevation = measurement.elevation.to_radians();
+			directions.push(Vec3::new(
+				elevation.cos() * azimuth.sin(),
+				elevation.sin(),
+				elevation.cos() * azimuth.cos(),
+			));
+			let mut impulse_response = [Frame::from_mono(0.0); MAX_HRTF_LENGTH];
+			for (i, frame) in impulse_response.iter_mut().enumerate() {
+				frame.left = measurement.left.get(i).copied().unwrap_or(0.0);
+				frame.right = measurement.right.get(i).copied().unwrap_or(0.0);
+			}
+			length = length
+				.max(measurement.left.len())
+				.max(measurement.right.len());
+			impulse_responses.push(impulse_response);
+		}
+		Self {
+			sample_rate,
+			length: length.min(MAX_HRTF_LENGTH),
+			directions,
+			impulse_responses,
+		}
+	}
+
+	/// Loads a dataset from a directory of stereo audio files.
+	///
+	/// Each file should contain the impulse response for the left ear
+	/// in the left channel and the impulse response for the right ear
+	/// in the right channel. File names should be in the format
+	/// `<azimuth>_<elevation>` (in degrees), so `-30_15.wav` is the
+	/// measurement from 30 degrees to the left and 15 degrees up.
+	#[cfg(any(feature = "mp3", feature = "ogg", feature = "flac", feature = "wav"))]
+	pub fn from_dir(path: impl AsRef<Path>) -> Result<Self, HrtfDatasetFromDirError> {
+		let mut sample_rate = None;
+		let mut measurements = vec![];
+		for entry in read_dir(path)? {
+			let path = entry?.path();
+			if !path.is_file() {
+				continue;
+			}
+			let (azimuth, elevation) = path
+				.file_stem()
+				.and_then(|stem| stem.to_str())
+				.and_then(|stem| {
+					let mut parts = stem.splitn(2, '_');
+					let azimuth = parts.next()?.parse().ok()?;
+					let elevation = parts.next()?.parse().ok()?;
+					Some((azimuth, elevation))
+				})
+				.ok_or_else(|| HrtfDatasetFromDirError::InvalidFileName(path.clone()))?;
+			let sound = Sound::from_file(&path, Default::default())?;
+			if *sample_rate.get_or_insert(sound.sample_rate()) != sound.sample_rate() {
+				return Err(HrtfDatasetFromDirError::MismatchedSampleRates);
+			}
+			measurements.push(HrtfMeasurement {
+				azimuth,
+				elevation,
+				left: sound.frames().iter().map(|frame| frame.left).collect(),
+				right: sound.frames().iter().map(|frame| frame.right).collect(),
+			});
+		}
+		match sample_rate {
+			Some(sample_rate) => Ok(Self::new(sample_rate, measurements)),
+			None => Err(HrtfDatasetFromDirError::NoImpulseResponses),
+		}
+	}
+
+	/// Returns the sample rate of the impulse responses.
+	pub fn sample_rate(&self) -> u32 {
+		self.sample_rate
+	}
+
+	/// Returns the number of measured directions in the dataset.
+	pub fn num_measurements(&self) -> usize {
+		self.directions.len()
+	}
+
+	/// Converts the impulse responses to a different sample rate.
+	pub(crate) fn resample(&mut self, sample_rate: u32) {
+		if sample_rate == self.sample_rate {
+			return;
+		}
+		let step = self.sample_rate as f64 / sample_rate as f64;
+		// an impulse response with more samples lets more energy
+		// through, so scale it to keep the same frequency response
+		let gain = step as f32;
+		let length = ((self.length as f64 / step).ceil() as usize).min(MAX_HRTF_LENGTH);
+		for impulse_response in &mut self.impulse_responses {
+			let original = impulse_response[..self.length].to_vec();
+			for (i, frame) in impulse_response.iter_mut().enumerate() {
+				*frame = if i < length {
+					ResamplingQuality::Sinc.resample(&original, i as f64 * step, step) * gain
+				} else {
+					Frame::from_mono(0.0)
+				};
+			}
+		}
+		self.sample_rate = sample_rate;
+		self.length = length;
+	}
+
+	/// Blends the impulse responses of the measured directions
+	/// closest to a direction.
+	fn interpolate(&self, direction: Vec3, out: &mut ImpulseResponse) {
+		let direction = direction.normalized();
+		// find the closest measurements by their angle from the direction
+		let mut closest: [Option<(usize, f64)>; MAX_INTERPOLATED_MEASUREMENTS] =
+			[None; MAX_INTERPOLATED_MEASUREMENTS];
+		for (index, measured_direction) in self.directions.iter().enumerate() {
+			let angle = measured_direction.dot(direction).clamp(-1.0, 1.0).acos();
+			for i in 0..MAX_INTERPOLATED_MEASUREMENTS {
+				match closest[i] {
+					Some((_, closest_angle)) if closest_angle <= angle => {}
+					_ => {
+						closest[i..].rotate_right(1);
+						closest[i] = Some((index, angle));
+						break;
+					}
+				}
+			}
+		}
+		for frame in out.iter_mut() {
+			*frame = Frame::from_mono(0.0);
+		}
+		let (nearest_index, nearest_angle) = match closest[0] {
+			Some(nearest) => nearest,
+			None => return,
+		};
+		if nearest_angle < f64::EPSILON {
+			out.copy_from_slice(&self.impulse_responses[nearest_index]);
+			return;
+		}
+		// inverse distance weighting
+		let total_weight: f64 = closest.iter().flatten().map(|(_, angle)| 1.0 / angle).sum();
+		for (index, angle) in closest.iter().flatten() {
+			let weight = (1.0 / angle / total_weight) as f32;
+			for (frame, measured_frame) in out[..self.length]
+				.iter_mut()
+				.zip(self.impulse_responses[*index].iter())
+			{
+				*frame += *measured_frame * weight;
+			}
+		}
+	}
+}
+
+/// The state of the HRTF panner for an instance.
+#[derive(Debug, Clone)]
+pub(crate) struct HrtfState {
+	input: [f32; MAX_HRTF_LENGTH],
+	input_index: usize,
+	previous_impulse_response: ImpulseResponse,
+	impulse_response: ImpulseResponse,
+	frames_until_update: usize,
+	started: bool,
+}
+
+impl HrtfState {
+	pub fn new() -> Self {
+		Self {
+			input: [0.0; MAX_HRTF_LENGTH],
+			input_index: 0,
+			previous_impulse_response: [Frame::from_mono(0.0); MAX_HRTF_LENGTH],
+			impulse_response: [Frame::from_mono(0.0); MAX_HRTF_LENGTH],
+			frames_until_update: 0,
+			started: false,
+		}
+	}
+
+	fn convolve(&self, impulse_response: &ImpulseResponse, length: usize) -> Frame {
+		let mut out = Frame::from_mono(0.0);
+		for (i, frame) in impulse_response[..length].iter().enumerate() {
+			let input_index = (self.input_index + MAX_HRTF_LENGTH - i) % MAX_HRTF_LENGTH;
+			out += *frame * self.input[input_index];
+		}
+		out
+	}
+
+	/// Renders a mono input sample coming from a direction
+	/// (relative to the listener) to stereo.
+	pub fn process(&mut self, dataset: &HrtfDataset, direction: Vec3, input: f32) -> Frame {
+		if self.frames_until_update == 0 {
+			self.previous_impulse_response = self.impulse_response;
+			dataset.interpolate(direction, &mut self.impulse_response);
+			// don't fade in from silence when the panner first starts
+			if !self.started {
+				self.previous_impulse_response = self.impulse_response;
+				self.started = true;
+			}
+			self.frames_until_update = HRTF_UPDATE_INTERVAL;
+		}
+		self.input_index = (self.input_index + 1) % MAX_HRTF_LENGTH;
+		self.input[self.input_index] = input;
+		let previous = self.convolve(&self.previous_impulse_response, dataset.length);
+		let current = self.convolve(&self.impulse_response, dataset.length);
+		let progress = 1.0 - self.frames_until_update as f32 / HRTF_UPDATE_INTERVAL as f32;
+		self.frames_until_update -= 1;
+		previous + (current - previous) * progress
+	}
+}
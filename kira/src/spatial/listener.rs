@@ -0,0 +1,157 @@
+use super::{smoothing_amount, ListenerId, Vec3};
+
+/// How an emitter's volume, panning, and Doppler shift are
+/// combined when there's more than one listener.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+#[cfg_attr(
+	feature = "serde_support",
+	derive(serde::Serialize, serde::Deserialize)
+)]
+pub enum ListenerPolicy {
+	/// Only the listener closest to the emitter is used.
+	#[default]
+	Nearest,
+	/// The results for every listener are blended together,
+	/// weighted by how loud the emitter is for each listener.
+	///
+	/// The weights are normalized so they add up to 1, so an
+	/// emitter is never louder than it is for the listener
+	/// it's loudest for.
+	NormalizedSum,
+}
+
+/// Settings for a listener.
+#[derive(Debug, Copy, Clone)]
+#[cfg_attr(
+	feature = "serde_support",
+	derive(serde::Serialize, serde::Deserialize),
+	serde(default)
+)]
+pub struct ListenerSettings {
+	/// The unique identifier for the listener.
+	pub id: Option<ListenerId>,
+	/// The initial position of the listener.
+	pub position: Vec3,
+	/// The initial direction the listener is facing.
+	pub forward: Vec3,
+	/// The initial direction of the top of the listener's head.
+	pub up: Vec3,
+}
+
+impl ListenerSettings {
+	/// Creates a new `ListenerSettings` with the default settings.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Sets the unique identifier for the listener.
+	pub fn id(self, id: impl Into<ListenerId>) -> Self {
+		Self {
+			id: Some(id.into()),
+			..self
+		}
+	}
+
+	/// Sets the initial position of the listener.
+	pub fn position(self, position: impl Into<Vec3>) -> Self {
+		Self {
+			position: position.into(),
+			..self
+		}
+	}
+
+	/// Sets the initial direction the listener is facing and
+	/// the initial direction of the top of the listener's head.
+	pub fn orientation(self, forward: impl Into<Vec3>, up: impl Into<Vec3>) -> Self {
+		Self {
+			forward: forward.into(),
+			up: up.into(),
+			..self
+		}
+	}
+}
+
+impl Default for ListenerSettings {
+	fn default() -> Self {
+		Self {
+			id: None,
+			position: Vec3::ZERO,
+			forward: Vec3::new(0.0, 0.0, -1.0),
+			up: Vec3::new(0.0, 1.0, 0.0),
+		}
+	}
+}
+
+/// A listener on the audio thread.
+#[derive(Debug, Clone)]
+pub(crate) struct Listener {
+	position: Vec3,
+	target_position: Vec3,
+	velocity: Vec3,
+	target_velocity: Vec3,
+	forward: Vec3,
+	target_forward: Vec3,
+	up: Vec3,
+	target_up: Vec3,
+	smoothing: f64,
+}
+
+impl Listener {
+	pub fn new(settings: ListenerSettings, smoothing: f64) -> Self {
+		Self {
+			position: settings.position,
+			target_position: settings.position,
+			velocity: Vec3::ZERO,
+			target_velocity: Vec3::ZERO,
+			forward: settings.forward,
+			target_forward: settings.forward,
+			up: settings.up,
+			target_up: settings.up,
+			smoothing,
+		}
+	}
+
+	pub fn position(&self) -> Vec3 {
+		self.position
+	}
+
+	pub fn velocity(&self) -> Vec3 {
+		self.velocity
+	}
+
+	/// Returns the direction pointing out of the listener's right ear.
+	pub fn right(&self) -> Vec3 {
+		self.forward.cross(self.up).normalized()
+	}
+
+	/// Converts an offset from the listener to the listener's
+	/// point of view, where positive x is to the right, positive y
+	/// is up, and positive z is forward.
+	pub fn to_local(&self, offset: Vec3) -> Vec3 {
+		let forward = self.forward.normalized();
+		let right = self.right();
+		let up = right.cross(forward).normalized();
+		Vec3::new(offset.dot(right), offset.dot(up), offset.dot(forward))
+	}
+
+	pub fn set_position(&mut self, position: Vec3) {
+		self.target_position = position;
+	}
+
+	pub fn set_velocity(&mut self, velocity: Vec3) {
+		self.target_velocity = velocity;
+	}
+
+	pub fn set_orientation(&mut self, forward: Vec3, up: Vec3) {
+		self.target_forward = forward;
+		self.target_up = up;
+	}
+
+	pub fn update(&mut self, dt: f64) {
+		let amount = smoothing_amount(dt, self.smoothing);
+		self.position = self.position.lerp(self.target_position, amount);
+		self.velocity = self.velocity.lerp(self.target_velocity, amount);
+		self.forward = self.forward.lerp(self.target_forward, amount);
+		self.up = self.up.lerp(self.target_up, amount);
+	}
+}
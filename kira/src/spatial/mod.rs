@@ -0,0 +1,135 @@
+//! Positions instances in 3D space relative to a listener.
+//!
+//! An instance with an [emitter](EmitterSettings) gets its volume
+//! from its distance to the listener and its panning from its
+//! direction relative to the listener. Positions are meant
+//! to be updated from the main thread once per game frame;
+//! the audio thread smooths out the jumps between updates.
+//!
+//! By default, the listener is at the origin facing the
+//! negative z direction with the positive y direction up,
+//! so the positive x direction is to the listener's right.
+//!
+//! ## Multiple listeners
+//!
+//! Besides the main listener, more listeners can be added with
+//! [`AudioManager::add_listener`](crate::manager::AudioManager::add_listener),
+//! for example for split-screen games. Emitters are spatialized
+//! relative to every listener, and the results are combined according
+//! to [`AudioManagerSettings::listener_policy`](crate::manager::AudioManagerSettings::listener_policy).
+//!
+//! ## Occlusion and obstruction
+//!
+//! Instances can be muffled by walls and other objects between
+//! them and the listener. Kira doesn't know about the game's
+//! geometry, so the game decides how occluded and obstructed
+//! each instance is (usually with raycasts) and sets the values with
+//! [`InstanceHandle::set_occlusion`](crate::instance::handle::InstanceHandle::set_occlusion)
+//! and [`InstanceHandle::set_obstruction`](crate::instance::handle::InstanceHandle::set_obstruction).
+//! [`OcclusionSettings`] controls how those values affect the volume
+//! and the cutoff frequency of a low-pass filter. Occlusion and
+//! obstruction work for instances with or without emitters.
+//!
+//! ## HRTF panning
+//!
+//! Stereo panning can only place sounds to the left or right.
+//! For listeners wearing headphones, emitters can use
+//! [`Panner::Hrtf`] instead, which filters the sound with
+//! head-related impulse responses so it can also sound like it's
+//! coming from above, below, or behind the listener. This needs
+//! an [`HrtfDataset`](hrtf::HrtfDataset) to be set with
+//! [`AudioManager::set_hrtf_dataset`](crate::manager::AudioManager::set_hrtf_dataset).
+//! HRTF panning is much more expensive than stereo panning, so
+//! only a limited number of instances can use it at a time
+//! (see [`AudioManagerSettings::num_hrtf_instances`](crate::manager::AudioManagerSettings::num_hrtf_instances)).
+//! Other instances fall back to stereo panning.
+//!
+//! ## Doppler effect
+//!
+//! If the listener or an emitter has a velocity, the instance's
+//! playback rate is shifted by how fast they're moving towards
+//! or away from each other, on top of the instance's own playback
+//! rate. Velocities aren't used to move anything, so they should
+//! be set alongside positions.
+//!
+//! ## Examples
+//!
+//! ```no_run
+//! # use kira::{instance::InstanceSettings, manager::AudioManager, sound::Sound};
+//! use kira::spatial::{EmitterSettings, Rolloff};
+//! #
+//! # let mut audio_manager = AudioManager::new(Default::default())?;
+//! # let mut sound = audio_manager.add_sound(Sound::from_file("engine.ogg", Default::default())?)?;
+//! let emitter = EmitterSettings::new()
+//!     .position([10.0, 0.0, 0.0])
+//!     .rolloff(Rolloff::Linear)
+//!     .max_distance(50.0);
+//! let mut instance = sound.play(InstanceSettings::new().emitter(emitter))?;
+//! // every game frame
+//! audio_manager.listener().set_position([0.0, 0.0, 5.0])?;
+//! instance.set_emitter_position([12.0, 0.0, 0.0])?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+mod emitter;
+pub mod handle;
+pub mod hrtf;
+mod listener;
+mod occlusion;
+mod scene;
+#[cfg(test)]
+mod tests;
+mod vec3;
+
+pub(crate) use emitter::Emitter;
+pub use emitter::{EmitterSettings, Panner, Rolloff};
+pub(crate) use listener::Listener;
+pub use listener::{ListenerPolicy, ListenerSettings};
+pub(crate) use occlusion::Occlusion;
+pub use occlusion::OcclusionSettings;
+pub(crate) use scene::SpatialScene;
+pub use vec3::Vec3;
+
+use handle::ListenerHandle;
+use uuid::Uuid;
+
+/// A unique identifier for a listener.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(
+	feature = "serde_support",
+	derive(serde::Serialize, serde::Deserialize),
+	serde(transparent)
+)]
+pub struct ListenerId {
+	uuid: Uuid,
+}
+
+impl ListenerId {
+	pub(crate) fn new() -> Self {
+		Self {
+			uuid: Uuid::new_v4(),
+		}
+	}
+
+	/// Returns the ID of the main listener, which
+	/// always exists.
+	pub(crate) fn main() -> Self {
+		Self { uuid: Uuid::nil() }
+	}
+}
+
+impl From<&ListenerHandle> for ListenerId {
+	fn from(handle: &ListenerHandle) -> Self {
+		handle.id()
+	}
+}
+
+/// Returns how far a smoothed value should move towards
+/// its target value in one frame.
+fn smoothing_amount(dt: f64, smoothing: f64) -> f64 {
+	if smoothing <= 0.0 {
+		1.0
+	} else {
+		1.0 - (-dt / smoothing).exp()
+	}
+}
@@ -0,0 +1,218 @@
+use crate::{
+	frame::Frame,
+	mixer::effect::{
+		filter::{Filter, FilterSettings},
+		Effect,
+	},
+	parameter::{Mapping, Parameters},
+};
+
+use super::smoothing_amount;
+
+/// How close the occlusion and obstruction have to be to 0
+/// for the low-pass filter to be turned off.
+const FILTER_BYPASS_THRESHOLD: f64 = 0.0001;
+
+/// Settings for how walls and other objects between
+/// an instance and the listener affect the sound.
+///
+/// Occlusion and obstruction are values from 0 to 1 that
+/// the game can set based on its own geometry queries.
+/// Occlusion means the sound is blocked completely, like
+/// a sound in another room, and obstruction means only
+/// the direct path to the listener is blocked, like a sound
+/// behind a pillar. Each one is mapped to a volume and
+/// a low-pass filter cutoff frequency. The final volume is
+/// the product of the two volumes, and the final cutoff is
+/// the lower of the two cutoffs.
+#[derive(Debug, Copy, Clone)]
+#[cfg_attr(
+	feature = "serde_support",
+	derive(serde::Serialize, serde::Deserialize),
+	serde(default)
+)]
+pub struct OcclusionSettings {
+	/// The initial occlusion of the instance (from 0 to 1).
+	pub occlusion: f64,
+	/// The initial obstruction of the instance (from 0 to 1).
+	pub obstruction: f64,
+	/// How occlusion maps to the volume of the instance.
+	pub occlusion_volume: Mapping,
+	/// How occlusion maps to the cutoff frequency of the
+	/// low-pass filter (in hertz).
+	pub occlusion_cutoff: Mapping,
+	/// How obstruction maps to the volume of the instance.
+	pub obstruction_volume: Mapping,
+	/// How obstruction maps to the cutoff frequency of the
+	/// low-pass filter (in hertz).
+	pub obstruction_cutoff: Mapping,
+	/// How long it takes for the occlusion and obstruction
+	/// to change most of the way (about 63%) to new values
+	/// (in seconds).
+	pub smoothing: f64,
+}
+
+impl OcclusionSettings {
+	/// Creates a new `OcclusionSettings` with the default settings.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Sets the initial occlusion of the instance (from 0 to 1).
+	pub fn occlusion(self, occlusion: f64) -> Self {
+		Self { occlusion, ..self }
+	}
+
+	/// Sets the initial obstruction of the instance (from 0 to 1).
+	pub fn obstruction(self, obstruction: f64) -> Self {
+		Self {
+			obstruction,
+			..self
+		}
+	}
+
+	/// Sets how occlusion maps to the volume of the instance.
+	pub fn occlusion_volume(self, occlusion_volume: Mapping) -> Self {
+		Self {
+			occlusion_volume,
+			..self
+		}
+	}
+
+	/// Sets how occlusion maps to the cutoff frequency of the
+	/// low-pass filter (in hertz).
+	pub fn occlusion_cutoff(self, occlusion_cutoff: Mapping) -> Self {
+		Self {
+			occlusion_cutoff,
+			..self
+		}
+	}
+
+	/// Sets how obstruction maps to the volume of the instance.
+	pub fn obstruction_volume(self, obstruction_volume: Mapping) -> Self {
+		Self {
+			obstruction_volume,
+			..self
+		}
+	}
+
+	/// Sets how obstruction maps to the cutoff frequency of the
+	/// low-pass filter (in hertz).
+	pub fn obstruction_cutoff(self, obstruction_cutoff: Mapping) -> Self {
+		Self {
+			obstruction_cutoff,
+			..self
+		}
+	}
+
+	/// Sets how long it takes for the occlusion and obstruction
+	/// to change most of the way to new values (in seconds).
+	pub fn smoothing(self, smoothing: f64) -> Self {
+		Self { smoothing, ..self }
+	}
+}
+
+impl Default for OcclusionSettings {
+	fn default() -> Self {
+		Self {
+			occlusion: 0.0,
+			obstruction: 0.0,
+			occlusion_volume: Mapping {
+				output_range: (1.0, 0.3),
+				..Default::default()
+			},
+			occlusion_cutoff: Mapping {
+				output_range: (20000.0, 1000.0),
+				..Default::default()
+			},
+			obstruction_volume: Mapping {
+				output_range: (1.0, 0.7),
+				..Default::default()
+			},
+			obstruction_cutoff: Mapping {
+				output_range: (20000.0, 2000.0),
+				..Default::default()
+			},
+			smoothing: 0.05,
+		}
+	}
+}
+
+/// The occlusion and obstruction of an instance on the audio thread.
+#[derive(Debug, Clone)]
+pub(crate) struct Occlusion {
+	settings: OcclusionSettings,
+	occlusion: f64,
+	obstruction: f64,
+	/// The low-pass filter, or `None` if the instance
+	/// isn't occluded or obstructed.
+	filter: Option<Filter>,
+}
+
+impl Occlusion {
+	pub fn new(settings: OcclusionSettings) -> Self {
+		let occlusion = settings.occlusion.clamp(0.0, 1.0);
+		let obstruction = settings.obstruction.clamp(0.0, 1.0);
+		Self {
+			settings: OcclusionSettings {
+				occlusion,
+				obstruction,
+				..settings
+			},
+			occlusion,
+			obstruction,
+			filter: None,
+		}
+	}
+
+	pub fn set_occlusion(&mut self, occlusion: f64) {
+		self.settings.occlusion = occlusion.clamp(0.0, 1.0);
+	}
+
+	pub fn set_obstruction(&mut self, obstruction: f64) {
+		self.settings.obstruction = obstruction.clamp(0.0, 1.0);
+	}
+
+	/// Returns the volume of the instance from its occlusion
+	/// and obstruction.
+	pub fn volume(&self) -> f64 {
+		let occlusion_volume = self.settings.occlusion_volume.map(self.occlusion);
+		let obstruction_volume = self.settings.obstruction_volume.map(self.obstruction);
+		(occlusion_volume * obstruction_volume).max(0.0)
+	}
+
+	fn cutoff(&self) -> f64 {
+		self.settings
+			.occlusion_cutoff
+			.map(self.occlusion)
+			.min(self.settings.obstruction_cutoff.map(self.obstruction))
+	}
+
+	pub fn update(&mut self, dt: f64) {
+		let amount = smoothing_amount(dt, self.settings.smoothing);
+		self.occlusion += (self.settings.occlusion - self.occlusion) * amount;
+		self.obstruction += (self.settings.obstruction - self.obstruction) * amount;
+		let bypass = self.settings.occlusion == 0.0
+			&& self.settings.obstruction == 0.0
+			&& self.occlusion < FILTER_BYPASS_THRESHOLD
+			&& self.obstruction < FILTER_BYPASS_THRESHOLD;
+		if bypass {
+			self.occlusion = 0.0;
+			self.obstruction = 0.0;
+			self.filter = None;
+		} else {
+			let settings = FilterSettings::new().cutoff(self.cutoff());
+			match &mut self.filter {
+				Some(filter) => filter.set_settings(settings),
+				None => self.filter = Some(Filter::new(settings)),
+			}
+		}
+	}
+
+	pub fn process(&mut self, dt: f64, input: Frame, parameters: &Parameters) -> Frame {
+		match &mut self.filter {
+			Some(filter) => filter.process(dt, input, parameters),
+			None => input,
+		}
+	}
+}
@@ -0,0 +1,89 @@
+use basedrop::Owned;
+
+use crate::{
+	command::SpatialCommand, manager::AudioManagerSettings,
+	static_container::index_map::StaticIndexMap,
+};
+
+use super::{hrtf::HrtfDataset, Listener, ListenerId, ListenerPolicy, ListenerSettings};
+
+/// The listeners and HRTF dataset on the audio thread.
+pub(crate) struct SpatialScene {
+	listeners: StaticIndexMap<ListenerId, Listener>,
+	listener_policy: ListenerPolicy,
+	listener_smoothing: f64,
+	speed_of_sound: f64,
+	hrtf_dataset: Option<Owned<HrtfDataset>>,
+}
+
+impl SpatialScene {
+	pub fn new(settings: &AudioManagerSettings) -> Self {
+		let mut listeners = StaticIndexMap::new(settings.num_listeners.max(1));
+		listeners
+			.try_insert(
+				ListenerId::main(),
+				Listener::new(ListenerSettings::default(), settings.listener_smoothing),
+			)
+			.ok();
+		Self {
+			listeners,
+			listener_policy: settings.listener_policy,
+			listener_smoothing: settings.listener_smoothing,
+			speed_of_sound: settings.speed_of_sound,
+			hrtf_dataset: None,
+		}
+	}
+
+	pub fn listeners(&self) -> impl Iterator<Item = &Listener> {
+		self.listeners.iter().map(|(_, listener)| listener)
+	}
+
+	pub fn listener_policy(&self) -> ListenerPolicy {
+		self.listener_policy
+	}
+
+	pub fn speed_of_sound(&self) -> f64 {
+		self.speed_of_sound
+	}
+
+	pub fn hrtf_dataset(&self) -> Option<&HrtfDataset> {
+		self.hrtf_dataset.as_deref()
+	}
+
+	pub fn run_command(&mut self, command: SpatialCommand) {
+		match command {
+			SpatialCommand::AddListener(id, settings) => {
+				self.listeners
+					.try_insert(id, Listener::new(settings, self.listener_smoothing))
+					.ok();
+			}
+			SpatialCommand::RemoveListener(id) => {
+				self.listeners.remove(&id);
+			}
+			SpatialCommand::SetListenerPosition(id, position) => {
+				if let Some(listener) = self.listeners.get_mut(&id) {
+					listener.set_position(position);
+				}
+			}
+			SpatialCommand::SetListenerVelocity(id, velocity) => {
+				if let Some(listener) = self.listeners.get_mut(&id) {
+					listener.set_velocity(velocity);
+				}
+			}
+			SpatialCommand::SetListenerOrientation(id, forward, up) => {
+				if let Some(listener) = self.listeners.get_mut(&id) {
+					listener.set_orientation(forward, up);
+				}
+			}
+			SpatialCommand::SetHrtfDataset(dataset) => {
+				self.hrtf_dataset = dataset;
+			}
+		}
+	}
+
+	pub fn update(&mut self, dt: f64) {
+		for (_, listener) in &mut self.listeners {
+			listener.update(dt);
+		}
+	}
+}
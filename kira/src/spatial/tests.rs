@@ -0,0 +1,109 @@
+use crate::Frame;
+
+use super::{
+	hrtf::{HrtfDataset, HrtfMeasurement, HrtfState},
+	Vec3,
+};
+
+fn measurement(azimuth: f64, left: f32, right: f32) -> HrtfMeasurement {
+	HrtfMeasurement {
+		azimuth,
+		elevation: 0.0,
+		left: vec![left],
+		right: vec![right],
+	}
+}
+
+fn create_test_dataset() -> HrtfDataset {
+	HrtfDataset::new(
+		48000,
+		vec![
+			measurement(0.0, 0.5, 0.5),
+			measurement(90.0, 0.0, 1.0),
+			measurement(-90.0, 1.0, 0.0),
+		],
+	)
+}
+
+fn render(dataset: &HrtfDataset, direction: Vec3) -> Frame {
+	let mut state = HrtfState::new();
+	state.process(dataset, direction, 1.0)
+}
+
+#[test]
+fn hrtf_uses_measured_directions() {
+	let dataset = create_test_dataset();
+	assert_eq!(
+		render(&dataset, Vec3::new(1.0, 0.0, 0.0)),
+		Frame::new(0.0, 1.0)
+	);
+	assert_eq!(
+		render(&dataset, Vec3::new(-1.0, 0.0, 0.0)),
+		Frame::new(1.0, 0.0)
+	);
+	assert_eq!(
+		render(&dataset, Vec3::new(0.0, 0.0, 1.0)),
+		Frame::new(0.5, 0.5)
+	);
+}
+
+#[test]
+fn hrtf_interpolates_between_measured_directions() {
+	let dataset = create_test_dataset();
+	// 45 degrees from the front and right measurements
+	// and 135 degrees from the left measurement
+	let out = render(&dataset, Vec3::new(1.0, 0.0, 1.0));
+	assert!((out.left - 2.5 / 7.0).abs() < 0.0001);
+	assert!((out.right - 4.5 / 7.0).abs() < 0.0001);
+}
+
+#[test]
+fn hrtf_crossfades_when_the_direction_changes() {
+	let dataset = create_test_dataset();
+	let mut state = HrtfState::new();
+	state.process(&dataset, Vec3::new(1.0, 0.0, 0.0), 1.0);
+	for _ in 0..63 {
+		state.process(&dataset, Vec3::new(1.0, 0.0, 0.0), 1.0);
+	}
+	let out = state.process(&dataset, Vec3::new(-1.0, 0.0, 0.0), 1.0);
+	assert_eq!(out, Frame::new(0.0, 1.0));
+	let mut out = out;
+	for _ in 0..63 {
+		out = state.process(&dataset, Vec3::new(-1.0, 0.0, 0.0), 1.0);
+	}
+	assert!(out.left > 0.9 && out.right < 0.1);
+}
+
+#[test]
+#[cfg(feature = "wav")]
+fn hrtf_datasets_load_from_directories() {
+	use super::hrtf::HrtfDatasetFromDirError;
+
+	let dir = std::env::temp_dir().join(format!("kira-hrtf-test-{}", std::process::id()));
+	std::fs::create_dir_all(&dir).unwrap();
+	let spec = hound::WavSpec {
+		channels: 2,
+		sample_rate: 48000,
+		bits_per_sample: 32,
+		sample_format: hound::SampleFormat::Float,
+	};
+	for (name, left, right) in [("0_0", 0.5f32, 0.5f32), ("-90_0", 1.0, 0.0)].iter() {
+		let mut writer = hound::WavWriter::create(dir.join(format!("{}.wav", name)), spec).unwrap();
+		writer.write_sample(*left).unwrap();
+		writer.write_sample(*right).unwrap();
+		writer.finalize().unwrap();
+	}
+	let dataset = HrtfDataset::from_dir(&dir).unwrap();
+	assert_eq!(dataset.sample_rate(), 48000);
+	assert_eq!(dataset.num_measurements(), 2);
+	assert_eq!(
+		render(&dataset, Vec3::new(-1.0, 0.0, 0.0)),
+		Frame::new(1.0, 0.0)
+	);
+	std::fs::write(dir.join("readme.txt"), "not an impulse response").unwrap();
+	assert!(matches!(
+		HrtfDataset::from_dir(&dir),
+		Err(HrtfDatasetFromDirError::InvalidFileName(_))
+	));
+	std::fs::remove_dir_all(&dir).unwrap();
+}
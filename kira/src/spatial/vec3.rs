@@ -0,0 +1,119 @@
+use std::ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign};
+
+/// A point or direction in 3D space.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+#[cfg_attr(
+	feature = "serde_support",
+	derive(serde::Serialize, serde::Deserialize)
+)]
+pub struct Vec3 {
+	/// The x component of the vector.
+	pub x: f64,
+	/// The y component of the vector.
+	pub y: f64,
+	/// The z component of the vector.
+	pub z: f64,
+}
+
+impl Vec3 {
+	/// A vector with all components set to 0.
+	pub const ZERO: Self = Self::new(0.0, 0.0, 0.0);
+
+	/// Creates a new vector.
+	pub const fn new(x: f64, y: f64, z: f64) -> Self {
+		Self { x, y, z }
+	}
+
+	/// Returns the dot product of this vector and another vector.
+	pub fn dot(self, other: Self) -> f64 {
+		self.x * other.x + self.y * other.y + self.z * other.z
+	}
+
+	/// Returns the cross product of this vector and another vector.
+	pub fn cross(self, other: Self) -> Self {
+		Self::new(
+			self.y * other.z - self.z * other.y,
+			self.z * other.x - self.x * other.z,
+			self.x * other.y - self.y * other.x,
+		)
+	}
+
+	/// Returns the length of the vector.
+	pub fn length(self) -> f64 {
+		self.dot(self).sqrt()
+	}
+
+	/// Returns a vector pointing in the same direction with
+	/// a length of 1, or a zero vector if this vector has
+	/// a length of 0.
+	pub fn normalized(self) -> Self {
+		let length = self.length();
+		if length == 0.0 {
+			Self::ZERO
+		} else {
+			self * (1.0 / length)
+		}
+	}
+
+	/// Moves the vector towards another vector by a fraction
+	/// of the distance between them.
+	pub(crate) fn lerp(self, other: Self, amount: f64) -> Self {
+		self + (other - self) * amount
+	}
+}
+
+impl From<[f64; 3]> for Vec3 {
+	fn from(components: [f64; 3]) -> Self {
+		Self::new(components[0], components[1], components[2])
+	}
+}
+
+impl From<(f64, f64, f64)> for Vec3 {
+	fn from((x, y, z): (f64, f64, f64)) -> Self {
+		Self::new(x, y, z)
+	}
+}
+
+impl Add for Vec3 {
+	type Output = Self;
+
+	fn add(self, rhs: Self) -> Self::Output {
+		Self::new(self.x + rhs.x, self.y + rhs.y, self.z + rhs.z)
+	}
+}
+
+impl AddAssign for Vec3 {
+	fn add_assign(&mut self, rhs: Self) {
+		*self = *self + rhs;
+	}
+}
+
+impl Sub for Vec3 {
+	type Output = Self;
+
+	fn sub(self, rhs: Self) -> Self::Output {
+		Self::new(self.x - rhs.x, self.y - rhs.y, self.z - rhs.z)
+	}
+}
+
+impl SubAssign for Vec3 {
+	fn sub_assign(&mut self, rhs: Self) {
+		*self = *self - rhs;
+	}
+}
+
+impl Mul<f64> for Vec3 {
+	type Output = Self;
+
+	fn mul(self, rhs: f64) -> Self::Output {
+		Self::new(self.x * rhs, self.y * rhs, self.z * rhs)
+	}
+}
+
+impl Neg for Vec3 {
+	type Output = Self;
+
+	fn neg(self) -> Self::Output {
+		Self::new(-self.x, -self.y, -self.z)
+	}
+}
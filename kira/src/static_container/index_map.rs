@@ -28,14 +28,26 @@ impl<K: Eq + Hash, V> StaticIndexMap<K, V> {
 		self.index_map.capacity()
 	}
 
+	pub fn contains_key(&self, key: &K) -> bool {
+		self.index_map.contains_key(key)
+	}
+
 	pub fn get(&self, key: &K) -> Option<&V> {
 		self.index_map.get(key)
 	}
 
+	pub fn get_index_of(&self, key: &K) -> Option<usize> {
+		self.index_map.get_index_of(key)
+	}
+
 	pub fn get_index(&self, index: usize) -> Option<(&K, &V)> {
 		self.index_map.get_index(index)
 	}
 
+	pub fn get_index_mut(&mut self, index: usize) -> Option<(&mut K, &mut V)> {
+		self.index_map.get_index_mut(index)
+	}
+
 	pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
 		self.index_map.get_mut(key)
 	}